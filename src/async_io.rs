@@ -0,0 +1,44 @@
+use std::io;
+use std::path::Path;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use crate::{Encoding, PreprocessedFile};
+use crate::codemap::named;
+
+impl PreprocessedFile<String>
+{
+    /// Opens a preprocessed file asynchronously.
+    ///
+    /// This is the asynchronous counterpart of [`PreprocessedFile::open`].
+    pub async fn open_async<P: AsRef<Path>>(filename: P) -> Result<Self, io::Error>
+    {
+        let buf = tokio::fs::read(&filename).await?;
+        PreprocessedFile::load(named(filename.as_ref(), &Encoding::Utf8.decode(buf)?))
+    }
+
+    /// Reads a preprocessed text from an asynchronous reader.
+    ///
+    /// This is the asynchronous counterpart of [`PreprocessedFile::from_reader`].
+    pub async fn from_async_reader<R: AsyncRead + Unpin>(mut reader: R, size_hint: Option<usize>) -> Result<Self, io::Error>
+    {
+        let mut buf = Vec::with_capacity(size_hint.unwrap_or_default());
+        reader.read_to_end(&mut buf).await?;
+        let contents = Encoding::Utf8.decode(buf)?;
+        PreprocessedFile::load(contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use codespan_reporting::files::Files;
+    use crate::{EasyLocation, PreprocessedFile};
+
+    #[tokio::test]
+    async fn async_loading()
+    {
+        let file = PreprocessedFile::open_async("examples/readme.rs").await.unwrap();
+        assert_eq!(file.name(file.file_id(131)).unwrap(), "examples/readme.rs");
+
+        let file = PreprocessedFile::from_async_reader(&b"#line 3 \"r.c\"\nx\n"[..], None).await.unwrap();
+        assert_eq!(file.name(file.file_id(14)).unwrap(), "r.c");
+    }
+}
@@ -0,0 +1,206 @@
+use std::borrow::Cow;
+use crate::{CodemapError, PreprocessedFile};
+use crate::codemap::remap;
+use crate::columns::Columns;
+use crate::directive::Syntax;
+
+type RenameHook = Box<dyn for<'n> Fn(&'n str) -> Cow<'n, str> + Send + Sync>;
+
+/// A builder to customize the construction of a [`PreprocessedFile`].
+///
+/// # Example
+/// ```
+/// use codespan_reporting::files::Files;
+/// use codespan_preprocessed::{EasyLocation, PreprocessedFile};
+///
+/// let file = PreprocessedFile::builder()
+///     .rename(|name| name.to_uppercase().into())
+///     .build("#line 1 \"top_file\"\nfirst line\n");
+/// assert_eq!(file.name(file.file_id(20)).unwrap(), "TOP_FILE");
+/// ```
+#[derive(Default)]
+pub struct PreprocessedFileBuilder {
+    syntax: Syntax,
+    rename: Option<RenameHook>,
+    remaps: Vec<(String,String)>,
+    columns: Columns,
+    qualified_names: bool,
+    default_name: Option<String>,
+}
+
+impl PreprocessedFileBuilder {
+
+    #[inline]
+    pub fn new() -> Self { Self::default() }
+
+    /// Enables the column adjustment directive with the given keyword.
+    ///
+    /// A line such as `#column 4` (with `"#column"` as keyword) shifts
+    /// the reported columns of the following lines by the given amount,
+    /// up to the next directive. It does not break the line numbering.
+    #[inline]
+    pub fn column_directive(mut self, keyword: impl Into<String>) -> Self
+    {
+        self.syntax.column_directive = Some(keyword.into());
+        self
+    }
+
+    /// Enables the compatibility with the Fortran preprocessors.
+    ///
+    /// The directives could be preceded by blanks (as in fixed-form
+    /// sources) and their keyword is case-insensitive (`#LINE`).
+    #[inline]
+    pub fn fortran_compatible(mut self, enabled: bool) -> Self
+    {
+        self.syntax.fortran = enabled;
+        self
+    }
+
+    /// Enables the tolerant parsing of the directives.
+    ///
+    /// This is designed for generated code (as bison or flex outputs):
+    /// directives could be indented, any blanks could separate the arguments,
+    /// trailing characters (as `\r` of CRLF line endings) are ignored and
+    /// the escaped characters of the filenames (as Windows path separators)
+    /// are unescaped for display.
+    #[inline]
+    pub fn tolerant(mut self, enabled: bool) -> Self
+    {
+        self.syntax.tolerant = enabled;
+        self
+    }
+
+    /// Sets a hook to transform the file names of the directives.
+    ///
+    /// The hook is called once for each distinct file name (canonicalize,
+    /// relativize, anonymize...) before the path remapping, even if the
+    /// file is entered by many directives (but again for each chunk of a
+    /// [`PreprocessedStream`](crate::PreprocessedStream)). The original
+    /// names remain available through [`PreprocessedFile::raw_name`].
+    /// ```
+    /// use std::sync::Arc;
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use codespan_preprocessed::PreprocessedFile;
+    ///
+    /// let calls = Arc::new(AtomicUsize::new(0));
+    /// let counter = calls.clone();
+    /// let file = PreprocessedFile::builder()
+    ///     .rename(move |name| { counter.fetch_add(1, Ordering::SeqCst); name.to_uppercase().into() })
+    ///     .build("#line 1 \"a.c\"\na\n#line 1 \"b.h\"\nb\n#line 2 \"a.c\"\nc\n");
+    /// assert_eq!(calls.load(Ordering::SeqCst), 2);
+    /// ```
+    #[inline]
+    pub fn rename<F>(mut self, hook: F) -> Self
+        where F: for<'n> Fn(&'n str) -> Cow<'n, str> + Send + Sync + 'static
+    {
+        self.rename = Some(Box::new(hook));
+        self
+    }
+
+    /// Adds a path prefix remapping (see [`PreprocessedFile::with_path_remap`]).
+    #[inline]
+    pub fn path_remap(mut self, from: impl Into<String>, to: impl Into<String>) -> Self
+    {
+        self.remaps.push((from.into(), to.into()));
+        self
+    }
+
+    /// Enables the strict mode: the invalid directives are
+    /// reported as errors (see [`PreprocessedFileBuilder::try_build`]).
+    #[inline]
+    pub fn strict(mut self, enabled: bool) -> Self
+    {
+        self.syntax.strict = enabled;
+        self
+    }
+
+    /// Counts the reported columns in display width
+    /// (see [`PreprocessedFile::with_unicode_columns`]).
+    #[inline]
+    pub fn unicode_columns(mut self, enabled: bool) -> Self
+    {
+        self.columns.unicode = enabled;
+        self
+    }
+
+    /// Sets the tab width used to count the reported columns
+    /// (see [`PreprocessedFile::with_tab_width`]).
+    #[inline]
+    pub fn tab_width(mut self, width: usize) -> Self
+    {
+        self.columns.tab_width = Some(width);
+        self
+    }
+
+    /// Qualifies the names of the included files with their include chain
+    /// (see [`PreprocessedFile::with_qualified_names`]).
+    #[inline]
+    pub fn qualified_names(mut self, enabled: bool) -> Self
+    {
+        self.qualified_names = enabled;
+        self
+    }
+
+    /// Sets the name of the slices which are not named by a directive
+    /// (see [`PreprocessedFile::with_default_name`]).
+    #[inline]
+    pub fn default_name(mut self, name: impl Into<String>) -> Self
+    {
+        self.default_name = Some(name.into());
+        self
+    }
+
+    /// Builds the codemap of the given contents.
+    ///
+    /// # Panics
+    /// In strict mode, if some directive is invalid (or if the source
+    /// is too large, see [`PreprocessedFile::new`]).
+    #[inline]
+    pub fn build<S:AsRef<str>>(&self, contents: S) -> PreprocessedFile<S>
+    {
+        self.try_build(contents).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Builds the codemap of the given contents or fails
+    /// if some directive is invalid (in strict mode).
+    pub fn try_build<S:AsRef<str>>(&self, contents: S) -> Result<PreprocessedFile<S>, CodemapError>
+    {
+        let mut file = PreprocessedFile::unsliced(contents);
+        file.columns = self.columns;
+        file.origin = self.default_name.clone();
+        self.extend(&mut file, 0)?;
+        Ok(file)
+    }
+
+    /// Slices and names the contents of the codemap from the given line start
+    /// (the previous contents being already processed, see [`crate::PreprocessedStream`]).
+    pub(crate) fn extend<S:AsRef<str>>(&self, file: &mut PreprocessedFile<S>, from: usize) -> Result<(), CodemapError>
+    {
+        let first_alias = file.aliases.len();
+        let first = file.slice_from(from, &self.syntax)?;
+        if let Some(hook) = &self.rename {
+            file.rename_slices(first, false, |name| match hook(name) {
+                Cow::Borrowed(renamed) if renamed == name => None,
+                renamed => Some(renamed.into_owned())
+            });
+        }
+        for (from, to) in &self.remaps {
+            file.rename_slices(first, true, remap(from, to));
+        }
+        if self.qualified_names {
+            file.qualify_slices(first, true);
+        }
+        file.prune_aliases_from(first, first_alias);
+        Ok(())
+    }
+}
+
+impl PreprocessedFile<String>
+{
+    /// Gets a builder to customize the codemap construction.
+    ///
+    /// The builder is not tied to the type of the contents
+    /// (see [`PreprocessedFileBuilder::build`]).
+    #[inline]
+    pub fn builder() -> PreprocessedFileBuilder { PreprocessedFileBuilder::new() }
+}
@@ -1,10 +1,15 @@
 use std::ops::{Range, Index};
-use std::iter;
 use codespan_reporting::files;
 use codespan_reporting::files::{Files, SimpleFile};
 use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::io::Read;
 use std::path::Path;
+use std::sync::Arc;
+use crate::{CodemapError, Encoding};
+use crate::columns::Columns;
+use crate::lines::{check_len, LineTable};
+use crate::directive::{Syntax, unescape, ENTER, RETURN, SYSTEM};
 
 
 #[derive(Clone, Debug)]
@@ -12,6 +17,8 @@ struct LineDirective {
     line_index: usize,
     byte_index: usize,
     offset: isize,
+    column: isize,
+    flags: u8,
     filename: Option<Range<usize>>
 }
 
@@ -23,21 +30,139 @@ struct LineDirective {
 /// `codespan_reporting`.
 #[derive(Clone, Debug, PartialEq)]
 pub struct FileSlice {
-    name: Range<usize>,
-    bytes: Range<usize>,
-    lines: Range<usize>,
-    offset: isize
+    pub(crate) name: Range<usize>,
+    pub(crate) alias: Option<usize>,
+    pub(crate) remapped: bool,
+    pub(crate) qualified: Option<usize>,
+    pub(crate) bytes: Range<usize>,
+    pub(crate) lines: Range<usize>,
+    pub(crate) offset: isize,
+    pub(crate) column: isize,
+    pub(crate) parent: Option<usize>,
+    pub(crate) flags: u8
 }
 
+/// The displayed names of the slices (renamed or qualified),
+/// each one stored once.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Aliases {
+    names: Vec<String>,
+    index: HashMap<String, usize>
+}
+
+impl Aliases {
+    /// Stores a name (only once) and gets its index.
+    pub(crate) fn intern(&mut self, name: String) -> usize
+    {
+        match self.index.get(&name) {
+            Some(&i) => i,
+            None => {
+                self.index.insert(name.clone(), self.names.len());
+                self.names.push(name);
+                self.names.len() - 1
+            }
+        }
+    }
+
+    #[inline]
+    pub(crate) fn len(&self) -> usize { self.names.len() }
+
+    /// Removes the names from the given index.
+    pub(crate) fn split_off(&mut self, at: usize) -> Vec<String>
+    {
+        let names = self.names.split_off(at);
+        names.iter().for_each(|name| { self.index.remove(name); });
+        names
+    }
+
+    #[inline]
+    pub(crate) fn iter(&self) -> impl Iterator<Item=&String> { self.names.iter() }
+}
+
+impl Index<usize> for Aliases {
+    type Output = String;
+
+    #[inline]
+    fn index(&self, i: usize) -> &String { &self.names[i] }
+}
+
+impl From<Vec<String>> for Aliases {
+    fn from(names: Vec<String>) -> Self
+    {
+        let index = names.iter().enumerate().map(|(i, name)| (name.clone(), i)).collect();
+        Aliases { names, index }
+    }
+}
+
+/// Node of the include tree of a preprocessed file.
+#[derive(Clone, Debug)]
+pub struct IncludeNode<'a> {
+    /// The slice of the node
+    pub slice: &'a FileSlice,
+    /// The slices of the files included by this one
+    pub children: Vec<IncludeNode<'a>>
+}
+
+/// Error of a span which is not included in a single file slice.
+///
+/// The span crosses a location directive: its start and its
+/// end are not located in the same original file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpanCrossesFiles {
+    /// The invalid span
+    pub span: Range<usize>,
+    /// The end of the slice containing the start of the span
+    pub boundary: usize
+}
+
+impl std::fmt::Display for SpanCrossesFiles {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "span {}..{} crosses a file boundary at byte {}",
+               self.span.start, self.span.end, self.boundary)
+    }
+}
+
+impl std::error::Error for SpanCrossesFiles {}
+
 /// The codemap of a preprocessed file.
-#[derive(Debug)]
+///
+/// # Sharing the codemap
+/// Cloning a codemap clones its tables and its source. To share
+/// a codemap between threads without duplicating the source text,
+/// use an `Arc<str>` as source (see [`PreprocessedFile::into_shared`]):
+/// ```
+/// use std::sync::Arc;
+/// use codespan_preprocessed::PreprocessedFile;
+///
+/// let file: PreprocessedFile<Arc<str>> = PreprocessedFile::new("#line 1 \"a\"\nfirst\n").into_shared();
+/// let clone = file.clone();
+/// std::thread::spawn(move || assert_eq!(clone.len(), 18)).join().unwrap();
+/// assert!(std::ptr::eq(file.source(), file.clone().source()));
+/// ```
+#[derive(Clone, Debug)]
 pub struct PreprocessedFile<Source> {
-    ids: Vec<FileSlice>,
-    lines: Vec<Range<usize>>,
-    contents: Source
+    pub(crate) ids: Vec<FileSlice>,
+    pub(crate) lines: LineTable,
+    pub(crate) contents: Source,
+    pub(crate) origin: Option<String>,
+    pub(crate) aliases: Aliases,
+    pub(crate) columns: Columns
 }
 
 
+/// Replaces the `from` prefix of a name by `to`.
+pub(crate) fn remap<'r>(from: &'r str, to: &'r str) -> impl Fn(&str) -> Option<String> + 'r
+{
+    move |name| name.strip_prefix(from).map(|suffix| format!("{}{}", to, suffix))
+}
+
+/// Prepends a `#line` directive to the contents of a file
+/// to correctly locate the diagnostics on its lines.
+pub(crate) fn named(filename: &Path, contents: &str) -> String
+{
+    format!("#line 1 \"{}\"\n{}", filename.to_string_lossy(), contents)
+}
+
 impl<'a, S:'a+AsRef<str>> Files<'a> for PreprocessedFile<S>
 {
     type FileId = &'a FileSlice;
@@ -45,7 +170,10 @@ impl<'a, S:'a+AsRef<str>> Files<'a> for PreprocessedFile<S>
     type Source = &'a str;
 
     fn name(&'a self, id: Self::FileId) -> Result<Self::Name, files::Error> {
-        Ok(self.contents.as_ref().index(id.name.clone()))
+        match id.qualified {
+            Some(qualified) => Ok(self.aliases[qualified].as_str()),
+            None => Ok(self.display_name(id))
+        }
     }
 
     fn source(&'a self, _: Self::FileId) -> Result<Self::Source, files::Error> {
@@ -54,130 +182,562 @@ impl<'a, S:'a+AsRef<str>> Files<'a> for PreprocessedFile<S>
 
     fn line_index(&'a self, id: Self::FileId, byte_index: usize) -> Result<usize, files::Error>
     {
-        if id.bytes.end <= byte_index {
+        if id.lines.is_empty() {
+            // empty slice of a directive at the end of the source:
+            // located on the (empty) line announced by the directive
+            Ok((id.lines.start as isize - id.offset) as usize)
+        } else if id.bytes.end <= byte_index {
             Ok((id.lines.end as isize -1 -id.offset) as usize)
         } else if byte_index < id.bytes.start {
             Err(files::Error::FileMissing)
         } else {
-            Ok((self.lines.binary_search_by(|bytes| {
-                if byte_index < bytes.start { Ordering::Greater } else if byte_index > bytes.end { Ordering::Less } else { Ordering::Equal }
-            }).unwrap() as isize - id.offset) as usize)
+            // the search is restricted to the lines of the slice
+            Ok((self.lines.line_within(byte_index, id.lines.clone()) as isize - id.offset) as usize)
         }
     }
 
     fn line_range(&'a self, id: Self::FileId, line_index: usize) -> Result<Range<usize>, files::Error>
     {
-        self.lines.get((line_index as isize+id.offset) as usize).cloned()
+        let line = (line_index as isize+id.offset) as usize;
+        if id.lines.is_empty() && line == self.lines.len() {
+            return Ok(self.lines.end()..self.lines.end());
+        }
+        self.lines.get(line)
             .ok_or(files::Error::LineTooLarge { given: line_index, max: self.lines.len() })
     }
+
+    fn column_number(&'a self, id: Self::FileId, line_index: usize, byte_index: usize) -> Result<usize, files::Error>
+    {
+        let line_range = self.line_range(id, line_index)?;
+        let column = self.columns.index(self.contents.as_ref(), line_range, byte_index) as isize;
+        // the column adjustment of the slice could not lead before the first column
+        Ok((column + id.column).max(0) as usize + 1)
+    }
 }
 
 impl<Source> PreprocessedFile<Source>
     where
         Source: AsRef<str>
 {
+    /// Builds the codemap of a preprocessed source (in permissive mode).
+    ///
+    /// The out of range line numbers are clamped and the malformed
+    /// directives are left as ordinary text (see [`PreprocessedFile::try_new`]).
+    ///
+    /// # Panics
+    /// With the `compact` feature, if the source is 4 GiB or more
+    /// (the loading functions, as [`PreprocessedFile::from_reader`], fail instead).
+    #[inline]
     pub fn new(contents: Source) -> Self
     {
-        let mut line_endings = contents
-            .as_ref()
-            .match_indices('\n')
-            .map(|(b,_)| b )
-            .collect::<Vec<_>>();
+        Self::with_syntax(contents, &Syntax::default())
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Builds the codemap of a loaded text (in permissive mode),
+    /// the too large sources being reported as invalid data.
+    pub(crate) fn load(contents: Source) -> Result<Self, std::io::Error>
+    {
+        Self::with_syntax(contents, &Syntax::default())
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+    }
+
+    /// Builds the codemap of a preprocessed source (in strict mode) or fails
+    /// if some directive is invalid: malformed or with an out of range line number
+    /// (or if the source is too large, see [`PreprocessedFile::new`]).
+    /// ```
+    /// use codespan_preprocessed::{CodemapError, PreprocessedFile};
+    ///
+    /// let source = "#line 1 \"a.c\"\n#line \"b.c\"\nint a;\n";
+    /// assert_eq!(PreprocessedFile::try_new(source).unwrap_err(), CodemapError::MalformedDirective { directive: 14..25 });
+    /// // the malformed directive is a line of "a.c"
+    /// assert_eq!(PreprocessedFile::new(source).include_tree().len(), 1);
+    /// ```
+    #[inline]
+    pub fn try_new(contents: Source) -> Result<Self, CodemapError>
+    {
+        Self::with_syntax(contents, &Syntax { strict: true, ..Syntax::default() })
+    }
 
-        // if the last line is not terminated with an EOL, assume it
-        match line_endings.last() {
-            // nothing to do,the last line will have an EOL
-            Some(l) if *l == contents.as_ref().len() - 1 => { }
-            // the data has no EOL at the end...
-            _ => line_endings.push(contents.as_ref().len() )
+    pub(crate) fn with_syntax(contents: Source, syntax: &Syntax) -> Result<Self, CodemapError>
+    {
+        let mut file = Self::unsliced(contents);
+        file.slice_from(0, syntax)?;
+        file.prune_aliases();
+        Ok(file)
+    }
+
+    /// Gets a codemap of the source which is not sliced yet
+    /// (see [`PreprocessedFile::slice_from`]).
+    pub(crate) fn unsliced(contents: Source) -> Self
+    {
+        PreprocessedFile {
+            ids: vec![],
+            lines: LineTable::default(),
+            contents,
+            origin: None,
+            aliases: Aliases::default(),
+            columns: Columns::default()
         }
+    }
 
-        let line_ranges =
-            iter::once(0)
-                .chain(line_endings.iter().map(|e| *e+1))
-                .zip(line_endings.iter())
-                .map(|(s,e)| s .. *e)
-                .collect::<Vec<_>>();
+    /// Slices the source from the given line start.
+    ///
+    /// The source before this point should be already sliced: the previous
+    /// slices are kept and the last one is extended or ended by the new directives,
+    /// so the lines appended to a source are sliced without rescanning it.
+    /// Returns the index of the first new slice (the names of the new slices
+    /// are not yet renamed, except the escaped ones in tolerant mode).
+    pub(crate) fn slice_from(&mut self, from: usize, syntax: &Syntax) -> Result<usize, CodemapError>
+    {
+        let contents = self.contents.as_ref();
+        check_len(contents.len())?;
+        let candidates = self.lines.scan_from(contents, from, &syntax.markers(), syntax.fortran || syntax.tolerant);
 
+        let mut previous_offset = self.ids.last().map_or(0, |id| id.offset);
+        let lines = &self.lines;
         let directives =
-            line_ranges.iter()
-                .enumerate()
-                .filter(|(_, r)| contents.as_ref()[r.start..r.end].starts_with("#line"))
-                .map(|(l, r)| {
-                    let str = &contents.as_ref()[r.start..r.end];
-                    if let Some(sep) = str[6..].find(' ') {
-                        let sep = sep + 6;
+            candidates.into_iter()
+                .filter_map(|l| {
+                    let r = lines.get(l).unwrap();
+                    let str = &contents[r.start..r.end];
+                    let directive = if let Some((number, filename, flags)) = syntax.line_directive(str) {
+                        let Some(line) = syntax.line_number(number) else {
+                            return Some(Err(CodemapError::LineNumberOutOfRange { directive: r, number }));
+                        };
                         LineDirective {
                             line_index: l,
                             byte_index: r.start,
-                            offset: l as isize + 2 - str[6..sep].parse::<isize>().unwrap(),
-                            filename: Some(r.start+sep+2..r.start+str.len()-1)
+                            offset: l as isize + 2 - line,
+                            column: 0,
+                            flags,
+                            filename: filename.map(|f| r.start+f.start .. r.start+f.end)
                         }
+                    } else if syntax.strict && syntax.is_malformed_directive(str) {
+                        return Some(Err(CodemapError::MalformedDirective { directive: r }));
                     } else {
+                        // the column directive does not break the line numbering,
+                        // only the directive line itself is skipped
+                        let column = syntax.column_directive(str)?;
                         LineDirective {
                             line_index: l,
                             byte_index: r.start,
-                            offset: l as isize + 2 - str[6..].parse::<isize>().unwrap(),
+                            offset: previous_offset + 1,
+                            column,
+                            flags: 0,
                             filename: None
                         }
-                    }
+                    };
+                    previous_offset = directive.offset;
+                    Some(Ok(directive))
                 })
-                .collect::<Vec<_>>();
+                .collect::<Result<Vec<_>,_>>()?;
 
-        let mut current = 0..0;
-        let mut files = Vec::with_capacity(directives.len()+2);
-
-        if let Some(first) = directives.first() {
-            if first.line_index > 0 {
-                files.push(FileSlice {
-                    name: current.clone(),
-                    bytes: 0..first.byte_index,
-                    lines: 0..first.line_index,
-                    offset: 0
-                });
+        // the contents before the first directive are an unnamed slice
+        if self.ids.is_empty() {
+            self.ids.push(FileSlice {
+                name: 0..0,
+                alias: None,
+                remapped: false,
+                qualified: None,
+                bytes: 0..0,
+                lines: 0..0,
+                offset: 0,
+                column: 0,
+                parent: None,
+                flags: 0
+            });
+        }
+        let mut first = self.ids.len();
+        for directive in directives {
+            // the last slice ends at the directive
+            let last = self.ids.last_mut().unwrap();
+            last.lines.end = directive.line_index;
+            last.bytes = self.lines.get(last.lines.start).unwrap().start .. directive.byte_index;
+            if last.lines.start == 0 && last.lines.is_empty() {
+                // no contents before the first directive
+                self.ids.pop();
+                first = first.min(self.ids.len());
             }
-            files.extend(directives.iter()
-                .zip(directives.iter().skip(1))
-                .map(|(start, end)| {
-                    if let Some(filename) = start.filename.clone() {
-                        current = filename;
-                    }
-                    FileSlice {
-                        name: current.clone(),
-                        bytes: line_ranges[start.line_index+1].start .. end.byte_index ,
-                        lines: start.line_index+1 .. end.line_index,
-                        offset: start.offset
-                    }
-                }));
-
-            let last_directive = directives.last().unwrap();
-
-            // if the file ends with a directive (which should never
-            // happen when it comes from m4 or cpp), we ignore this last one
-            // since it will generate out of bounds for line_ranges access
-            if last_directive.line_index+1 < line_ranges.len() {
-                // ok, here, we know that there is some chars behind the directive
-                files.push(FileSlice {
-                    name: last_directive.filename.clone().unwrap_or(current),
-                    bytes: line_ranges[last_directive.line_index+1].start .. line_ranges.last().unwrap().end,
-                    lines: last_directive.line_index+1 .. line_ranges.len(),
-                    offset: last_directive.offset
-                });
+
+            let i = self.ids.len();
+            let previous = self.ids.last();
+            let name = directive.filename.unwrap_or_else(|| previous.map_or(0..0, |p| p.name.clone()));
+            // the include chain is followed with the linemarker flags
+            let parent = if directive.flags & ENTER != 0 && i > 0 {
+                Some(i-1)
+            } else if directive.flags & RETURN != 0 {
+                previous.and_then(|p| p.parent).and_then(|p| self.ids[p].parent)
+            } else {
+                previous.and_then(|p| p.parent)
+            };
+            // a slice without flags continues the system header of the previous one
+            let flags = match previous {
+                Some(previous) if directive.flags == 0 && previous.name == name => previous.flags & SYSTEM,
+                _ => directive.flags
+            };
+            self.ids.push(FileSlice {
+                name,
+                alias: None,
+                remapped: false,
+                qualified: None,
+                bytes: 0..0,
+                lines: directive.line_index+1 .. directive.line_index+1,
+                offset: directive.offset,
+                column: directive.column,
+                parent,
+                flags
+            });
+        }
+
+        // the last slice goes up to the end of the source: if the source
+        // ends with a directive (which should never happen when it comes
+        // from m4 or cpp), the last slice is empty, it starts and ends at
+        // the end of the directive and contains no line (so the bytes of
+        // this directive are located on the directive line itself)
+        let end = self.lines.last().end;
+        let last = self.ids.last_mut().unwrap();
+        last.lines.end = self.lines.len();
+        last.bytes = self.lines.get(last.lines.start).map_or(end, |next| next.start) .. end;
+
+        if syntax.tolerant {
+            self.rename_slices(first, false, |name| name.contains('\\').then(|| unescape(name)));
+        }
+        Ok(first)
+    }
+
+    /// Gets the byte offsets of the line starts.
+    ///
+    /// The offsets are those of the whole preprocessed source (directive
+    /// lines included) and follow the [`files::line_starts`] convention:
+    /// a source terminated by an EOL gets a last (empty) line after it.
+    /// ```
+    /// use codespan_preprocessed::PreprocessedFile;
+    ///
+    /// let file = PreprocessedFile::new("#line 1 \"a\"\nfirst\n");
+    /// let starts = file.line_starts().collect::<Vec<_>>();
+    /// assert_eq!(starts, codespan_reporting::files::line_starts(file.source()).collect::<Vec<_>>());
+    /// ```
+    pub fn line_starts(&self) -> impl Iterator<Item=usize> + '_
+    {
+        let trailing = (self.lines.end() < self.len()).then_some(self.len());
+        self.lines.starts().chain(trailing)
+    }
+
+    /// Dumps the codemap for debugging purpose.
+    ///
+    /// Each slice is printed with its (displayed) name, its byte range,
+    /// its lines (both original and preprocessed ones, 1-based), its offset
+    /// and column adjustment, followed by the raw directive which starts it.
+    pub fn dump<W:std::io::Write>(&self, out: &mut W) -> std::io::Result<()>
+    {
+        writeln!(out, "{} bytes, {} lines, {} slices", self.len(), self.lines.len(), self.ids.len())?;
+        self.ids.iter().enumerate().try_for_each(|(i, id)| {
+            let first = id.lines.start as isize - id.offset + 1;
+            let last = id.lines.end as isize - id.offset;
+            writeln!(out, "#{} {:?} bytes {}..{} lines {}..={} (preprocessed {}..={}) offset {} column {:+}",
+                     i, self.name(id).unwrap_or_default(), id.bytes.start, id.bytes.end,
+                     first, last, id.lines.start+1, id.lines.end, id.offset, id.column)?;
+            match id.lines.start.checked_sub(1).and_then(|l| self.lines.get(l)) {
+                Some(directive) => writeln!(out, "   directive: {}", &self.source()[directive]),
+                None => writeln!(out, "   no directive")
             }
-        } else {
-            files.push(FileSlice {
-                name: current,
-                bytes: 0..line_ranges.last().unwrap().end,
-                lines: 0..line_ranges.len(),
-                offset: 0
-            })
+        })
+    }
+
+    /// Gets the slice containing the inclusion of a slice.
+    ///
+    /// The include chain is computed from the flags of the GCC
+    /// linemarkers (`1` when entering a file, `2` when returning to it):
+    /// the parent of a slice is the slice of the including file which
+    /// precedes the inclusion.
+    #[inline]
+    pub fn parent_of(&self, id: &FileSlice) -> Option<&FileSlice>
+    {
+        id.parent.map(|parent| &self.ids[parent])
+    }
+
+    /// Gets the include chain of a slice: the displayed names of the including
+    /// files and the lines of the inclusions, from the innermost one.
+    pub(crate) fn include_chain<'i>(&'i self, id: &'i FileSlice) -> impl Iterator<Item=(&'i str, usize)>
+    {
+        // the inclusion takes place on the line following the parent slice
+        std::iter::successors(self.parent_of(id), |parent| self.parent_of(parent))
+            .map(|parent| (self.display_name(parent), (parent.lines.end as isize - parent.offset + 1) as usize))
+    }
+
+    /// Checks if a slice comes from a system header.
+    ///
+    /// This is given by the flag `3` of the GCC linemarkers.
+    #[inline]
+    pub fn is_system_header(&self, id: &FileSlice) -> bool
+    {
+        id.flags & SYSTEM != 0
+    }
+
+    /// Gets the include tree of the slices.
+    ///
+    /// The roots are the slices of the main file (those without parent)
+    /// and the children of a slice are the slices of the files it includes.
+    pub fn include_tree(&self) -> Vec<IncludeNode<'_>>
+    {
+        fn children<'a>(ids: &'a [FileSlice], index: &[Vec<usize>], nodes: &[usize]) -> Vec<IncludeNode<'a>>
+        {
+            nodes.iter()
+                .map(|&i| IncludeNode { slice: &ids[i], children: children(ids, index, &index[i]) })
+                .collect()
         }
+        // the children of each slice, then the roots (in the order of the slices)
+        let mut index = vec![vec![]; self.ids.len()+1];
+        self.ids.iter().enumerate()
+            .for_each(|(i, id)| index[id.parent.unwrap_or(self.ids.len())].push(i));
+        let roots = index.pop().unwrap_or_default();
+        children(&self.ids, &index, &roots)
+    }
 
+    /// Converts the source into a shared one.
+    ///
+    /// The tables are kept as is (no rescanning) and the
+    /// clones of the returned codemap share the same source text.
+    pub fn into_shared(self) -> PreprocessedFile<Arc<str>>
+    {
         PreprocessedFile {
-            ids: files,
-            lines: line_ranges,
-            contents
+            ids: self.ids,
+            lines: self.lines,
+            contents: Arc::from(self.contents.as_ref()),
+            origin: self.origin,
+            aliases: self.aliases,
+            columns: self.columns
+        }
+    }
+
+    /// Gets the name of a slice as written in the directive.
+    ///
+    /// This name is not affected by path remapping.
+    pub fn raw_name(&self, id: &FileSlice) -> &str
+    {
+        match &self.origin {
+            // unnamed slices come from the origin of the contents
+            Some(origin) if id.name.is_empty() => origin.as_str(),
+            _ => self.contents.as_ref().index(id.name.clone())
+        }
+    }
+
+    /// Remaps the prefix of the displayed file names.
+    ///
+    /// Each file name starting with `from` is displayed with
+    /// this prefix replaced by `to` (as the `--remap-path-prefix`
+    /// option of `rustc`). Only the first matching remapping applies
+    /// to a name (the remappings are not chained).
+    /// The names written in the directives remain available through
+    /// [`PreprocessedFile::raw_name`].
+    /// ```
+    /// use codespan_reporting::files::Files;
+    /// use codespan_preprocessed::{EasyLocation, PreprocessedFile};
+    ///
+    /// let file = PreprocessedFile::new("#line 1 \"/a/x.c\"\nint x;\n")
+    ///     .with_path_remap("/a", "/b")
+    ///     .with_path_remap("/b", "/c");
+    /// assert_eq!(file.name(file.file_id(16)).unwrap(), "/b/x.c");
+    /// ```
+    pub fn with_path_remap(mut self, from: impl AsRef<str>, to: impl AsRef<str>) -> Self
+    {
+        let (from, to) = (from.as_ref(), to.as_ref());
+        self.rename_with(true, remap(from, to));
+        self
+    }
+
+    /// Renames the displayed names of all the named slices.
+    ///
+    /// The closure returns `None` if the name should be left unchanged.
+    /// A remapping skips the slices already remapped.
+    pub(crate) fn rename_with<F:Fn(&str) -> Option<String>>(&mut self, remapping: bool, rename: F)
+    {
+        self.rename_slices(0, remapping, rename);
+        // the qualified names are built upon the renamed ones
+        if self.ids.iter().any(|id| id.qualified.is_some()) {
+            self.qualify_names(true);
+        } else {
+            self.prune_aliases();
+        }
+    }
+
+    /// Renames the displayed names of the named slices from the given one
+    /// (the unused names are kept and the qualified names are not rebuilt).
+    pub(crate) fn rename_slices<F:Fn(&str) -> Option<String>>(&mut self, first: usize, remapping: bool, rename: F)
+    {
+        // the hook is called once by name (a file could be entered many times)
+        let mut renamed: HashMap<String, Option<usize>> = HashMap::new();
+        for i in first..self.ids.len() {
+            if remapping && self.ids[i].remapped { continue; }
+            let name = self.display_name(&self.ids[i]);
+            if name.is_empty() { continue; }
+            let alias = match renamed.get(name) {
+                Some(&alias) => alias,
+                None => {
+                    let name = name.to_string();
+                    let alias = rename(&name).map(|renamed| self.intern(renamed));
+                    renamed.insert(name, alias);
+                    alias
+                }
+            };
+            if let Some(alias) = alias {
+                self.ids[i].alias = Some(alias);
+                self.ids[i].remapped |= remapping;
+            }
+        }
+    }
+
+    /// Gets the displayed name of a slice (renamed but not qualified).
+    pub(crate) fn display_name(&self, id: &FileSlice) -> &str
+    {
+        match id.alias {
+            Some(alias) => self.aliases[alias].as_str(),
+            None => self.raw_name(id)
+        }
+    }
+
+    /// Qualifies the names of the included files with their include chain.
+    ///
+    /// The name of a file is then displayed as `included_file (from top_file:3)`
+    /// (or `c.h (from b.h:2, from a.c:5)` for nested inclusions) in the
+    /// diagnostics. The include chain is given by the GCC linemarker flags
+    /// (see [`EasyLocation::included_from`]).
+    /// ```
+    /// use codespan_reporting::files::Files;
+    /// use codespan_preprocessed::{EasyLocation, PreprocessedFile};
+    ///
+    /// let file = PreprocessedFile::new("# 1 \"a.c\"\n# 1 \"b.h\" 1\nint b;\n# 2 \"a.c\" 2\nint a;\n")
+    ///     .with_qualified_names(true);
+    /// assert_eq!(file.name(file.file_id(24)).unwrap(), "b.h (from a.c:1)");
+    /// assert_eq!(file.name(file.file_id(43)).unwrap(), "a.c");
+    /// ```
+    #[inline]
+    pub fn with_qualified_names(mut self, enabled: bool) -> Self
+    {
+        self.qualify_names(enabled);
+        self
+    }
+
+    fn qualify_names(&mut self, enabled: bool)
+    {
+        self.qualify_slices(0, enabled);
+        self.prune_aliases();
+    }
+
+    /// Qualifies the names of the slices from the given one
+    /// (the unused names are kept).
+    pub(crate) fn qualify_slices(&mut self, first: usize, enabled: bool)
+    {
+        for i in first..self.ids.len() {
+            let chain = self.include_chain(&self.ids[i])
+                .map(|(name, line)| format!("from {}:{}", name, line))
+                .collect::<Vec<_>>();
+            self.ids[i].qualified = if enabled && !chain.is_empty() {
+                let qualified = format!("{} ({})", self.display_name(&self.ids[i]), chain.join(", "));
+                Some(self.intern(qualified))
+            } else {
+                None
+            };
+        }
+    }
+
+    /// Stores a displayed name (only once).
+    #[inline]
+    pub(crate) fn intern(&mut self, name: String) -> usize
+    {
+        self.aliases.intern(name)
+    }
+
+    /// Removes the displayed names which are no longer used by any slice.
+    #[inline]
+    fn prune_aliases(&mut self)
+    {
+        self.prune_aliases_from(0, 0)
+    }
+
+    /// Removes the displayed names, from the given one, which are no longer
+    /// used by the slices from the given one (the previous slices
+    /// should only use the previous names).
+    pub(crate) fn prune_aliases_from(&mut self, first: usize, first_alias: usize)
+    {
+        let old = self.aliases.split_off(first_alias);
+        let mut moved = vec![None; old.len()];
+        for id in &mut self.ids[first..] {
+            for alias in [&mut id.alias, &mut id.qualified].into_iter().flatten() {
+                if let Some(i) = alias.checked_sub(first_alias) {
+                    *alias = *moved[i].get_or_insert_with(|| self.aliases.intern(old[i].clone()));
+                }
+            }
+        }
+    }
+
+    /// Counts the reported columns in display width.
+    ///
+    /// The wide characters (as CJK ones or emojis) count for two columns,
+    /// the zero-width ones (as combining marks) for none and an emoji sequence
+    /// (as a flag or joined emojis) for its single glyph, so the columns
+    /// match what is seen in a terminal. By default, a column is a character.
+    #[inline]
+    pub fn with_unicode_columns(mut self, enabled: bool) -> Self
+    {
+        self.columns.unicode = enabled;
+        self
+    }
+
+    /// Counts a tab as a move to the next tab stop (every `width` columns).
+    ///
+    /// By default, a tab is a single column (as any other character).
+    /// ```
+    /// use codespan_preprocessed::PreprocessedFile;
+    ///
+    /// let file = PreprocessedFile::new("\tx = 1;\n").with_tab_width(8);
+    /// assert_eq!(file.column_at(1), 9);
+    /// ```
+    #[inline]
+    pub fn with_tab_width(mut self, width: usize) -> Self
+    {
+        self.columns.tab_width = Some(width);
+        self
+    }
+
+    /// Gets the (one-based) column of a byte, as reported in the diagnostics.
+    /// ```
+    /// use codespan_preprocessed::PreprocessedFile;
+    ///
+    /// let file = PreprocessedFile::new("let \u{4e2d} = 1;\n");
+    /// assert_eq!(file.column_at(8), 7);
+    /// assert_eq!(file.with_unicode_columns(true).column_at(8), 8);
+    /// ```
+    pub fn column_at(&self, byte_index: usize) -> usize
+    {
+        let id = self.file_id(byte_index);
+        self.line_index(id, byte_index)
+            .and_then(|line| self.column_number(id, line, byte_index))
+            .expect("BUG: the byte is located in its file slice")
+    }
+
+    /// Sets the name of the slices which are not named by a directive
+    /// (as the contents before the first directive).
+    ///
+    /// By default, these slices have an empty name.
+    /// ```
+    /// use codespan_reporting::files::Files;
+    /// use codespan_preprocessed::{EasyLocation, PreprocessedFile};
+    ///
+    /// let file = PreprocessedFile::new("first\n#line 1 \"a.c\"\nint a;\n")
+    ///     .with_default_name("<stdin>");
+    /// assert_eq!(file.name(file.file_id(2)).unwrap(), "<stdin>");
+    /// assert_eq!(file.name(file.file_id(20)).unwrap(), "a.c");
+    /// ```
+    pub fn with_default_name(mut self, name: impl Into<String>) -> Self
+    {
+        self.origin = Some(name.into());
+        // the qualified names could refer to the unnamed slices
+        if self.ids.iter().any(|id| id.qualified.is_some()) {
+            self.qualify_names(true);
         }
+        self
     }
 
     #[inline]
@@ -188,39 +748,222 @@ impl<Source> PreprocessedFile<Source>
 
     #[inline]
     pub fn is_empty(&self) -> bool { self.source().is_empty() }
+
+    /// Gets the start of the line following the one containing the given byte
+    /// (or `None` on the last line).
+    pub fn next_line_start(&self, byte_index: usize) -> Option<usize>
+    {
+        let line = self.lines.line_within(byte_index, 0..self.lines.len());
+        self.lines.get(line+1).map(|range| range.start)
+    }
+
+    /// Gets the start of the line preceding the one containing the given byte
+    /// (or `None` on the first line).
+    pub fn prev_line_start(&self, byte_index: usize) -> Option<usize>
+    {
+        let line = self.lines.line_within(byte_index, 0..self.lines.len());
+        self.lines.get(line.checked_sub(1)?).map(|range| range.start)
+    }
+
+    /// Gets the range of the word (identifier characters) at the given byte.
+    ///
+    /// Returns `None` if the byte is not inside a word. This is useful to
+    /// build a label from a position found by an error recovery:
+    /// ```
+    /// use codespan_preprocessed::PreprocessedFile;
+    ///
+    /// let file = PreprocessedFile::new("let answer = 42;\n");
+    /// assert_eq!(file.word_range_at(6), Some(4..10));
+    /// assert_eq!(file.word_range_at(11), None);
+    /// ```
+    pub fn word_range_at(&self, byte_index: usize) -> Option<Range<usize>>
+    {
+        let is_word = |c: char| c.is_alphanumeric() || c == '_';
+        let source = self.source();
+        source.get(byte_index..)?.chars().next().filter(|c| is_word(*c))?;
+        let start = source[..byte_index].rfind(|c| !is_word(c))
+            .map_or(0, |i| i + source[i..].chars().next().unwrap().len_utf8());
+        let end = source[byte_index..].find(|c| !is_word(c))
+            .map_or(source.len(), |i| byte_index + i);
+        Some(start..end)
+    }
 }
 
 impl PreprocessedFile<String>
 {
+    /// Opens a preprocessed file.
+    ///
+    /// The encoding is detected from the byte order mark (BOM)
+    /// and defaults to UTF-8 (see [`PreprocessedFile::open_with_encoding`]).
+    #[inline]
     pub fn open<P: AsRef<Path>>(filename: P) -> Result<Self, std::io::Error>
+    {
+        Self::open_with_encoding(filename, Encoding::Utf8)
+    }
+
+    /// Opens a preprocessed file with the given encoding.
+    ///
+    /// The BOM, if any, is stripped and takes precedence
+    /// over the given encoding. The contents are transcoded
+    /// into UTF-8 before building the codemap.
+    pub fn open_with_encoding<P: AsRef<Path>>(filename: P, encoding: Encoding) -> Result<Self, std::io::Error>
     {
         let mut file = std::fs::File::open(&filename)?;
         let mut buf = Vec::new();
         file.read_to_end(&mut buf)?;
-        // prepend '#line' directive to correctly locate diagnosis
-        let contents = format!("#line 1 \"{}\"\n{}",
-                               filename.as_ref().to_string_lossy(),
-                               String::from_utf8(buf).expect("invalid UTF-8 characters in file"));
-        Ok(PreprocessedFile::new(contents))
+        PreprocessedFile::load(named(filename.as_ref(), &encoding.decode(buf)?))
     }
 
     pub fn from_stdin() -> Result<Self, std::io::Error>
     {
-        let mut buf = Vec::new();
-        std::io::stdin().read_to_end(&mut buf)?;
-        let contents = String::from_utf8(buf).expect("invalid UTF-8 characters on stdin");
-        Ok(PreprocessedFile::new(contents))
+        Self::from_reader(std::io::stdin().lock(), None)
+    }
+
+    /// Reads a preprocessed text from any reader
+    /// (a decompressed stream, a socket, an archive entry...).
+    ///
+    /// The size hint, if known, is used to preallocate the buffer.
+    /// As for [`PreprocessedFile::open`], the encoding is detected from the BOM.
+    pub fn from_reader<R: Read>(mut reader: R, size_hint: Option<usize>) -> Result<Self, std::io::Error>
+    {
+        let mut buf = Vec::with_capacity(size_hint.unwrap_or_default());
+        reader.read_to_end(&mut buf)?;
+        let contents = Encoding::Utf8.decode(buf)?;
+        PreprocessedFile::load(contents)
+    }
+}
+
+impl<S:AsRef<str>> PreprocessedFile<S>
+{
+    /// Gets the file slice of a span.
+    ///
+    /// Contrary to [`EasyLocation::file_id`], this checks that
+    /// the whole span lies within a single slice.
+    pub fn span_file(&self, span: Range<usize>) -> Result<&FileSlice, SpanCrossesFiles>
+    {
+        let id = self.file_id(span.start);
+        if span.end <= id.bytes.end {
+            Ok(id)
+        } else {
+            Err(SpanCrossesFiles { span, boundary: id.bytes.end })
+        }
+    }
+
+    /// Translates a line of the preprocessed source into its original
+    /// file slice and line number (both line numbers are one-based).
+    ///
+    /// Returns `None` for a directive line or a line out of the source.
+    /// ```
+    /// use codespan_reporting::files::Files;
+    /// use codespan_preprocessed::PreprocessedFile;
+    ///
+    /// let file = PreprocessedFile::new("#line 10 \"a.c\"\nint a;\nint b;\n");
+    /// let (id, line) = file.original_line_of(3).unwrap();
+    /// assert_eq!((file.name(id).unwrap(), line), ("a.c", 11));
+    /// assert_eq!(file.preprocessed_line_of(id, 11), Some(3));
+    /// assert_eq!(file.original_line_of(1), None);
+    /// ```
+    pub fn original_line_of(&self, line_number: usize) -> Option<(&FileSlice, usize)>
+    {
+        let line = line_number.checked_sub(1)?;
+        let id = &self.ids[self.ids.partition_point(|id| id.lines.end <= line).min(self.ids.len()-1)];
+        id.lines.contains(&line)
+            .then(|| (id, (line as isize - id.offset) as usize + 1))
+    }
+
+    /// Translates an original line number of a file slice into
+    /// the line of the preprocessed source (both are one-based).
+    ///
+    /// Returns `None` if the line is not in this slice (an original
+    /// file could be split into several slices, see [`PreprocessedFile::original_line_of`]).
+    pub fn preprocessed_line_of(&self, id: &FileSlice, line_number: usize) -> Option<usize>
+    {
+        let line = (line_number as isize - 1).checked_add(id.offset)?;
+        usize::try_from(line).ok()
+            .filter(|line| id.lines.contains(line))
+            .map(|line| line + 1)
+    }
+
+    /// Translates a position of an original file (its name, one-based line
+    /// and column) into a byte of the preprocessed source.
+    ///
+    /// The file is searched by its displayed or raw name, and a column
+    /// past the end of the line is clamped to it. Returns `None` if this
+    /// line of the file is not in the preprocessed source.
+    /// ```
+    /// use codespan_preprocessed::PreprocessedFile;
+    ///
+    /// let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a;\n#line 7 \"b.h\"\nint b;\n");
+    /// assert_eq!(file.byte_at("b.h", 7, 5), Some(39));
+    /// assert_eq!(file.range_at("a.c", 1, 5..6), Some(18..19));
+    /// assert_eq!(file.byte_at("a.c", 2, 1), None);
+    /// ```
+    pub fn byte_at(&self, name: &str, line_number: usize, column_number: usize) -> Option<usize>
+    {
+        let line_index = line_number.checked_sub(1)?;
+        let id = self.ids.iter()
+            .filter(|id| self.name(id).is_ok_and(|n| n == name) || self.raw_name(id) == name)
+            .find(|id| self.preprocessed_line_of(id, line_number).is_some())?;
+        let range = self.line_range(id, line_index).ok()?;
+        let source = self.source();
+        (range.start..range.end)
+            .filter(|byte| source.is_char_boundary(*byte))
+            .find(|byte| self.column_number(id, line_index, *byte).is_ok_and(|column| column >= column_number))
+            .or(Some(range.end))
+    }
+
+    /// Translates a span of a line of an original file (the columns
+    /// are one-based, the end one being excluded) into a span of
+    /// the preprocessed source (see [`PreprocessedFile::byte_at`]).
+    #[inline]
+    pub fn range_at(&self, name: &str, line_number: usize, columns: Range<usize>) -> Option<Range<usize>>
+    {
+        Some(self.byte_at(name, line_number, columns.start)?..self.byte_at(name, line_number, columns.end)?)
     }
 }
 
 pub trait EasyLocation<'a>:Files<'a>
 {
     fn file_id(&'a self, byte_index: usize) -> <Self as Files<'a>>::FileId;
+
+    /// Splits a span into the parts located in different files.
+    ///
+    /// By default, the whole span is located in the file of its start.
+    fn file_spans(&'a self, span: Range<usize>) -> Vec<(<Self as Files<'a>>::FileId, Range<usize>)>
+    {
+        vec![(self.file_id(span.start), span)]
+    }
+
+    /// Gets the bytes of the source covered by a file.
+    ///
+    /// By default, a file covers its whole source.
+    fn file_bytes(&'a self, id: <Self as Files<'a>>::FileId) -> Range<usize>
+    {
+        0..self.source(id).map_or(0, |source| source.as_ref().len())
+    }
+
+    /// Gets the include chain of a file: the names and line numbers
+    /// of the successive inclusions, from the innermost one.
+    ///
+    /// By default, there is no inclusion.
+    fn included_from(&'a self, _id: <Self as Files<'a>>::FileId) -> Vec<(<Self as Files<'a>>::Name, usize)>
+    {
+        vec![]
+    }
+
+    /// Checks if a file is a system header.
+    ///
+    /// By default, there is no system header.
+    fn is_system_header(&'a self, _id: <Self as Files<'a>>::FileId) -> bool
+    {
+        false
+    }
 }
 
-impl<'a, S:'a+AsRef<str>> EasyLocation<'a> for PreprocessedFile<S>
+impl<S:AsRef<str>> PreprocessedFile<S>
 {
-    fn file_id(&'a self, byte_index: usize) -> <Self as Files<'a>>::FileId
+    /// Index of the slice containing the given byte.
+    fn slice_index(&self, byte_index: usize) -> usize
     {
         // as ids are sorted according to the byte order of the input,
         // we could use a binary_search...
@@ -229,13 +972,55 @@ impl<'a, S:'a+AsRef<str>> EasyLocation<'a> for PreprocessedFile<S>
             else if byte_index > x.bytes.end { Ordering::Less }
             else { Ordering::Equal }
         ) {
-            Ok(i) => &self.ids[i],
-            Err(i) if i < self.ids.len() => &self.ids[i],
-            _ => self.ids.last().unwrap(),
+            Ok(i) => i,
+            Err(i) if i < self.ids.len() => i,
+            _ => self.ids.len()-1,
         }
     }
 }
 
+impl<'a, S:'a+AsRef<str>> EasyLocation<'a> for PreprocessedFile<S>
+{
+    #[inline]
+    fn file_id(&'a self, byte_index: usize) -> <Self as Files<'a>>::FileId
+    {
+        &self.ids[self.slice_index(byte_index)]
+    }
+
+    fn file_spans(&'a self, span: Range<usize>) -> Vec<(<Self as Files<'a>>::FileId, Range<usize>)>
+    {
+        let first = self.slice_index(span.start);
+        let mut spans = self.ids[first..].iter()
+            .take_while(|id| id.bytes.start < span.end || id.bytes.start <= span.start)
+            .map(|id| (id, span.start.max(id.bytes.start)..span.end.min(id.bytes.end)))
+            // the directive lines between slices are dropped
+            .filter(|(_, part)| !part.is_empty())
+            .collect::<Vec<_>>();
+        if spans.is_empty() {
+            // keep empty spans where they are
+            spans.push((&self.ids[first], span));
+        }
+        spans
+    }
+
+    #[inline]
+    fn file_bytes(&'a self, id: <Self as Files<'a>>::FileId) -> Range<usize>
+    {
+        id.bytes.clone()
+    }
+
+    #[inline]
+    fn is_system_header(&'a self, id: <Self as Files<'a>>::FileId) -> bool
+    {
+        PreprocessedFile::is_system_header(self, id)
+    }
+
+    fn included_from(&'a self, id: <Self as Files<'a>>::FileId) -> Vec<(<Self as Files<'a>>::Name, usize)>
+    {
+        self.include_chain(id).collect()
+    }
+}
+
 
 impl<'a,N,S> EasyLocation<'a> for SimpleFile<N,S>
     where
@@ -243,4 +1028,239 @@ impl<'a,N,S> EasyLocation<'a> for SimpleFile<N,S>
         S: 'a + AsRef<str>,
 {
     fn file_id(&'a self, _: usize) -> <Self as Files<'a>>::FileId { }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use codespan_reporting::files::{Files, Location};
+    use crate::{CodemapError, EasyLocation, PreprocessedFile, SpanCrossesFiles};
+
+    #[test]
+    fn path_remapping()
+    {
+        let file = PreprocessedFile::new(
+            "#line 1 \"/sandbox/42/src/a.c\"\nfirst\n#line 1 \"/usr/include/b.h\"\nsecond\n"
+        ).with_path_remap("/sandbox/42", ".");
+
+        let a = file.file_id(31);
+        assert_eq!(file.name(a).unwrap(), "./src/a.c");
+        assert_eq!(file.raw_name(a), "/sandbox/42/src/a.c");
+
+        let b = file.file_id(64);
+        assert_eq!(file.name(b).unwrap(), "/usr/include/b.h");
+
+        // only the first matching remapping applies
+        let file = file.with_path_remap("./src", "lib").with_path_remap("/usr", "");
+        assert_eq!(file.name(file.file_id(31)).unwrap(), "./src/a.c");
+        assert_eq!(file.name(file.file_id(64)).unwrap(), "/include/b.h");
+        // the names no longer displayed are dropped
+        let file = file.with_qualified_names(true).with_qualified_names(false);
+        assert_eq!(file.aliases.len(), 2);
+    }
+
+    #[test]
+    fn line_number_boundaries()
+    {
+        // GNU extension: the line 0 is the first one
+        let file = PreprocessedFile::new("#line 0 \"a\"\nfirst\n");
+        assert_eq!(file.location(file.file_id(12), 12).unwrap().line_number, 1);
+
+        let file = PreprocessedFile::new("#line 2147483647 \"a\"\nfirst\n");
+        assert_eq!(file.location(file.file_id(23), 23).unwrap().line_number, 2147483647);
+
+        let file = PreprocessedFile::new("#line 99999999999999999999 \"a\"\nfirst\n");
+        assert_eq!(file.location(file.file_id(33), 33).unwrap().line_number, 2147483647);
+
+        assert_eq!(PreprocessedFile::try_new("x\n#line 2147483648\nfirst\n").unwrap_err(),
+                   CodemapError::LineNumberOutOfRange { directive: 2..18, number: 2147483648 });
+        assert!(PreprocessedFile::try_new("#line 2147483647\nfirst\n").is_ok());
+    }
+
+    #[test]
+    fn reader_input()
+    {
+        let input = b"\xEF\xBB\xBF#line 3 \"r.c\"\nx\n";
+        let file = PreprocessedFile::from_reader(&input[..], Some(input.len())).unwrap();
+        assert_eq!(file.source(), "#line 3 \"r.c\"\nx\n");
+        assert_eq!(file.location(file.file_id(14), 14).unwrap(), Location { line_number: 3, column_number: 1 });
+    }
+
+    #[test]
+    fn text_navigation()
+    {
+        let file = PreprocessedFile::new("#line 1 \"a\"\nfoo(\u{e9}t\u{e9}_1)\n\nbar\n");
+        assert_eq!(file.next_line_start(3), Some(12));
+        assert_eq!(file.next_line_start(25), Some(26));
+        assert_eq!(file.next_line_start(27), None);
+        assert_eq!(file.prev_line_start(26), Some(25));
+        assert_eq!(file.prev_line_start(11), None);
+        assert_eq!(file.word_range_at(19), Some(16..23));
+        assert_eq!(file.word_range_at(16), Some(16..23));
+        assert_eq!(file.word_range_at(15), None);
+        assert_eq!(file.word_range_at(29), None);
+    }
+
+    #[test]
+    fn codemap_dump()
+    {
+        let file = PreprocessedFile::new("int a;\n#line 10 \"a.h\"\nint b;\nint c;\n");
+        let mut dump = Vec::new();
+        file.dump(&mut dump).unwrap();
+        assert_eq!(String::from_utf8(dump).unwrap(), unindent::unindent(r#"
+            36 bytes, 4 lines, 2 slices
+            #0 "" bytes 0..7 lines 1..=1 (preprocessed 1..=1) offset 0 column +0
+               no directive
+            #1 "a.h" bytes 22..35 lines 10..=11 (preprocessed 3..=4) offset -7 column +0
+               directive: #line 10 "a.h"
+        "#));
+    }
+
+    #[test]
+    fn include_tree()
+    {
+        let file = PreprocessedFile::new(unindent::unindent(r#"
+            # 1 "a.c"
+            int a;
+            # 1 "b.h" 1
+            int b;
+            # 1 "c.h" 1
+            int c;
+            # 2 "b.h" 2
+            int bb;
+            # 3 "a.c" 2
+            int aa;
+        "#));
+        let name = |id| file.name(id).unwrap();
+        let a = file.file_id(10);
+        let b = file.file_id(29);
+        let c = file.file_id(48);
+        let bb = file.file_id(67);
+        let aa = file.file_id(87);
+        assert_eq!(file.parent_of(a), None);
+        assert_eq!(file.parent_of(b).map(name), Some("a.c"));
+        assert_eq!(file.parent_of(c).map(name), Some("b.h"));
+        assert_eq!(file.parent_of(bb), Some(a));
+        assert_eq!(file.parent_of(aa), None);
+
+        assert_eq!(file.included_from(c), vec![("b.h", 2), ("a.c", 2)]);
+        assert_eq!(file.included_from(bb), vec![("a.c", 2)]);
+        assert_eq!(file.included_from(aa), vec![]);
+
+        let tree = file.include_tree();
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree[0].slice, a);
+        assert_eq!(tree[0].children.len(), 2);
+        assert_eq!(tree[0].children[0].slice, b);
+        assert_eq!(tree[0].children[0].children[0].slice, c);
+        assert_eq!(tree[0].children[1].slice, bb);
+
+        // the qualified names follow the path remapping
+        let file = file.with_qualified_names(true).with_path_remap("", "src/");
+        let name = |byte| file.name(file.file_id(byte)).unwrap();
+        assert_eq!(name(10), "src/a.c");
+        assert_eq!(name(48), "src/c.h (from src/b.h:2, from src/a.c:2)");
+        assert_eq!(name(67), "src/b.h (from src/a.c:2)");
+        assert_eq!(file.included_from(file.file_id(48)), vec![("src/b.h", 2), ("src/a.c", 2)]);
+        let file = file.with_qualified_names(false);
+        assert_eq!(file.name(file.file_id(48)).unwrap(), "src/c.h");
+    }
+
+    #[test]
+    fn line_translation()
+    {
+        let file = PreprocessedFile::new("first\n#line 5 \"a.c\"\na5\na6\n#line 1 \"b.c\"\nb1\n");
+        let lines = (1..=7).map(|l| file.original_line_of(l).map(|(id, line)| (file.name(id).unwrap(), line)))
+            .collect::<Vec<_>>();
+        assert_eq!(lines, vec![Some(("", 1)), None, Some(("a.c", 5)), Some(("a.c", 6)), None, Some(("b.c", 1)), None]);
+        for l in [1, 3, 4, 6] {
+            let (id, line) = file.original_line_of(l).unwrap();
+            assert_eq!(file.preprocessed_line_of(id, line), Some(l));
+        }
+        let (a, _) = file.original_line_of(3).unwrap();
+        assert_eq!(file.preprocessed_line_of(a, 4), None);
+        assert_eq!(file.preprocessed_line_of(a, 7), None);
+        assert_eq!(file.original_line_of(0), None);
+    }
+
+    #[test]
+    fn directive_at_eof()
+    {
+        use codespan_reporting::diagnostic::{Diagnostic, Label};
+        use codespan_reporting::term::{self, termcolor::NoColor};
+
+        for source in ["#line 5 \"a.c\"", "#line 5 \"a.c\"\n", "x\n#line 5 \"a.c\"", "x\n# 5 \"a.c\" 2\n", "x\n#line 5"] {
+            let file = PreprocessedFile::new(source);
+            let last = file.ids.last().unwrap();
+            assert!(last.lines.is_empty() && last.bytes.is_empty(), "{:?}", source);
+            // the end of the source is located
+            for byte in [last.bytes.start, source.len()] {
+                let id = file.file_id(byte);
+                let location = file.location(id, byte).unwrap();
+                let label = Label::primary(id, byte..byte);
+                let mut out = NoColor::new(vec![]);
+                term::emit(&mut out, &term::Config::default(), &file, &Diagnostic::error().with_labels(vec![label]))
+                    .unwrap_or_else(|e| panic!("{:?} at {} ({:?}): {}", source, byte, location, e));
+            }
+        }
+        let file = PreprocessedFile::new("x\n#line 5 \"a.c\"");
+        assert_eq!(file.name(file.file_id(16)).unwrap(), "a.c");
+        assert_eq!(file.original_line_of(2), None);
+        assert_eq!(file.original_line_of(3), None);
+    }
+
+    #[test]
+    fn system_headers()
+    {
+        let file = PreprocessedFile::new(unindent::unindent(r#"
+            # 1 "a.c"
+            int a;
+            # 1 "/usr/include/stdio.h" 1 3
+            int b;
+            #line 10
+            int c;
+            # 2 "a.c" 2
+            int d;
+        "#));
+        assert!(!file.is_system_header(file.file_id(10)));
+        assert!(file.is_system_header(file.file_id(48)));
+        assert!(file.is_system_header(file.file_id(64)));
+        assert!(!file.is_system_header(file.file_id(83)));
+    }
+
+    #[test]
+    fn span_validity()
+    {
+        let file = PreprocessedFile::new("#line 1 \"a\"\nfirst\n#line 1 \"b\"\nsecond\n");
+        assert_eq!(file.name(file.span_file(12..17).unwrap()).unwrap(), "a");
+        assert_eq!(file.name(file.span_file(30..36).unwrap()).unwrap(), "b");
+        assert_eq!(file.span_file(14..32), Err(SpanCrossesFiles { span: 14..32, boundary: 18 }));
+    }
+
+    #[test]
+    fn span_splitting()
+    {
+        let file = PreprocessedFile::new("#line 1 \"a\"\nfirst\n#line 1 \"b\"\nsecond\n");
+        let spans = file.file_spans(14..32).into_iter()
+            .map(|(id, span)| (file.name(id).unwrap(), span))
+            .collect::<Vec<_>>();
+        assert_eq!(spans, vec![("a", 14..18), ("b", 30..32)]);
+        assert_eq!(file.file_spans(12..17).len(), 1);
+    }
+
+    #[test]
+    fn column_adjustment()
+    {
+        let file = PreprocessedFile::builder()
+            .column_directive("#column")
+            .build("#line 10 \"gen.c\"\n#column 4\nx = 1;\ny = 2;\n#line 20\nz = 3;\n");
+
+        let x = file.file_id(27);
+        assert_eq!(file.location(x, 27).unwrap(), Location { line_number: 10, column_number: 5 });
+        let y = file.file_id(36);
+        assert_eq!(file.location(y, 36).unwrap(), Location { line_number: 11, column_number: 7 });
+        // a new directive resets the column adjustment
+        let z = file.file_id(50);
+        assert_eq!(file.name(z).unwrap(), "gen.c");
+        assert_eq!(file.location(z, 50).unwrap(), Location { line_number: 20, column_number: 1 });
+    }
+}
@@ -0,0 +1,84 @@
+use std::ops::Range;
+use codespan_reporting::files;
+use unicode_width::UnicodeWidthStr;
+
+/// The counting of the reported columns.
+///
+/// By default, a column is a character (as for `codespan_reporting`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Columns {
+    /// The columns are counted in display width (wide CJK characters
+    /// count for two columns, combining marks for none and the emoji
+    /// sequences, as flags or joined emojis, for the width of their glyph)
+    pub(crate) unicode: bool,
+    /// A tab moves to the next tab stop (a multiple of this width)
+    pub(crate) tab_width: Option<usize>
+}
+
+impl Columns {
+
+    /// Zero-based column of a byte in a line of the source.
+    pub(crate) fn index(&self, source: &str, line_range: Range<usize>, byte_index: usize) -> usize
+    {
+        if !self.unicode && self.tab_width.is_none() {
+            return files::column_index(source, line_range, byte_index);
+        }
+        let mut end = byte_index.min(line_range.end).min(source.len());
+        while !source.is_char_boundary(end) { end -= 1; }
+        let count = |text: &str| if self.unicode { text.width() } else { text.chars().count() };
+        let text = &source[line_range.start.min(end)..end];
+        match self.tab_width {
+            Some(width) if width > 0 => {
+                let mut segments = text.split('\t');
+                let first = segments.next().map_or(0, count);
+                segments.fold(first, |column, segment| (column / width + 1).saturating_mul(width).saturating_add(count(segment)))
+            }
+            _ => count(text)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Columns;
+
+    #[test]
+    fn display_width()
+    {
+        let line = "a\u{4e2d}\u{6587}e\u{301}x";
+        let chars = Columns::default();
+        let unicode = Columns { unicode: true, ..Columns::default() };
+        assert_eq!(chars.index(line, 0..line.len(), 7), 3);
+        assert_eq!(unicode.index(line, 0..line.len(), 7), 5);
+        // the combining accent does not move the column
+        assert_eq!(chars.index(line, 0..line.len(), 10), 5);
+        assert_eq!(unicode.index(line, 0..line.len(), 10), 6);
+        // a byte inside a character is located at this character
+        assert_eq!(unicode.index(line, 0..line.len(), 5), 3);
+    }
+
+    #[test]
+    fn emoji_sequences()
+    {
+        // a family (joined emojis), a flag (regional indicators) and a skin tone
+        let line = "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}|\u{1f1eb}\u{1f1f7}|\u{1f44d}\u{1f3fd}|";
+        let unicode = Columns { unicode: true, ..Columns::default() };
+        let bars = line.match_indices('|').map(|(i, _)| unicode.index(line, 0..line.len(), i)).collect::<Vec<_>>();
+        assert_eq!(bars, vec![2, 5, 8]);
+    }
+
+    #[test]
+    fn tab_stops()
+    {
+        let line = "\tab\t\u{4e2d}\tx";
+        let chars = Columns::default();
+        let tabs = Columns { tab_width: Some(4), ..Columns::default() };
+        let unicode = Columns { unicode: true, tab_width: Some(4) };
+        assert_eq!(chars.index(line, 0..line.len(), 1), 1);
+        assert_eq!(tabs.index(line, 0..line.len(), 1), 4);
+        assert_eq!(tabs.index(line, 0..line.len(), 4), 8);
+        assert_eq!(tabs.index(line, 0..line.len(), 8), 12);
+        assert_eq!(unicode.index(line, 0..line.len(), 7), 10);
+        assert_eq!(unicode.index(line, 0..line.len(), 8), 12);
+    }
+}
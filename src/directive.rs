@@ -0,0 +1,262 @@
+use std::ops::Range;
+
+/// The syntax of the directives to parse.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Syntax {
+    /// Keyword of the column adjustment directive (if any)
+    pub(crate) column_directive: Option<String>,
+    /// Directives could be indented and case-insensitive
+    pub(crate) fortran: bool,
+    /// Directives are parsed with tolerance to blanks and escapes
+    pub(crate) tolerant: bool,
+    /// Errors are reported instead of being recovered
+    pub(crate) strict: bool,
+}
+
+/// Flag of a linemarker entering an included file.
+pub(crate) const ENTER: u8 = 1 << 1;
+/// Flag of a linemarker returning to an including file.
+pub(crate) const RETURN: u8 = 1 << 2;
+
+/// Flag of a linemarker for a system header.
+pub(crate) const SYSTEM: u8 = 1 << 3;
+
+/// The greatest line number (as accepted by the C standard).
+pub(crate) const MAX_LINE_NUMBER: u64 = 2147483647;
+
+impl Syntax {
+
+    /// Skips the leading blanks (if allowed).
+    #[inline]
+    fn directive_start(&self, line: &str) -> usize
+    {
+        if self.fortran || self.tolerant { line.len() - line.trim_start_matches([' ', '\t']).len() } else { 0 }
+    }
+
+    /// The first characters of the lines which could be directives.
+    pub(crate) fn markers(&self) -> Vec<u8>
+    {
+        let mut markers = vec![b'#', b'%'];
+        markers.extend(self.column_directive.as_ref().and_then(|keyword| keyword.bytes().next()));
+        markers
+    }
+
+    /// Classifies a line starting with `#` (or its digraph `%:`).
+    ///
+    /// Returns the start of the arguments if this is a line directive,
+    /// i.e. the `line` keyword (`#line 42 "file"`) or a GCC linemarker
+    /// (`# 42 "file" 1`). Blanks are allowed between `#` and the keyword
+    /// which should be followed by a word boundary (`#lineage` or `#pragma`
+    /// are not line directives).
+    fn directive_arguments(&self, line: &str) -> Option<usize>
+    {
+        let start = self.directive_start(line);
+        let name = line[start..].strip_prefix('#')
+            .or_else(|| line[start..].strip_prefix("%:"))?
+            .trim_start_matches(BLANKS);
+        let name_start = line.len() - name.len();
+        if name.starts_with(|c: char| c.is_ascii_digit()) {
+            // GCC linemarker: the arguments follow the '#'
+            return Some(name_start);
+        }
+        let len = name.find(|c: char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(name.len());
+        let keyword = &name[..len];
+        let is_line = if self.fortran { keyword.eq_ignore_ascii_case("line") } else { keyword == "line" };
+        is_line.then_some(name_start + len)
+    }
+
+    /// Parses a line directive (`#line 42 "file"` or `# 42 "file"`).
+    ///
+    /// Returns the line number, the range of the filename
+    /// (relative to the line), if any, and the linemarker flags
+    /// (the flag `n` being set as the bit `1 << n`).
+    ///
+    /// A malformed directive is not a directive
+    /// (see [`Syntax::is_malformed_directive`]).
+    pub(crate) fn line_directive(&self, line: &str) -> Option<(u64, Option<Range<usize>>, u8)>
+    {
+        let args = self.directive_arguments(line)?;
+        match arguments(line, args) {
+            Some((number, filename, rest)) if self.tolerant || is_flags(&line[rest..]) =>
+                Some((number, filename, flags(&line[rest..]))),
+            _ => None
+        }
+    }
+
+    /// Checks if a line looks like a line directive
+    /// (`#line` or `# <digit>`) but could not be parsed.
+    #[inline]
+    pub(crate) fn is_malformed_directive(&self, line: &str) -> bool
+    {
+        self.directive_arguments(line).is_some() && self.line_directive(line).is_none()
+    }
+
+    /// Checks the line number of a directive.
+    ///
+    /// `#line 0` (a GNU extension) is clamped to the first line,
+    /// too large numbers are clamped to [`MAX_LINE_NUMBER`] unless
+    /// the syntax is strict (then `None` is returned).
+    pub(crate) fn line_number(&self, number: u64) -> Option<isize>
+    {
+        match number {
+            0 => Some(1),
+            n if n <= MAX_LINE_NUMBER => Some(n as isize),
+            _ if self.strict => None,
+            _ => Some(MAX_LINE_NUMBER as isize)
+        }
+    }
+
+    /// Parses a column adjustment directive.
+    pub(crate) fn column_directive(&self, line: &str) -> Option<isize>
+    {
+        let keyword = self.column_directive.as_ref()?;
+        line[self.directive_start(line)..]
+            .strip_prefix(keyword.as_str())
+            .filter(|arg| arg.starts_with(' '))
+            .and_then(|arg| arg.trim().parse::<isize>().ok())
+    }
+}
+
+const BLANKS: [char;2] = [' ', '\t'];
+
+/// Parses the arguments of a line directive: the line number
+/// and an optional quoted filename (with escaped characters).
+///
+/// Returns also the start of the remaining characters.
+fn arguments(line: &str, start: usize) -> Option<(u64, Option<Range<usize>>, usize)>
+{
+    let args = line[start..].trim_start_matches(BLANKS);
+    let is_linemarker = line[..start].ends_with(|c: char| c == '#' || c == ':' || BLANKS.contains(&c));
+    if !is_linemarker && args.len() == line.len()-start { return None; /* no separator after keyword */ }
+
+    let digits = args.len() - args.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    // too large numbers saturate (they will be checked later)
+    let number = if digits == 0 { return None } else { args[..digits].parse::<u64>().unwrap_or(u64::MAX) };
+
+    let rest = args[digits..].trim_start_matches(BLANKS);
+    let Some(quoted) = rest.strip_prefix('"') else {
+        return Some((number, None, line.len() - args[digits..].len()));
+    };
+    let open = line.len() - quoted.len();
+    let mut escaped = false;
+    let close = quoted.find(|c: char| {
+        let end = c == '"' && !escaped;
+        escaped = c == '\\' && !escaped;
+        end
+    })?;
+    Some((number, Some(open..open+close), open+close+1))
+}
+
+/// Checks that the remaining characters of a directive are only flags
+/// (as for GCC linemarkers) or blanks.
+fn is_flags(rest: &str) -> bool
+{
+    rest.chars().all(|c| c.is_ascii_digit() || c.is_ascii_whitespace())
+}
+
+/// Gets the linemarker flags (from 1 to 4) as a bit set.
+fn flags(rest: &str) -> u8
+{
+    rest.split_ascii_whitespace()
+        .filter_map(|flag| flag.parse::<u8>().ok())
+        .filter(|flag| (1..=4).contains(flag))
+        .fold(0, |flags, flag| flags | (1 << flag))
+}
+
+/// Unescapes a filename as written in a C string.
+pub(crate) fn unescape(name: &str) -> String
+{
+    let mut unescaped = String::with_capacity(name.len());
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.extend(chars.next()),
+            c => unescaped.push(c)
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Syntax, MAX_LINE_NUMBER, ENTER, SYSTEM};
+
+    #[test]
+    fn fortran_directives()
+    {
+        let strict = Syntax::default();
+        let fortran = Syntax { fortran: true, ..Syntax::default() };
+
+        assert_eq!(strict.line_directive("#line 12 \"a.f\""), Some((12, Some(10..13), 0)));
+        assert_eq!(strict.line_directive("  #line 12 \"a.f\""), None);
+        assert_eq!(strict.line_directive("#LINE 12"), None);
+
+        assert_eq!(fortran.line_directive("  #line 12 \"a.f\""), Some((12, Some(12..15), 0)));
+        assert_eq!(fortran.line_directive("\t#LINE 12"), Some((12, None, 0)));
+        assert_eq!(fortran.line_directive("      x = 1"), None);
+    }
+
+    #[test]
+    fn directive_classification()
+    {
+        let syntax = Syntax::default();
+
+        assert_eq!(syntax.line_directive("#pragma once"), None);
+        assert_eq!(syntax.line_directive("#lineage 5"), None);
+        assert_eq!(syntax.line_directive("#linex"), None);
+        assert_eq!(syntax.line_directive("#define line 3"), None);
+        assert_eq!(syntax.line_directive("# 42"), Some((42, None, 0)));
+        assert_eq!(syntax.line_directive("# 42 \"a.c\" 1 3"), Some((42, Some(6..9), ENTER | SYSTEM)));
+        assert_eq!(syntax.line_directive("#  line 3 \"x\""), Some((3, Some(11..12), 0)));
+    }
+
+    #[test]
+    fn line_number_range()
+    {
+        let strict = Syntax { strict: true, ..Syntax::default() };
+        let permissive = Syntax::default();
+
+        assert_eq!(strict.line_number(0), Some(1));
+        assert_eq!(strict.line_number(MAX_LINE_NUMBER), Some(MAX_LINE_NUMBER as isize));
+        assert_eq!(strict.line_number(MAX_LINE_NUMBER+1), None);
+        assert_eq!(permissive.line_number(MAX_LINE_NUMBER+1), Some(MAX_LINE_NUMBER as isize));
+        assert_eq!(permissive.line_number(u64::MAX), Some(MAX_LINE_NUMBER as isize));
+    }
+
+    #[test]
+    fn digraph_directives()
+    {
+        let syntax = Syntax::default();
+
+        assert_eq!(syntax.line_directive("%:line 42 \"file\""), Some((42, Some(11..15), 0)));
+        assert_eq!(syntax.line_directive("%: 42"), Some((42, None, 0)));
+        assert_eq!(syntax.line_directive("%:pragma once"), None);
+        assert_eq!(syntax.line_directive("%{"), None);
+    }
+
+    #[test]
+    fn malformed_directive()
+    {
+        let syntax = Syntax::default();
+        assert_eq!(syntax.line_directive("#line \"a.c\""), None);
+        assert!(syntax.is_malformed_directive("#line \"a.c\""));
+        assert!(syntax.is_malformed_directive("# 12 \"a.c\" x"));
+        assert!(!syntax.is_malformed_directive("#line 12 \"a.c\""));
+        assert!(!syntax.is_malformed_directive("#pragma once"));
+    }
+
+    #[test]
+    fn tolerant_directives()
+    {
+        let tolerant = Syntax { tolerant: true, ..Syntax::default() };
+        assert_eq!(tolerant.line_directive("#line 99999999999999999999999"), Some((u64::MAX, None, 0)));
+
+        assert_eq!(tolerant.line_directive("#line 12 \"a.y\"\r"), Some((12, Some(10..13), 0)));
+        assert_eq!(tolerant.line_directive("#line\t12  \"a.y\"  "), Some((12, Some(11..14), 0)));
+        assert_eq!(tolerant.line_directive("  #line 7"), Some((7, None, 0)));
+        assert_eq!(tolerant.line_directive("#line 7 \"C:\\\\src\\\"q\\\".y\""), Some((7, Some(9..23), 0)));
+        assert_eq!(tolerant.line_directive("#linex 7"), None);
+        assert_eq!(tolerant.line_directive("#line \"a.y\""), None);
+        assert_eq!(super::unescape("C:\\\\src\\\\parse.y"), "C:\\src\\parse.y");
+    }
+}
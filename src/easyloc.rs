@@ -40,6 +40,57 @@ impl<X> EasyLocated<X>
     pub fn map<Y,F:FnMut(X) -> Y>(self, mut f:F) -> EasyLocated<Y> {
         EasyLocated { inner: f(self.inner), loc: self.loc }
     }
+
+    /// Maps the data by a fallible function, the error keeping
+    /// the location (as to become the label of a diagnostic).
+    /// ```
+    /// use codespan_preprocessed::EasyLocator;
+    ///
+    /// let number = (4..7).locate("12a").try_map(|s| s.parse::<u32>());
+    /// let err = number.unwrap_err();
+    /// assert_eq!(err.to_string(), "invalid digit found in string");
+    /// assert_eq!(*err.location(), 4..7);
+    /// ```
+    #[inline]
+    pub fn try_map<Y,E,F:FnOnce(X) -> Result<Y,E>>(self, f:F) -> Result<EasyLocated<Y>,EasyLocated<E>>
+    {
+        match f(self.inner) {
+            Ok(y) => Ok(EasyLocated::new(y, self.loc)),
+            Err(e) => Err(EasyLocated::new(e, self.loc))
+        }
+    }
+
+    /// Gets the smallest range covering both locations
+    /// (as the span of a node from the ones of its children).
+    /// ```
+    /// use codespan_preprocessed::EasyLocator;
+    ///
+    /// let lhs = (4..5).locate('a');
+    /// let rhs = (8..9).locate('b');
+    /// assert_eq!(lhs.merge(&rhs), 4..9);
+    /// assert_eq!(rhs.merge(&lhs), 4..9);
+    /// ```
+    #[inline]
+    pub fn merge<Y>(&self, other: &EasyLocated<Y>) -> Range<usize>
+    {
+        self.loc.start.min(other.loc.start)..self.loc.end.max(other.loc.end)
+    }
+
+    /// Pairs two located data, located by the smallest range covering both
+    /// (see [`EasyLocated::merge`]).
+    /// ```
+    /// use codespan_preprocessed::EasyLocator;
+    ///
+    /// let pair = (4..5).locate('a').cover((8..9).locate(2));
+    /// assert_eq!(*pair, ('a', 2));
+    /// assert_eq!(*pair.location(), 4..9);
+    /// ```
+    #[inline]
+    pub fn cover<Y>(self, other: EasyLocated<Y>) -> EasyLocated<(X,Y)>
+    {
+        let loc = self.merge(&other);
+        EasyLocated { inner: (self.inner, other.inner), loc }
+    }
 }
 
 impl<X> EasyLocated<Option<X>>
@@ -313,7 +364,7 @@ impl EasyLocator for Range<usize> {
     }
 }
 
-impl<'a> EasyLocator for &'a Range<usize> {
+impl EasyLocator for &Range<usize> {
 
     #[inline]
     fn locate<X>(&self, x: X) -> EasyLocated<X> {
@@ -329,7 +380,7 @@ impl<Y> EasyLocator for EasyLocated<Y> {
     }
 }
 
-impl<'a,Y> EasyLocator for &'a EasyLocated<Y> {
+impl<Y> EasyLocator for &EasyLocated<Y> {
 
     #[inline]
     fn locate<X>(&self, x: X) -> EasyLocated<X> {
@@ -354,4 +405,25 @@ mod tests {
         let y = x.transpose().unwrap();
         assert_eq! ( *y, 2);
     }
+
+    #[test]
+    fn merging()
+    {
+        let outer = (2..10).locate("outer");
+        let inner = (4..6).locate("inner");
+        assert_eq!(outer.merge(&inner), 2..10);
+        assert_eq!(inner.merge(&outer), 2..10);
+        // an empty location still counts
+        assert_eq!(inner.merge(&(12..12).locate(())), 4..12);
+        assert_eq!(*inner.cover(outer).location(), 2..10);
+    }
+
+    #[test]
+    fn fallible_mapping()
+    {
+        let x = (3..5).locate("42").try_map(|s| s.parse::<i32>()).unwrap();
+        assert_eq!((*x, x.location().clone()), (42, 3..5));
+        let err = (3..5).locate(-1_i32).try_map(u8::try_from).unwrap_err();
+        assert_eq!(*err.location(), 3..5);
+    }
 }
\ No newline at end of file
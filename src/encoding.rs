@@ -0,0 +1,87 @@
+use std::io;
+
+/// Text encoding of a preprocessed file.
+///
+/// A byte order mark (BOM) at the beginning of a file always
+/// takes precedence over the encoding given by the user.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Utf16Le,
+    Utf16Be
+}
+
+impl Encoding {
+
+    /// Detects the encoding from the byte order mark, if any.
+    ///
+    /// Returns the encoding and the length of the BOM.
+    pub fn detect(bytes: &[u8]) -> Option<(Encoding, usize)>
+    {
+        match bytes {
+            [0xEF, 0xBB, 0xBF, ..] => Some((Encoding::Utf8, 3)),
+            [0xFF, 0xFE, ..] => Some((Encoding::Utf16Le, 2)),
+            [0xFE, 0xFF, ..] => Some((Encoding::Utf16Be, 2)),
+            _ => None
+        }
+    }
+
+    /// Decodes the bytes into a string, stripping the BOM if any.
+    pub fn decode(self, mut bytes: Vec<u8>) -> Result<String, io::Error>
+    {
+        let encoding = match Encoding::detect(&bytes) {
+            Some((encoding, bom)) => { bytes.drain(..bom); encoding }
+            None => self
+        };
+        match encoding {
+            Encoding::Utf8 => String::from_utf8(bytes).map_err(invalid_data),
+            Encoding::Utf16Le => decode_utf16(&bytes, u16::from_le_bytes),
+            Encoding::Utf16Be => decode_utf16(&bytes, u16::from_be_bytes),
+        }
+    }
+}
+
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8;2]) -> u16) -> Result<String, io::Error>
+{
+    let chunks = bytes.chunks_exact(2);
+    if !chunks.remainder().is_empty() {
+        return Err(invalid_data("odd number of bytes in UTF-16 data"));
+    }
+    let units = chunks.map(|c| from_bytes([c[0], c[1]]));
+    char::decode_utf16(units)
+        .collect::<Result<String,_>>()
+        .map_err(invalid_data)
+}
+
+#[inline]
+fn invalid_data<E:Into<Box<dyn std::error::Error + Send + Sync>>>(e: E) -> io::Error
+{
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Encoding;
+
+    #[test]
+    fn byte_order_marks()
+    {
+        let utf8 = b"\xEF\xBB\xBF#line 1 \"a\"\n".to_vec();
+        assert_eq!(Encoding::Utf8.decode(utf8).unwrap(), "#line 1 \"a\"\n");
+
+        let utf16le = "\u{FEFF}#line 1 \"é\"\n".encode_utf16()
+            .flat_map(u16::to_le_bytes).collect::<Vec<_>>();
+        assert_eq!(Encoding::Utf8.decode(utf16le).unwrap(), "#line 1 \"é\"\n");
+
+        let utf16be = "\u{FEFF}x\n".encode_utf16()
+            .flat_map(u16::to_be_bytes).collect::<Vec<_>>();
+        assert_eq!(Encoding::Utf8.decode(utf16be).unwrap(), "x\n");
+
+        // without BOM, the given encoding is used
+        let utf16le = "x\n".encode_utf16()
+            .flat_map(u16::to_le_bytes).collect::<Vec<_>>();
+        assert_eq!(Encoding::Utf16Le.decode(utf16le).unwrap(), "x\n");
+        assert!(Encoding::Utf8.decode(vec![0xFF, 0x00, 0x41]).is_err());
+    }
+}
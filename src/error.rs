@@ -0,0 +1,143 @@
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+
+/// Error when building the codemap of a preprocessed file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CodemapError {
+    /// The line number of a directive is too large.
+    ///
+    /// The valid line numbers are those accepted by the C standard
+    /// (up to 2147483647), the GNU extension `#line 0` is accepted
+    /// as the first line.
+    LineNumberOutOfRange {
+        /// The bytes of the directive line
+        directive: Range<usize>,
+        /// The invalid line number (saturated to `u64::MAX`)
+        number: u64
+    },
+    /// A line directive could not be parsed (in strict mode).
+    ///
+    /// In permissive mode, such a line is left as ordinary text.
+    MalformedDirective {
+        /// The bytes of the directive line
+        directive: Range<usize>
+    },
+    /// The external source map is invalid.
+    InvalidSourceMap(String),
+    /// The source is too large for the line table
+    /// (4 GiB or more with the `compact` feature).
+    SourceTooLarge {
+        /// The length of the source
+        len: usize
+    }
+}
+
+impl Display for CodemapError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CodemapError::LineNumberOutOfRange { directive, number } =>
+                write!(f, "line number {} out of range in directive at bytes {}..{}",
+                       number, directive.start, directive.end),
+            CodemapError::MalformedDirective { directive } =>
+                write!(f, "malformed line directive at bytes {}..{}", directive.start, directive.end),
+            CodemapError::InvalidSourceMap(msg) =>
+                write!(f, "invalid source map: {}", msg),
+            CodemapError::SourceTooLarge { len } =>
+                write!(f, "source of {} bytes too large for the line table", len)
+        }
+    }
+}
+
+impl std::error::Error for CodemapError {}
+
+/// Error when a diagnostic could not be reported.
+#[cfg(feature = "reporting")]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ReportError {
+    /// The diagnostic could not be written.
+    Io(std::io::Error),
+    /// A location of the diagnostic is invalid for its source.
+    InvalidLocation(String)
+}
+
+#[cfg(feature = "reporting")]
+impl Display for ReportError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReportError::Io(err) => write!(f, "diagnostic not written: {}", err),
+            ReportError::InvalidLocation(msg) => write!(f, "invalid diagnostic location: {}", msg)
+        }
+    }
+}
+
+#[cfg(feature = "reporting")]
+impl std::error::Error for ReportError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReportError::Io(err) => Some(err),
+            ReportError::InvalidLocation(_) => None
+        }
+    }
+}
+
+#[cfg(feature = "reporting")]
+impl From<std::io::Error> for ReportError {
+    #[inline]
+    fn from(err: std::io::Error) -> Self { ReportError::Io(err) }
+}
+
+#[cfg(feature = "reporting")]
+impl From<codespan_reporting::files::Error> for ReportError {
+    fn from(err: codespan_reporting::files::Error) -> Self {
+        match err {
+            codespan_reporting::files::Error::Io(err) => ReportError::Io(err),
+            err => ReportError::InvalidLocation(err.to_string())
+        }
+    }
+}
+
+/// Error when some errors were reported
+/// (see [`EasyReporting::abort_if_errors`](crate::reporting::EasyReporting::abort_if_errors)).
+#[cfg(feature = "reporting")]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ErrorsReported {
+    /// The number of reported errors
+    pub errors: u32
+}
+
+#[cfg(feature = "reporting")]
+impl Display for ErrorsReported {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.errors {
+            1 => write!(f, "aborting due to 1 previous error"),
+            n => write!(f, "aborting due to {} previous errors", n)
+        }
+    }
+}
+
+#[cfg(feature = "reporting")]
+impl std::error::Error for ErrorsReported {}
+
+/// Error when two suggestions replace overlapping spans
+/// (see [`apply_suggestions`](crate::reporting::apply_suggestions)).
+#[cfg(feature = "reporting")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OverlapError {
+    /// The span of the first suggestion
+    pub first: Range<usize>,
+    /// The span of the second suggestion (overlapping the first one)
+    pub second: Range<usize>
+}
+
+#[cfg(feature = "reporting")]
+impl Display for OverlapError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "overlapping suggestions at bytes {}..{} and {}..{}",
+               self.first.start, self.first.end, self.second.start, self.second.end)
+    }
+}
+
+#[cfg(feature = "reporting")]
+impl std::error::Error for OverlapError {}
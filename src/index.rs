@@ -0,0 +1,268 @@
+use std::io::{self, Read, Write};
+use std::path::Path;
+use crate::PreprocessedFile;
+use crate::codemap::FileSlice;
+use crate::columns::Columns;
+use crate::lines::{check_len, LineTable};
+
+/// Header of the binary index files (with format version).
+const MAGIC: &[u8;8] = b"CSPPIDX3";
+
+/// Fingerprint of the contents (length and FNV-1a hash).
+fn fingerprint(contents: &str) -> (u64, u64)
+{
+    let hash = contents.bytes()
+        .fold(0xcbf29ce484222325_u64, |h, b| (h ^ b as u64).wrapping_mul(0x100000001b3));
+    (contents.len() as u64, hash)
+}
+
+fn invalid(msg: &str) -> io::Error
+{
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+struct Writer<W:Write>(W);
+
+impl<W:Write> Writer<W> {
+    fn u64(&mut self, x: u64) -> io::Result<()> { self.0.write_all(&x.to_le_bytes()) }
+    fn usize(&mut self, x: usize) -> io::Result<()> { self.u64(x as u64) }
+    fn isize(&mut self, x: isize) -> io::Result<()> { self.u64(x as i64 as u64) }
+    fn str(&mut self, x: &str) -> io::Result<()> { self.usize(x.len())?; self.0.write_all(x.as_bytes()) }
+}
+
+/// A reader of an untrusted index: all the lengths
+/// are checked against the remaining bytes.
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn u64(&mut self) -> io::Result<u64> {
+        let (bytes, rest) = self.0.split_first_chunk::<8>().ok_or_else(|| invalid("truncated codemap index"))?;
+        self.0 = rest;
+        Ok(u64::from_le_bytes(*bytes))
+    }
+    fn usize(&mut self) -> io::Result<usize> {
+        usize::try_from(self.u64()?).map_err(|_| invalid("offset too large"))
+    }
+    fn isize(&mut self) -> io::Result<isize> { Ok(self.u64()? as i64 as isize) }
+    /// Reads a number of items, each one of at least the given size.
+    fn count(&mut self, size: usize) -> io::Result<usize> {
+        let n = self.usize()?;
+        if n > self.0.len() / size { Err(invalid("truncated codemap index")) } else { Ok(n) }
+    }
+    fn str(&mut self) -> io::Result<String> {
+        let n = self.count(1)?;
+        let (bytes, rest) = self.0.split_at(n);
+        self.0 = rest;
+        String::from_utf8(bytes.to_vec()).map_err(|_| invalid("invalid UTF-8 name"))
+    }
+}
+
+impl<S:AsRef<str>> PreprocessedFile<S>
+{
+    /// Saves the codemap into a binary index file.
+    ///
+    /// The index could be loaded later with [`PreprocessedFile::load_index`]
+    /// to skip the scanning of the same contents (the names and the counting
+    /// of the columns are saved too).
+    pub fn save_index<P:AsRef<Path>>(&self, path: P) -> io::Result<()>
+    {
+        let mut out = io::BufWriter::new(std::fs::File::create(path)?);
+        self.write_index(&mut out)?;
+        out.flush()
+    }
+
+    /// Loads a codemap from a binary index file.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidData`] if the index was not
+    /// computed for the given contents.
+    pub fn load_index<P:AsRef<Path>>(path: P, contents: S) -> io::Result<Self>
+    {
+        Self::read_index(io::BufReader::new(std::fs::File::open(path)?), contents)
+    }
+
+    /// Writes the binary index of the codemap.
+    pub fn write_index<W:Write>(&self, out: W) -> io::Result<()>
+    {
+        let mut out = Writer(out);
+        out.0.write_all(MAGIC)?;
+        let (len, hash) = fingerprint(self.source());
+        out.u64(len)?;
+        out.u64(hash)?;
+
+        match &self.origin {
+            None => out.u64(0)?,
+            Some(origin) => { out.u64(1)?; out.str(origin)?; }
+        }
+        out.usize(self.aliases.len())?;
+        self.aliases.iter().try_for_each(|alias| out.str(alias))?;
+        out.u64(self.columns.unicode as u64)?;
+        out.u64(self.columns.tab_width.map_or(u64::MAX, |width| width as u64))?;
+
+        out.usize(self.lines.len())?;
+        self.lines.starts().try_for_each(|start| out.usize(start))?;
+        out.usize(self.lines.end())?;
+
+        out.usize(self.ids.len())?;
+        self.ids.iter().try_for_each(|id| {
+            out.usize(id.name.start)?;
+            out.usize(id.name.end)?;
+            out.u64(id.alias.map_or(u64::MAX, |alias| alias as u64))?;
+            out.u64(id.remapped as u64)?;
+            out.usize(id.bytes.start)?;
+            out.usize(id.bytes.end)?;
+            out.usize(id.lines.start)?;
+            out.usize(id.lines.end)?;
+            out.isize(id.offset)?;
+            out.isize(id.column)?;
+            out.u64(id.parent.map_or(u64::MAX, |parent| parent as u64))?;
+            out.u64(id.flags as u64)
+        })
+    }
+
+    /// Reads a binary index of the given contents.
+    ///
+    /// The index is checked to be consistent with the contents: a truncated
+    /// or corrupted index fails with [`io::ErrorKind::InvalidData`].
+    pub fn read_index<R:Read>(mut input: R, contents: S) -> io::Result<Self>
+    {
+        let mut data = vec![];
+        input.read_to_end(&mut data)?;
+        let mut input = Reader(&data);
+        if input.0.get(..MAGIC.len()) != Some(MAGIC) {
+            return Err(invalid("not a codemap index"));
+        }
+        input.0 = &input.0[MAGIC.len()..];
+        let text = contents.as_ref();
+        let (len, hash) = fingerprint(text);
+        if input.u64()? != len || input.u64()? != hash {
+            return Err(invalid("codemap index of other contents"));
+        }
+        let len = text.len();
+        check_len(len).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        let corrupted = || invalid("corrupted codemap index");
+
+        let origin = match input.u64()? {
+            0 => None,
+            _ => Some(input.str()?)
+        };
+        let aliases = (0..input.count(8)?).map(|_| input.str()).collect::<io::Result<Vec<_>>>()?;
+        let columns = Columns {
+            unicode: input.u64()? != 0,
+            tab_width: match input.u64()? {
+                u64::MAX => None,
+                width if width <= u32::MAX as u64 => Some(width as usize),
+                _ => return Err(invalid("invalid tab width"))
+            }
+        };
+
+        let starts = (0..input.count(8)?).map(|_| input.usize()).collect::<io::Result<Vec<_>>>()?;
+        let end = input.usize()?;
+        // the lines start at 0 and are sorted within the contents
+        if starts.first() != Some(&0) || starts.windows(2).any(|w| w[0] >= w[1])
+            || end > len || starts.last().is_some_and(|last| *last > end) {
+            return Err(corrupted());
+        }
+        let lines = LineTable::from_parts(starts, end);
+
+        let ids = (0..input.count(12*8)?).map(|_| {
+            Ok(FileSlice {
+                name: input.usize()? .. input.usize()?,
+                alias: match input.u64()? {
+                    u64::MAX => None,
+                    alias if (alias as usize) < aliases.len() => Some(alias as usize),
+                    _ => return Err(invalid("invalid alias"))
+                },
+                remapped: input.u64()? != 0,
+                qualified: None,
+                bytes: input.usize()? .. input.usize()?,
+                lines: input.usize()? .. input.usize()?,
+                offset: input.isize()?,
+                column: input.isize()?,
+                parent: match input.u64()? {
+                    u64::MAX => None,
+                    parent => Some(parent as usize)
+                },
+                flags: input.u64()? as u8
+            })
+        }).collect::<io::Result<Vec<_>>>()?;
+
+        // the slices are sorted (a parent before its children), within the contents and the lines,
+        // with bounded adjustments which keep the line numbers positive
+        let n = ids.len();
+        let bound = len.max(u32::MAX as usize);
+        let within = |range: &std::ops::Range<usize>, end: usize| range.start <= range.end && range.end <= end;
+        let valid = |(i, id): (usize, &FileSlice)| {
+            within(&id.name, len) && text.is_char_boundary(id.name.start) && text.is_char_boundary(id.name.end)
+                && within(&id.bytes, len) && within(&id.lines, lines.len())
+                && id.offset.unsigned_abs() <= bound && id.column.unsigned_abs() <= bound
+                && id.offset <= id.lines.start as isize
+                && id.parent.is_none_or(|p| p < i)
+                && (i == 0 || (ids[i-1].bytes.end <= id.bytes.start && ids[i-1].lines.end <= id.lines.start))
+        };
+        if n == 0 || !ids.iter().enumerate().all(valid) {
+            return Err(corrupted());
+        }
+        Ok(PreprocessedFile { ids, lines, contents, origin, aliases: aliases.into(), columns })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+    use codespan_reporting::files::Files;
+    use crate::{EasyLocation, PreprocessedFile};
+
+    #[test]
+    fn index_roundtrip()
+    {
+        let contents = "#line 1 \"/tmp/a\"\nfirst\n#line 10 \"b\"\nsecond\n";
+        let file = PreprocessedFile::new(contents).with_path_remap("/tmp", ".")
+            .with_unicode_columns(true).with_tab_width(4);
+        let mut index = Vec::new();
+        file.write_index(&mut index).unwrap();
+
+        let loaded = PreprocessedFile::read_index(index.as_slice(), contents).unwrap();
+        for byte in [17, 40] {
+            let (id, loaded_id) = (file.file_id(byte), loaded.file_id(byte));
+            assert_eq!(id, loaded_id);
+            assert_eq!(file.name(id).unwrap(), loaded.name(loaded_id).unwrap());
+            assert_eq!(file.location(id, byte).unwrap(), loaded.location(loaded_id, byte).unwrap());
+        }
+        // the columns are counted the same way
+        assert_eq!(loaded.columns, file.columns);
+
+        // the fingerprint does not match
+        assert!(PreprocessedFile::read_index(index.as_slice(), "other contents").is_err());
+    }
+
+    #[test]
+    fn corrupted_index()
+    {
+        let contents = "# 1 \"a.c\"\nint a;\n# 1 \"b.h\" 1\nint b;\n# 2 \"a.c\" 2\n#line 7 \"é.c\"\nint c;\n";
+        let file = PreprocessedFile::new(contents).with_path_remap("", "./");
+        let mut index = Vec::new();
+        file.write_index(&mut index).unwrap();
+
+        // the corrupted index is rejected or, at least, usable without panic
+        let check = |index: &[u8]| match PreprocessedFile::read_index(index, contents) {
+            Ok(loaded) => for byte in 0..=contents.len() {
+                let id = loaded.file_id(byte);
+                let _ = (loaded.name(id), loaded.location(id, byte), loaded.included_from(id));
+                let _ = loaded.include_tree();
+            },
+            Err(err) => assert_eq!(err.kind(), ErrorKind::InvalidData)
+        };
+        for end in 0..index.len() {
+            check(&index[..end]);
+        }
+        for bit in 0..8*index.len() {
+            let mut flipped = index.clone();
+            flipped[bit / 8] ^= 1 << (bit % 8);
+            check(&flipped);
+        }
+        // a huge length is not allocated
+        let mut huge = index.clone();
+        huge[32..40].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert_eq!(PreprocessedFile::read_index(huge.as_slice(), contents).unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+}
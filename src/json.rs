@@ -0,0 +1,273 @@
+use std::fmt::Write;
+
+/// Writes a JSON string literal (with quotes).
+pub(crate) fn write_string(out: &mut String, s: &str)
+{
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => { let _ = write!(out, "\\u{:04x}", c as u32); }
+            c => out.push(c)
+        }
+    }
+    out.push('"');
+}
+
+/// Gets a JSON string literal (with quotes).
+#[inline]
+pub(crate) fn string(s: &str) -> String
+{
+    let mut out = String::with_capacity(s.len()+2);
+    write_string(&mut out, s);
+    out
+}
+
+/// A parsed JSON value.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>)
+}
+
+impl Value {
+
+    /// Gets the field of an object.
+    pub(crate) fn get(&self, key: &str) -> Option<&Value>
+    {
+        match self {
+            Value::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None
+        }
+    }
+
+    #[inline]
+    pub(crate) fn as_str(&self) -> Option<&str>
+    {
+        if let Value::String(s) = self { Some(s) } else { None }
+    }
+
+    #[inline]
+    pub(crate) fn as_array(&self) -> Option<&[Value]>
+    {
+        if let Value::Array(a) = self { Some(a) } else { None }
+    }
+}
+
+/// Parses a JSON text.
+pub(crate) fn parse(text: &str) -> Result<Value, String>
+{
+    let mut parser = Parser { text, pos: 0 };
+    let value = parser.value()?;
+    parser.blanks();
+    if parser.pos < text.len() {
+        return Err(format!("unexpected trailing characters at {}", parser.pos));
+    }
+    Ok(value)
+}
+
+struct Parser<'t> {
+    text: &'t str,
+    pos: usize
+}
+
+impl Parser<'_> {
+
+    fn blanks(&mut self)
+    {
+        let rest = &self.text[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn peek(&mut self) -> Option<char>
+    {
+        self.blanks();
+        self.text[self.pos..].chars().next()
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String>
+    {
+        match self.peek() {
+            Some(x) if x == c => { self.pos += 1; Ok(()) }
+            _ => Err(format!("expected '{}' at {}", c, self.pos))
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String>
+    {
+        match self.peek() {
+            Some('{') => {
+                self.pos += 1;
+                let mut fields = vec![];
+                if self.peek() == Some('}') { self.pos += 1; return Ok(Value::Object(fields)); }
+                loop {
+                    let key = self.string()?;
+                    self.expect(':')?;
+                    fields.push((key, self.value()?));
+                    match self.peek() {
+                        Some(',') => self.pos += 1,
+                        Some('}') => { self.pos += 1; return Ok(Value::Object(fields)); }
+                        _ => return Err(format!("expected ',' or '}}' at {}", self.pos))
+                    }
+                }
+            }
+            Some('[') => {
+                self.pos += 1;
+                let mut items = vec![];
+                if self.peek() == Some(']') { self.pos += 1; return Ok(Value::Array(items)); }
+                loop {
+                    items.push(self.value()?);
+                    match self.peek() {
+                        Some(',') => self.pos += 1,
+                        Some(']') => { self.pos += 1; return Ok(Value::Array(items)); }
+                        _ => return Err(format!("expected ',' or ']' at {}", self.pos))
+                    }
+                }
+            }
+            Some('"') => self.string().map(Value::String),
+            Some(_) => {
+                let rest = &self.text[self.pos..];
+                let len = rest.find(|c: char| !(c.is_ascii_alphanumeric() || "+-.".contains(c))).unwrap_or(rest.len());
+                let value = match &rest[..len] {
+                    "null" => Value::Null,
+                    "true" => Value::Bool(true),
+                    "false" => Value::Bool(false),
+                    number => Value::Number(parse_number(number)
+                        .ok_or_else(|| format!("invalid value at {}", self.pos))?)
+                };
+                self.pos += len;
+                Ok(value)
+            }
+            None => Err("unexpected end of JSON text".to_string())
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String>
+    {
+        self.expect('"')?;
+        let mut s = String::new();
+        let mut chars = self.text[self.pos..].char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => { self.pos += i+1; return Ok(s); }
+                '\\' => match chars.next().map(|(_, c)| c) {
+                    Some(c @ ('"' | '\\' | '/')) => s.push(c),
+                    Some('n') => s.push('\n'),
+                    Some('r') => s.push('\r'),
+                    Some('t') => s.push('\t'),
+                    Some('b') => s.push('\u{8}'),
+                    Some('f') => s.push('\u{c}'),
+                    Some('u') => {
+                        let high = hex4(&mut chars)?;
+                        let code = match high {
+                            // a surrogate pair encodes a character outside the BMP
+                            0xd800..=0xdbff => match (chars.next(), chars.next()) {
+                                (Some((_, '\\')), Some((_, 'u'))) => match hex4(&mut chars)? {
+                                    low @ 0xdc00..=0xdfff => 0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00),
+                                    _ => return Err("invalid surrogate pair".to_string())
+                                }
+                                _ => return Err("unpaired surrogate".to_string())
+                            }
+                            0xdc00..=0xdfff => return Err("unpaired surrogate".to_string()),
+                            code => code
+                        };
+                        s.push(char::from_u32(code).ok_or_else(|| "invalid unicode escape".to_string())?);
+                    }
+                    Some(c) => return Err(format!("invalid escape '\\{}' at {}", c, self.pos + i)),
+                    None => break
+                }
+                c if (c as u32) < 0x20 => return Err(format!("control character in string at {}", self.pos + i)),
+                c => s.push(c)
+            }
+        }
+        Err("unterminated JSON string".to_string())
+    }
+}
+
+/// Reads the four hexadecimal digits of a unicode escape.
+fn hex4(chars: &mut std::str::CharIndices) -> Result<u32, String>
+{
+    let hex = chars.by_ref().take(4).map(|(_, c)| c).collect::<String>();
+    if hex.len() != 4 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err("invalid unicode escape".to_string());
+    }
+    Ok(u32::from_str_radix(&hex, 16).unwrap())
+}
+
+/// Parses a number with the JSON grammar (no `inf`, `NaN`, `+1`, `.5`, `01`...).
+fn parse_number(number: &str) -> Option<f64>
+{
+    fn digits(s: &str) -> (&str, &str) {
+        s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()))
+    }
+    let rest = number.strip_prefix('-').unwrap_or(number);
+    let (int, mut rest) = digits(rest);
+    if int.is_empty() || (int.len() > 1 && int.starts_with('0')) {
+        return None;
+    }
+    if let Some(frac) = rest.strip_prefix('.') {
+        let (frac, tail) = digits(frac);
+        if frac.is_empty() { return None; }
+        rest = tail;
+    }
+    if let Some(exp) = rest.strip_prefix(['e', 'E']) {
+        let (exp, tail) = digits(exp.strip_prefix(['+', '-']).unwrap_or(exp));
+        if exp.is_empty() { return None; }
+        rest = tail;
+    }
+    // an overflowing number is not representable either
+    number.parse().ok().filter(|x: &f64| rest.is_empty() && x.is_finite())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, string, Value};
+
+    #[test]
+    fn roundtrip()
+    {
+        let text = format!("{{\"a\": [1, -2.5, true, null], \"b\": {}}}", string("q\"\\\n\u{1}"));
+        let value = parse(&text).unwrap();
+        assert_eq!(value.get("a"), Some(&Value::Array(vec![
+            Value::Number(1.), Value::Number(-2.5), Value::Bool(true), Value::Null
+        ])));
+        assert_eq!(value.get("b").and_then(Value::as_str), Some("q\"\\\n\u{1}"));
+        assert!(parse("{\"a\": }").is_err());
+    }
+
+    #[test]
+    fn escapes()
+    {
+        assert_eq!(parse(r#""\/\"\\\u00e9""#), Ok(Value::String("/\"\\é".to_string())));
+        // a surrogate pair is combined into one character
+        assert_eq!(parse(r#""\ud83d\ude00""#), Ok(Value::String("😀".to_string())));
+        assert!(parse(r#""\ud83d""#).is_err());
+        assert!(parse(r#""\ud83dx""#).is_err());
+        assert!(parse(r#""\ud83d\u0041""#).is_err());
+        assert!(parse(r#""\ude00""#).is_err());
+        assert!(parse(r#""\u12""#).is_err());
+        assert!(parse(r#""\u+123""#).is_err());
+        assert!(parse(r#""\x41""#).is_err());
+        assert!(parse(r#""\a""#).is_err());
+        assert!(parse("\"a\nb\"").is_err());
+    }
+
+    #[test]
+    fn numbers()
+    {
+        assert_eq!(parse("[0, -0.5, 1e3, 2.5E-1]"), Ok(Value::Array(vec![
+            Value::Number(0.), Value::Number(-0.5), Value::Number(1e3), Value::Number(0.25)
+        ])));
+        for invalid in ["inf", "-inf", "NaN", "infinity", "1e999", "+1", ".5", "1.", "01", "1e", "-"] {
+            assert!(parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+}
@@ -20,6 +20,8 @@
 //! # Example
 //!
 //!```
+//! # #[cfg(feature = "reporting")]
+//! # fn main() {
 //! use codespan_preprocessed::reporting::Diagnostic;
 //! use codespan_preprocessed::PreprocessedFile;
 //!
@@ -45,6 +47,9 @@
 //!        .with_message("this is just an example")
 //!        .with_primary_label(113..117, "do you see that ?")
 //!        .with_secondary_label(21..26, "is it related to this ?");
+//! # }
+//! # #[cfg(not(feature = "reporting"))]
+//! # fn main() {}
 //!```
 //!
 //! ### Reporting a diagnostic
@@ -52,9 +57,11 @@
 //!```
 //! use codespan_reporting::term;
 //! use codespan_reporting::term::termcolor::{ColorChoice, StandardStream};
+//! # #[cfg(feature = "reporting")]
 //! # use codespan_preprocessed::reporting::*;
 //! # use codespan_preprocessed::PreprocessedFile;
 //!
+//! # #[cfg(feature = "reporting")]
 //! # fn main()
 //! # {
 //! #   let contents = PreprocessedFile::new("");
@@ -63,6 +70,8 @@
 //! let config = codespan_reporting::term::Config::default();
 //! term::emit(&mut writer.lock(), &config, &contents, &diagnostic.to_diagnostic(&contents));
 //! # }
+//! # #[cfg(not(feature = "reporting"))]
+//! # fn main() {}
 //!```
 //! ### Easy reporting (alternative)
 //! This crate provides an easier way to report diagnostic based on
@@ -70,9 +79,11 @@
 //! ```
 //! # use codespan_reporting::term;
 //! # use codespan_reporting::term::termcolor::{ColorChoice, StandardStream};
+//! # #[cfg(feature = "reporting")]
 //! use codespan_preprocessed::reporting::{Diagnostic,EasyReport,EasyReporting};
 //! # use codespan_preprocessed::PreprocessedFile;
 //!
+//! # #[cfg(feature = "reporting")]
 //! # fn main()
 //! # {
 //! #   let contents = PreprocessedFile::new("");
@@ -80,6 +91,8 @@
 //! let report = EasyReporting::new(&contents);
 //! report.emit(diagnostic);
 //! # }
+//! # #[cfg(not(feature = "reporting"))]
+//! # fn main() {}
 //! ```
 //! ### Output
 //! The both previous codes will produce:
@@ -95,10 +108,45 @@
 //! 1 │ a first statement;
 //!   │   ----- is it related to this ?
 //! ```
+//!
+//! # Features
+//! * `reporting` (default): the [`reporting`] of the diagnostics; without it, only the codemap
+//!   (the [`Files`](codespan_reporting::files::Files) of `codespan_reporting`) is available.
+//!   Note that `codespan-reporting` (and so `termcolor`) is still a dependency
+//!   with `--no-default-features`, as the codemap implements its `Files` trait
+//! * `mmap`: memory-mapped sources (see [`PreprocessedFile::open_mmap`](PreprocessedFile))
+//! * `tokio`: asynchronous loading (see [`PreprocessedFile::open_async`](PreprocessedFile))
+//! * `sarif`: SARIF output of the diagnostics (see [`reporting::SarifReport`](reporting))
+//! * `miette`: the codemap as a `miette::SourceCode` and the diagnostics as miette ones
+//! * `ariadne`: an alternative rendering of the diagnostics by ariadne
+//! * `compact`: line table stored with 32 bits offsets (the sources should be less than 4 GiB)
+#[cfg(feature = "tokio")]
+mod async_io;
+mod builder;
 mod codemap;
+mod columns;
+mod directive;
 mod easyloc;
+mod encoding;
+mod error;
+mod index;
+mod json;
+mod lines;
+#[cfg(feature = "mmap")]
+mod mmap;
+#[cfg(feature = "reporting")]
 pub mod reporting;
+mod sourcemap;
+mod stream;
+mod strip;
 
-pub use codemap::PreprocessedFile;
+pub use codemap::{FileSlice, IncludeNode, PreprocessedFile, SpanCrossesFiles};
+pub use builder::PreprocessedFileBuilder;
+#[cfg(feature = "mmap")]
+pub use mmap::MappedSource;
 pub use codemap::EasyLocation;
+pub use encoding::Encoding;
+pub use stream::PreprocessedStream;
+pub use strip::SpanRemapper;
+pub use error::CodemapError;
 pub use easyloc::{EasyLocated,EasyLocator};
\ No newline at end of file
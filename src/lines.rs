@@ -0,0 +1,205 @@
+use std::ops::Range;
+use crate::CodemapError;
+
+// with the `compact` feature, the line starts are stored as u32
+// (so the source should be less than 4 GiB)
+#[cfg(feature = "compact")]
+type Offset = u32;
+#[cfg(not(feature = "compact"))]
+type Offset = usize;
+
+/// The table of the lines of a source.
+///
+/// Only the line starts are stored, the ranges of
+/// the lines (without EOL) are computed on demand.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct LineTable {
+    starts: Vec<Offset>,
+    end: usize
+}
+
+impl LineTable {
+
+    #[inline]
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub(crate) fn new(source: &str) -> Self
+    {
+        Self::scan(source, &[], false).0
+    }
+
+    /// Builds the line table and finds the candidate directive lines.
+    ///
+    /// A candidate is a line starting with one of the given markers
+    /// (possibly after some blanks if `indented` is set).
+    /// Both are found in a single pass over the source.
+    pub(crate) fn scan(source: &str, markers: &[u8], indented: bool) -> (Self, Vec<usize>)
+    {
+        let mut lines = Self::default();
+        let candidates = lines.scan_from(source, 0, markers, indented);
+        (lines, candidates)
+    }
+
+    /// Extends the line table with the lines of the source from the given
+    /// byte and finds their candidate directive lines.
+    ///
+    /// The source before this byte should be already scanned and
+    /// terminated by an EOL (so the new lines start there).
+    pub(crate) fn scan_from(&mut self, source: &str, from: usize, markers: &[u8], indented: bool) -> Vec<usize>
+    {
+        let bytes = source.as_bytes();
+        let is_candidate = |start: usize| {
+            let first = if indented {
+                bytes[start..].iter().position(|b| *b != b' ' && *b != b'\t').map_or(bytes.len(), |n| start+n)
+            } else {
+                start
+            };
+            bytes.get(first).is_some_and(|b| markers.contains(b))
+        };
+        // the start of the new line was popped (or is the start of an empty source)
+        if self.starts.last().is_none_or(|last| byte(*last) < from) {
+            self.starts.push(offset(from));
+        }
+        let mut candidates = vec![];
+        if is_candidate(from) { candidates.push(self.starts.len()-1); }
+        for b in memchr::memchr_iter(b'\n', &bytes[from..]).map(|b| from+b) {
+            if is_candidate(b+1) { candidates.push(self.starts.len()); }
+            self.starts.push(offset(b+1));
+        }
+
+        // if the last line is terminated with an EOL,
+        // there is no more line behind it
+        self.end = if source.ends_with('\n') {
+            self.starts.pop();
+            source.len() - 1
+        } else {
+            source.len()
+        };
+        candidates
+    }
+
+    /// Rebuilds a line table from its line starts and end.
+    pub(crate) fn from_parts(starts: impl IntoIterator<Item=usize>, end: usize) -> Self
+    {
+        Self { starts: starts.into_iter().map(offset).collect(), end }
+    }
+
+    /// Number of lines.
+    #[inline]
+    pub(crate) fn len(&self) -> usize { self.starts.len() }
+
+    /// Starts of the lines.
+    #[inline]
+    pub(crate) fn starts(&self) -> impl Iterator<Item=usize> + '_
+    {
+        self.starts.iter().map(|start| byte(*start))
+    }
+
+    /// End of the last line (without EOL).
+    #[inline]
+    pub(crate) fn end(&self) -> usize { self.end }
+
+    /// Range of a line (without EOL).
+    pub(crate) fn get(&self, line: usize) -> Option<Range<usize>>
+    {
+        let start = byte(*self.starts.get(line)?);
+        match self.starts.get(line+1) {
+            Some(next) => Some(start .. byte(*next) - 1),
+            None => Some(start .. self.end)
+        }
+    }
+
+    /// Range of the last line.
+    #[inline]
+    pub(crate) fn last(&self) -> Range<usize>
+    {
+        self.get(self.len()-1).unwrap()
+    }
+
+    /// Index of the line containing the given byte, searched
+    /// only among the given lines (which should contain it).
+    ///
+    /// The EOL belongs to the line it terminates.
+    #[inline]
+    pub(crate) fn line_within(&self, byte_index: usize, lines: Range<usize>) -> usize
+    {
+        let first = lines.start;
+        first + self.starts[lines].partition_point(|start| byte(*start) <= byte_index).saturating_sub(1)
+    }
+}
+
+/// Checks that a source of the given length could be indexed
+/// by the line table (always, unless with the `compact` feature).
+#[inline]
+pub(crate) fn check_len(len: usize) -> Result<(), CodemapError>
+{
+    #[allow(clippy::useless_conversion)]
+    Offset::try_from(len).map(|_| ()).map_err(|_| CodemapError::SourceTooLarge { len })
+}
+
+#[inline]
+fn offset(byte_index: usize) -> Offset
+{
+    #[allow(clippy::useless_conversion)]
+    Offset::try_from(byte_index).expect("source too large for compact line table")
+}
+
+#[inline]
+#[allow(clippy::unnecessary_cast)]
+fn byte(offset: Offset) -> usize { offset as usize }
+
+#[cfg(test)]
+mod tests {
+    use super::{check_len, LineTable};
+
+    #[test]
+    fn line_ranges()
+    {
+        let lines = LineTable::new("ab\n\ncd");
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines.get(0), Some(0..2));
+        assert_eq!(lines.get(1), Some(3..3));
+        assert_eq!(lines.last(), 4..6);
+        assert_eq!(lines.line_within(2, 0..3), 0);
+        assert_eq!(lines.line_within(5, 1..3), 2);
+
+        let lines = LineTable::new("ab\n");
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines.last(), 0..2);
+
+        let lines = LineTable::new("");
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines.last(), 0..0);
+    }
+
+    #[test]
+    fn candidate_lines()
+    {
+        let (_, candidates) = LineTable::scan("#line 1\na # b\n@col 2\n#\n", b"#", false);
+        assert_eq!(candidates, vec![0, 3]);
+        let (_, candidates) = LineTable::scan("#line 1\na # b\n@col 2\n#\n", b"#@", false);
+        assert_eq!(candidates, vec![0, 2, 3]);
+        let (_, candidates) = LineTable::scan("  #line 1\na # b\n\t#\n", b"#", true);
+        assert_eq!(candidates, vec![0, 2]);
+    }
+
+    #[test]
+    fn appended_lines()
+    {
+        let source = "#a\nb\n#c\nd";
+        let (whole, _) = LineTable::scan(source, b"#", false);
+        let mut lines = LineTable::new("");
+        assert_eq!(lines.scan_from(&source[..3], 0, b"#", false), vec![0]);
+        assert_eq!(lines.scan_from(&source[..8], 3, b"#", false), vec![2]);
+        assert!(lines.scan_from(source, 8, b"#", false).is_empty());
+        assert_eq!(lines.starts, whole.starts);
+        assert_eq!(lines.end, whole.end);
+    }
+
+    #[test]
+    fn source_length()
+    {
+        assert!(check_len(u32::MAX as usize).is_ok());
+        #[cfg(feature = "compact")]
+        assert_eq!(check_len(u32::MAX as usize + 1), Err(crate::CodemapError::SourceTooLarge { len: u32::MAX as usize + 1 }));
+    }
+}
@@ -0,0 +1,84 @@
+use std::fmt::{Debug, Formatter};
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use memmap2::Mmap;
+use crate::{Encoding, PreprocessedFile};
+
+/// Memory-mapped contents of a preprocessed file.
+///
+/// The mapping is checked to be valid UTF-8 when it is opened,
+/// so it can be used as the source of a [`PreprocessedFile`]
+/// without copying the whole file into a `String`.
+pub struct MappedSource {
+    map: Mmap,
+    bom: usize
+}
+
+impl MappedSource {
+    /// Maps the given file into memory.
+    ///
+    /// The file should not be modified while it is mapped
+    /// (see [`memmap2::Mmap`] for more details).
+    pub fn open<P: AsRef<Path>>(filename: P) -> Result<Self, io::Error>
+    {
+        let file = File::open(filename)?;
+        // SAFETY: the mapped file is expected to be left unchanged
+        // as long as the mapping is alive (documented above)
+        let map = unsafe { Mmap::map(&file)? };
+        // a mapped file can't be transcoded, only UTF-8 BOM is stripped
+        let bom = match Encoding::detect(&map) {
+            None => 0,
+            Some((Encoding::Utf8, bom)) => bom,
+            Some(_) => return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                                 "UTF-16 file can't be memory-mapped"))
+        };
+        std::str::from_utf8(&map[bom..])
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(Self { map, bom })
+    }
+}
+
+impl AsRef<str> for MappedSource {
+    #[inline]
+    fn as_ref(&self) -> &str {
+        // SAFETY: the UTF-8 validity was checked when opening
+        unsafe { std::str::from_utf8_unchecked(&self.map[self.bom..]) }
+    }
+}
+
+impl Debug for MappedSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MappedSource").field("len", &self.map.len()).finish()
+    }
+}
+
+impl PreprocessedFile<MappedSource>
+{
+    /// Opens a preprocessed file by mapping it into memory.
+    ///
+    /// This behaves as [`PreprocessedFile::open`] except that the
+    /// contents are not copied: it is well suited to very large
+    /// preprocessor outputs.
+    pub fn open_mmap<P: AsRef<Path>>(filename: P) -> Result<Self, io::Error>
+    {
+        let contents = MappedSource::open(&filename)?;
+        Ok(PreprocessedFile::load(contents)?
+            .with_default_name(filename.as_ref().to_string_lossy()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use codespan_reporting::files::Files;
+    use crate::{EasyLocation, PreprocessedFile};
+
+    #[test]
+    fn mapped_file()
+    {
+        let file = PreprocessedFile::open_mmap("examples/readme.rs").unwrap();
+        let id = file.file_id(131);
+        assert_eq!(file.name(id).unwrap(), "examples/readme.rs");
+        assert_eq!(file.source(), std::fs::read_to_string("examples/readme.rs").unwrap());
+    }
+}
@@ -0,0 +1,163 @@
+use std::fmt::{Debug, Display};
+use std::ops::Range;
+use ariadne::{Cache, Config, IndexType, Label, Report, ReportKind, Source};
+use codespan_reporting::diagnostic::{LabelStyle, Severity};
+use codespan_reporting::files::{self, Files};
+use crate::codemap::EasyLocation;
+use super::Diagnostic;
+
+/// A span of an ariadne report.
+///
+/// The bytes are relative to the start of the file
+/// (see [`EasyLocation::file_bytes`]).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AriadneSpan<Id> {
+    id: Id,
+    range: Range<usize>
+}
+
+impl<Id:PartialEq + ToOwned> ariadne::Span for AriadneSpan<Id>
+{
+    type SourceId = Id;
+
+    #[inline]
+    fn source(&self) -> &Id { &self.id }
+
+    #[inline]
+    fn start(&self) -> usize { self.range.start }
+
+    #[inline]
+    fn end(&self) -> usize { self.range.end }
+}
+
+/// The sources of the ariadne reports.
+///
+/// Each file is loaded on demand as an ariadne source numbered
+/// as its original file (the column adjustments are not supported).
+///
+/// Note that ariadne 0.6 applies the line numbering to the locations
+/// of the labels but not to the margin of the snippets.
+pub struct AriadneCache<'a,L:EasyLocation<'a>>
+{
+    source: &'a L,
+    files: Vec<(<L as Files<'a>>::FileId, Source<String>)>
+}
+
+impl<'a,L:EasyLocation<'a>> AriadneCache<'a,L>
+{
+    #[inline]
+    pub fn new(source: &'a L) -> Self
+    {
+        Self { source, files: vec![] }
+    }
+}
+
+impl<'a,L:EasyLocation<'a>> Cache<<L as Files<'a>>::FileId> for AriadneCache<'a,L>
+{
+    type Storage = String;
+
+    fn fetch(&mut self, id: &<L as Files<'a>>::FileId) -> Result<&Source<String>, impl Debug>
+    {
+        let index = match self.files.iter().position(|(other, _)| other == id) {
+            Some(index) => index,
+            None => {
+                let bytes = self.source.file_bytes(*id);
+                let text = self.source.source(*id)?.as_ref()
+                    .get(bytes.clone())
+                    .ok_or(files::Error::FileMissing)?
+                    .to_string();
+                let line = self.source.line_index(*id, bytes.start)?;
+                self.files.push((*id, Source::from(text).with_display_line_offset(line)));
+                self.files.len()-1
+            }
+        };
+        Ok::<_, files::Error>(&self.files[index].1)
+    }
+
+    fn display<'b>(&self, id: &'b <L as Files<'a>>::FileId) -> Option<impl Display + 'b>
+    {
+        self.source.name(*id).ok().map(|name| name.to_string())
+    }
+}
+
+impl<E:Display> Diagnostic<E>
+{
+    /// Converts this diagnostic into an ariadne report
+    /// (to be written with an [`AriadneCache`] of the same source).
+    /// ```
+    /// use codespan_preprocessed::PreprocessedFile;
+    /// use codespan_preprocessed::reporting::{AriadneCache, Diagnostic};
+    ///
+    /// let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a;\n");
+    /// let mut output = vec![];
+    /// Diagnostic::error().with_message("oops")
+    ///     .with_primary_label(18..19, "here")
+    ///     .to_ariadne(&file, ariadne::Config::default().with_color(false))
+    ///     .write(AriadneCache::new(&file), &mut output)
+    ///     .unwrap();
+    /// assert!(String::from_utf8(output).unwrap().contains("a.c:1:5"));
+    /// ```
+    pub fn to_ariadne<'a,L:EasyLocation<'a>>(mut self, src: &'a L, config: Config)
+        -> Report<'static, AriadneSpan<<L as Files<'a>>::FileId>>
+    {
+        let notes = self.take_notes(src);
+        let span = |id, range: Range<usize>| {
+            let start = src.file_bytes(id).start;
+            AriadneSpan { id, range: range.start - start..range.end - start }
+        };
+        // a diagnostic without label is located at the start of the first file
+        let location = self.primary_location()
+            .or_else(|| self.labels.first().map(|(_, range, _)| range))
+            .map_or_else(|| src.file_bytes(src.file_id(0)).start, |range| range.start);
+        let kind = match self.severity {
+            Severity::Bug | Severity::Error => ReportKind::Error,
+            Severity::Warning => ReportKind::Warning,
+            Severity::Note | Severity::Help => ReportKind::Advice
+        };
+        let code = self.code.to_string();
+        let mut report = Report::build(kind, span(src.file_id(location), location..location))
+            .with_config(config.with_index_type(IndexType::Byte))
+            .with_message(self.message)
+            .with_labels(self.labels
+                .into_iter()
+                .flat_map(|(style, range, message)| {
+                    // as for codespan, a label crossing a file boundary is split
+                    let mut message = Some(message);
+                    src.file_spans(range)
+                        .into_iter()
+                        .map(move |(id, range)| (style, span(id, range), message.take().unwrap_or_default()))
+                })
+                .map(|(style, span, message)| {
+                    let label = Label::new(span)
+                        .with_priority(if style == LabelStyle::Primary { 1 } else { 0 });
+                    if message.is_empty() { label } else { label.with_message(message) }
+                }));
+        if !code.is_empty() {
+            report = report.with_code(code);
+        }
+        report.with_notes(notes);
+        report.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::PreprocessedFile;
+    use crate::reporting::{Diagnostic, EasyReport, EasyReporting, Renderer};
+
+    #[test]
+    fn ariadne_rendering()
+    {
+        let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a;\n#line 7 \"b.h\"\nint b;\n");
+        let report = EasyReporting::to_buffer(&file).with_renderer(Renderer::Ariadne);
+        report.emit(Diagnostic::warning().with_code("W1").with_message("unused")
+            .with_primary_label(39..40, "here")
+            .with_secondary_label(18..19, "also")
+            .with_note("remove it"));
+        let rendered = report.rendered();
+        assert!(rendered.starts_with("[W1] Warning: unused\n"));
+        assert!(rendered.contains("b.h:7:5"));
+        assert!(rendered.contains("a.c:1:5"));
+        assert!(rendered.contains("Note: remove it"));
+    }
+}
@@ -0,0 +1,292 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{Display, Formatter, Write as _};
+use codespan_reporting::diagnostic::Severity;
+use codespan_reporting::files::Location;
+use crate::codemap::EasyLocation;
+use crate::json;
+use super::Diagnostic;
+use super::resolved::{level, resolve, Resolved, ResolvedLabel};
+
+/// A diagnostic of a run, identified across the runs by a fingerprint
+/// (see [`FindingsDiff`]).
+///
+/// The fingerprint hashes the code (or the message without code), the
+/// original file and the text of the line of the primary label: it is
+/// resilient to the line shifts, but not to a change of the line itself.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Finding {
+    pub severity: Severity,
+    /// The code (possibly empty)
+    pub code: String,
+    pub message: String,
+    /// The original file of the primary label (empty without location)
+    pub file: String,
+    /// The original line of the primary label (0 without location)
+    pub line: usize,
+    /// The original column of the primary label (0 without location)
+    pub column: usize,
+    pub fingerprint: u64,
+    /// The resolved primary label (for the serialization)
+    span: Option<ResolvedLabel>
+}
+
+impl Finding {
+
+    /// Identifies a diagnostic located in the given source.
+    pub fn new<'a,L:EasyLocation<'a>,E:Display>(source: &'a L, diag: &Diagnostic<E>) -> Self
+    {
+        let code = diag.code.to_string();
+        let (mut span, mut context) = (None, String::new());
+        if let Some(range) = diag.primary_location() {
+            let id = source.file_id(range.start);
+            if let (Ok(text), Ok(index)) = (source.source(id), source.line_index(id, range.start)) {
+                if let Ok(bytes) = source.line_range(id, index) {
+                    context = text.as_ref()[bytes].trim().to_string();
+                }
+            }
+            span = Some(resolve(source, id, true, range.clone(), String::new()));
+        }
+        let (file, line, column) = span.as_ref()
+            .map_or((String::new(), 0, 0), |span| (span.file.clone(), span.start.line_number, span.start.column_number));
+        let mut hash = Fnv::default();
+        hash.write(if code.is_empty() { diag.message.as_bytes() } else { code.as_bytes() });
+        hash.write(file.as_bytes());
+        hash.write(context.as_bytes());
+        Finding { severity: diag.severity, code, message: diag.message.clone(), file, line, column, fingerprint: hash.0, span }
+    }
+
+    /// Serializes the finding as a JSON object (on one line), as to save
+    /// the findings of a run as a baseline.
+    ///
+    /// The object is the one of a [`JsonReport`](super::JsonReport)
+    /// (with the primary label only) completed with the `fingerprint`.
+    pub fn to_json(&self) -> String
+    {
+        let diag = Resolved {
+            severity: self.severity,
+            code: self.code.clone(),
+            message: self.message.clone(),
+            labels: self.span.iter().cloned().collect(),
+            notes: vec![],
+            suggestions: vec![],
+            children: vec![],
+            timestamp: None
+        };
+        let mut out = super::json::to_json(&diag);
+        out.pop(); // the closing brace
+        let _ = write!(out, r#","fingerprint":"{:016x}"}}"#, self.fingerprint);
+        out
+    }
+
+    /// Parses a finding serialized by [`Finding::to_json`].
+    pub fn from_json(text: &str) -> Option<Self>
+    {
+        let value = json::parse(text).ok()?;
+        let string = |value: &json::Value, key| value.get(key).and_then(json::Value::as_str).map(str::to_string);
+        let number = |value: &json::Value, key| match value.get(key) {
+            Some(json::Value::Number(n)) => Some(*n as usize),
+            _ => None
+        };
+        let severity = match string(&value, "level")?.as_str() {
+            "bug" => Severity::Bug,
+            "error" => Severity::Error,
+            "warning" => Severity::Warning,
+            "note" => Severity::Note,
+            "help" => Severity::Help,
+            _ => return None
+        };
+        let code = match value.get("code")? {
+            json::Value::Null => String::new(),
+            code => string(code, "code")?
+        };
+        let primary = value.get("spans")?.as_array()?.iter()
+            .find(|span| span.get("is_primary") == Some(&json::Value::Bool(true)));
+        let span = match primary {
+            Some(span) => Some(ResolvedLabel {
+                primary: true,
+                file: string(span, "file_name")?,
+                bytes: number(span, "byte_start")?..number(span, "byte_end")?,
+                start: Location { line_number: number(span, "line_start")?, column_number: number(span, "column_start")? },
+                end: Location { line_number: number(span, "line_end")?, column_number: number(span, "column_end")? },
+                message: string(span, "label").unwrap_or_default()
+            }),
+            None => None
+        };
+        let (file, line, column) = span.as_ref()
+            .map_or((String::new(), 0, 0), |span| (span.file.clone(), span.start.line_number, span.start.column_number));
+        Some(Finding {
+            severity,
+            code,
+            message: string(&value, "message")?,
+            file,
+            line,
+            column,
+            fingerprint: u64::from_str_radix(&string(&value, "fingerprint")?, 16).ok()?,
+            span
+        })
+    }
+}
+
+impl Display for Finding
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result
+    {
+        if !self.file.is_empty() {
+            write!(f, "{}:{}:{}: ", self.file, self.line, self.column)?;
+        }
+        f.write_str(level(self.severity))?;
+        if !self.code.is_empty() {
+            write!(f, "[{}]", self.code)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// The delta of the findings between two runs, as to check that
+/// no new diagnostic is introduced (as a CI gate).
+///
+/// The findings are matched by fingerprint (in order, when several
+/// share the same one), whatever their lines.
+///
+/// # Example
+/// ```
+/// use codespan_preprocessed::PreprocessedFile;
+/// use codespan_preprocessed::reporting::{Diagnostic, Finding, FindingsDiff};
+///
+/// let before = PreprocessedFile::new("#line 1 \"a.c\"\nint a;\nint b;\n");
+/// let old = [
+///     Finding::new(&before, &Diagnostic::warning().with_code("W1").with_message("unused").with_primary_label(18..19, "")),
+///     Finding::new(&before, &Diagnostic::warning().with_code("W2").with_message("shadowed").with_primary_label(25..26, ""))
+/// ];
+/// // a line is inserted before the unchanged warning
+/// let after = PreprocessedFile::new("#line 1 \"a.c\"\nint z;\nint a;\nint c;\n");
+/// let new = [
+///     Finding::new(&after, &Diagnostic::warning().with_code("W1").with_message("unused").with_primary_label(25..26, "")),
+///     Finding::new(&after, &Diagnostic::warning().with_code("W2").with_message("shadowed").with_primary_label(32..33, ""))
+/// ];
+/// let diff = FindingsDiff::new(&old, &new);
+/// assert_eq!((diff.new.len(), diff.fixed.len(), diff.unchanged.len()), (1, 1, 1));
+/// assert!(diff.has_new());
+/// assert_eq!(diff.to_string(), "\
+/// + a.c:3:5: warning[W2]: shadowed
+/// - a.c:2:5: warning[W2]: shadowed
+/// 1 new, 1 fixed, 1 unchanged
+/// ");
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FindingsDiff {
+    /// The findings of the new run only
+    pub new: Vec<Finding>,
+    /// The findings of the old run only
+    pub fixed: Vec<Finding>,
+    /// The findings of both runs (as found by the new one)
+    pub unchanged: Vec<Finding>
+}
+
+impl FindingsDiff {
+
+    /// Classifies the findings of a new run against the ones of an old run.
+    pub fn new(old: &[Finding], new: &[Finding]) -> Self
+    {
+        // the old findings of each fingerprint, in order
+        let mut remaining: HashMap<u64, VecDeque<usize>> = HashMap::new();
+        for (i, finding) in old.iter().enumerate() {
+            remaining.entry(finding.fingerprint).or_default().push_back(i);
+        }
+        let mut matched = vec![false; old.len()];
+        let mut diff = FindingsDiff::default();
+        for finding in new {
+            match remaining.get_mut(&finding.fingerprint).and_then(VecDeque::pop_front) {
+                Some(i) => {
+                    matched[i] = true;
+                    diff.unchanged.push(finding.clone());
+                }
+                None => diff.new.push(finding.clone())
+            }
+        }
+        diff.fixed = old.iter().zip(matched).filter(|(_, matched)| !matched).map(|(old, _)| old.clone()).collect();
+        diff
+    }
+
+    /// Checks if the new run introduced findings.
+    #[inline]
+    pub fn has_new(&self) -> bool { !self.new.is_empty() }
+}
+
+/// The delta is rendered as `+` lines (new findings) and `-` lines
+/// (fixed ones), followed by a summary.
+impl Display for FindingsDiff
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result
+    {
+        self.new.iter().try_for_each(|finding| writeln!(f, "+ {}", finding))?;
+        self.fixed.iter().try_for_each(|finding| writeln!(f, "- {}", finding))?;
+        writeln!(f, "{} new, {} fixed, {} unchanged", self.new.len(), self.fixed.len(), self.unchanged.len())
+    }
+}
+
+/// The 64-bit FNV-1a hash (stable across the runs and the platforms,
+/// as the fingerprints are saved).
+struct Fnv(u64);
+
+impl Default for Fnv {
+    #[inline]
+    fn default() -> Self { Fnv(0xcbf29ce484222325) }
+}
+
+impl Fnv {
+    /// Hashes some bytes (followed by a separator).
+    fn write(&mut self, bytes: &[u8])
+    {
+        for &byte in bytes.iter().chain(&[0xff]) {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x100000001b3);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json;
+    use crate::PreprocessedFile;
+    use crate::reporting::Diagnostic;
+    use super::{Finding, FindingsDiff};
+
+    #[test]
+    fn persisted_findings()
+    {
+        let file = PreprocessedFile::new("#line 1 \"a \\\"b\\\".c\"\nint a;\n");
+        let finding = Finding::new(&file, &Diagnostic::error().with_message("oops\nhere").with_primary_label(24..25, ""));
+        assert_eq!(finding.file, "a \\\"b\\\".c");
+        assert_eq!(Finding::from_json(&finding.to_json()), Some(finding));
+        let global = Finding::new(&file, &Diagnostic::note().with_message("done"));
+        assert_eq!((global.line, global.to_string()), (0, "note: done".to_string()));
+        assert_eq!(Finding::from_json(r#"{"level":"fatal"}"#), None);
+        assert_eq!(Finding::from_json(&global.to_json()), Some(global));
+    }
+
+    #[test]
+    fn json_report_format()
+    {
+        let file = PreprocessedFile::new("#line 3 \"a.c\"\nint a;\n");
+        let diag = Diagnostic::warning().with_code("W1").with_message("unused").with_primary_label(18..19, "here");
+        let finding = Finding::new(&file, &diag);
+        let line = json::parse(&finding.to_json()).unwrap();
+        assert_eq!(line.get("$message_type").and_then(json::Value::as_str), Some("diagnostic"));
+        assert_eq!(line.get("code").and_then(|code| code.get("code")).and_then(json::Value::as_str), Some("W1"));
+        let span = &line.get("spans").and_then(json::Value::as_array).unwrap()[0];
+        assert_eq!(span.get("line_start"), Some(&json::Value::Number(3.)));
+        assert_eq!(span.get("column_end"), Some(&json::Value::Number(6.)));
+        assert!(line.get("fingerprint").and_then(json::Value::as_str).is_some());
+    }
+
+    #[test]
+    fn repeated_fingerprints()
+    {
+        let file = PreprocessedFile::new("int a;\n");
+        let finding = |message: &str| Finding::new(&file, &Diagnostic::warning().with_message(message));
+        let old = [finding("a"), finding("b"), finding("a"), finding("a")];
+        let diff = FindingsDiff::new(&old, &[finding("a"), finding("c"), finding("a")]);
+        assert_eq!((diff.new.len(), diff.fixed.len(), diff.unchanged.len()), (1, 2, 2));
+        assert_eq!(diff.fixed.iter().map(|f| f.message.as_str()).collect::<Vec<_>>(), ["b", "a"]);
+    }
+}
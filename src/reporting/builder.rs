@@ -0,0 +1,166 @@
+use std::io::{self, IsTerminal};
+use codespan_reporting::term::Config;
+use codespan_reporting::term::termcolor::{ColorChoice, StandardStream};
+use crate::codemap::EasyLocation;
+use super::{pager, EasyReporting};
+
+/// The standard output stream of a reporting.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Output {
+    /// The standard output
+    Stdout,
+    /// The standard error
+    #[default]
+    Stderr
+}
+
+/// The renderer of the diagnostics.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Renderer {
+    /// The rendering of `codespan-reporting`
+    #[default]
+    Codespan,
+    /// The rendering of `ariadne` (except for the diagnostics
+    /// without label, rendered by `codespan-reporting`)
+    #[cfg(feature = "ariadne")]
+    Ariadne
+}
+
+/// A builder to customize the output of an [`EasyReporting`].
+///
+/// # Example
+/// ```
+/// use codespan_reporting::term::termcolor::ColorChoice;
+/// use codespan_preprocessed::PreprocessedFile;
+/// use codespan_preprocessed::reporting::{EasyReport, EasyReporting};
+///
+/// let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a;\n");
+/// let report = EasyReporting::builder()
+///     .stdout()
+///     .color(ColorChoice::Auto)
+///     .build(&file);
+/// ```
+#[derive(Clone)]
+pub struct EasyReportingBuilder {
+    output: Output,
+    color: ColorChoice,
+    config: Config,
+    renderer: Renderer
+}
+
+impl Default for EasyReportingBuilder {
+    fn default() -> Self
+    {
+        Self { output: Output::default(), color: ColorChoice::Auto, config: Config::default(), renderer: Renderer::default() }
+    }
+}
+
+impl EasyReportingBuilder {
+
+    #[inline]
+    pub fn new() -> Self { Self::default() }
+
+    /// Writes the diagnostics to the given stream (the standard error by default).
+    #[inline]
+    pub fn output(mut self, output: Output) -> Self
+    {
+        self.output = output;
+        self
+    }
+
+    /// Writes the diagnostics to the standard output.
+    #[inline]
+    pub fn stdout(self) -> Self { self.output(Output::Stdout) }
+
+    /// Writes the diagnostics to the standard error.
+    #[inline]
+    pub fn stderr(self) -> Self { self.output(Output::Stderr) }
+
+    /// Sets the color choice (automatic by default).
+    ///
+    /// With [`ColorChoice::Auto`], the colors are forced if the `CLICOLOR_FORCE`
+    /// environment variable is set (to a non-empty value other than `0`), else
+    /// they are disabled if `NO_COLOR` is set (to a non-empty value), if `CLICOLOR`
+    /// is `0` or if the output stream is not a terminal (as a redirection to a log).
+    #[inline]
+    pub fn color(mut self, color: ColorChoice) -> Self
+    {
+        self.color = color;
+        self
+    }
+
+    /// Sets the rendering configuration.
+    #[inline]
+    pub fn config(mut self, config: Config) -> Self
+    {
+        self.config = config;
+        self
+    }
+
+    /// Sets the renderer of the diagnostics (codespan by default).
+    #[inline]
+    pub fn renderer(mut self, renderer: Renderer) -> Self
+    {
+        self.renderer = renderer;
+        self
+    }
+
+    /// Builds the reporting of the diagnostics located in the given source.
+    pub fn build<'a,L:EasyLocation<'a>>(&self, source: &'a L) -> EasyReporting<'a,L>
+    {
+        let writer = match self.output {
+            Output::Stdout => StandardStream::stdout(color_choice(self.color, |var| std::env::var_os(var), io::stdout().is_terminal())),
+            Output::Stderr => StandardStream::stderr(color_choice(self.color, |var| std::env::var_os(var), io::stderr().is_terminal()))
+        };
+        let mut report = EasyReporting::with_writer(source, writer, self.config.clone())
+            .with_renderer(self.renderer);
+        report.interactive = self.output == Output::Stderr && pager::is_interactive();
+        report
+    }
+}
+
+/// Resolves the automatic color choice with the environment variables
+/// and the kind of the output stream (see [`EasyReportingBuilder::color`]).
+pub(crate) fn color_choice<F:Fn(&str) -> Option<std::ffi::OsString>>(choice: ColorChoice, env: F, terminal: bool) -> ColorChoice
+{
+    if choice != ColorChoice::Auto {
+        return choice;
+    }
+    let set = |var| env(var).is_some_and(|value| !value.is_empty() && value != "0");
+    let no_color = env("NO_COLOR").is_some_and(|value| !value.is_empty());
+    if set("CLICOLOR_FORCE") {
+        ColorChoice::Always
+    } else if no_color || !terminal || env("CLICOLOR").is_some_and(|value| value == "0") {
+        ColorChoice::Never
+    } else {
+        ColorChoice::Auto
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+    use codespan_reporting::term::termcolor::ColorChoice;
+    use super::color_choice;
+
+    #[test]
+    fn color_environment()
+    {
+        let env = |vars: &'static [(&str, &str)]| move |var: &str| {
+            vars.iter().find(|(name, _)| *name == var).map(|(_, value)| OsString::from(value))
+        };
+        assert_eq!(color_choice(ColorChoice::Auto, env(&[]), true), ColorChoice::Auto);
+        assert_eq!(color_choice(ColorChoice::Auto, env(&[("NO_COLOR", "1")]), true), ColorChoice::Never);
+        assert_eq!(color_choice(ColorChoice::Auto, env(&[("NO_COLOR", "")]), true), ColorChoice::Auto);
+        assert_eq!(color_choice(ColorChoice::Auto, env(&[("CLICOLOR", "0")]), true), ColorChoice::Never);
+        assert_eq!(color_choice(ColorChoice::Auto, env(&[("CLICOLOR", "1")]), true), ColorChoice::Auto);
+        // a redirected output is not colored, unless forced
+        assert_eq!(color_choice(ColorChoice::Auto, env(&[]), false), ColorChoice::Never);
+        assert_eq!(color_choice(ColorChoice::Auto, env(&[("CLICOLOR_FORCE", "1")]), false), ColorChoice::Always);
+        assert_eq!(color_choice(ColorChoice::Auto, env(&[("CLICOLOR_FORCE", "0")]), false), ColorChoice::Never);
+        assert_eq!(color_choice(ColorChoice::Auto, env(&[("CLICOLOR_FORCE", "1"), ("NO_COLOR", "1")]), true), ColorChoice::Always);
+        // an explicit choice is kept
+        assert_eq!(color_choice(ColorChoice::Always, env(&[("NO_COLOR", "1")]), false), ColorChoice::Always);
+    }
+}
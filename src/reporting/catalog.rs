@@ -0,0 +1,88 @@
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
+
+/// A message written by the reporting itself.
+///
+/// It is displayed in English (see [`MessageCatalog::summary`]).
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Summary {
+    /// The number of emitted warnings
+    Warnings(u32),
+    /// The number of emitted errors
+    Errors(u32),
+    /// The number of suppressed duplicates
+    Duplicates(u32),
+    /// The maximal number of errors is reached
+    TooManyErrors,
+    /// The number of diagnostics of a code which are not rendered
+    /// (see [`EasyReporting::limit_per_code`](super::EasyReporting::limit_per_code))
+    Suppressed { count: u32, code: String },
+    /// A file including the one of a diagnostic, with the line of the
+    /// inclusion (see [`Diagnostic::with_include_notes`](super::Diagnostic::with_include_notes))
+    IncludedFrom { file: String, line: usize }
+}
+
+impl Display for Summary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Summary::Warnings(1) => write!(f, "1 warning emitted"),
+            Summary::Warnings(n) => write!(f, "{} warnings emitted", n),
+            Summary::Errors(1) => write!(f, "1 error emitted"),
+            Summary::Errors(n) => write!(f, "{} errors emitted", n),
+            Summary::Duplicates(1) => write!(f, "1 duplicate diagnostic suppressed"),
+            Summary::Duplicates(n) => write!(f, "{} duplicate diagnostics suppressed", n),
+            Summary::TooManyErrors => write!(f, "too many errors emitted, stopping now"),
+            Summary::Suppressed { count: 1, code } => write!(f, "suppressed 1 additional {} diagnostic", code),
+            Summary::Suppressed { count, code } => write!(f, "suppressed {} additional {} diagnostics", count, code),
+            Summary::IncludedFrom { file, line } => write!(f, "in file included from {}:{}", file, line)
+        }
+    }
+}
+
+/// A translation of the rendered messages
+/// (see [`EasyReporting::with_catalog`](super::EasyReporting::with_catalog)).
+///
+/// By default, nothing is translated.
+///
+/// # Example
+/// ```
+/// use std::borrow::Cow;
+/// use codespan_preprocessed::PreprocessedFile;
+/// use codespan_preprocessed::reporting::{Diagnostic, EasyReport, EasyReporting, MessageCatalog, Summary};
+///
+/// struct French;
+///
+/// impl MessageCatalog for French {
+///     fn translate<'m>(&self, text: &'m str) -> Cow<'m, str> {
+///         match text {
+///             "unused variable" => "variable inutilisée".into(),
+///             _ => text.into()
+///         }
+///     }
+///     fn summary(&self, summary: Summary) -> String {
+///         match summary {
+///             Summary::Warnings(n) => format!("{} avertissement(s)", n),
+///             Summary::Suppressed { count, code } => format!("{} diagnostic(s) {} supprimé(s)", count, code),
+///             _ => summary.to_string()
+///         }
+///     }
+/// }
+///
+/// let file = PreprocessedFile::new("int a;\n");
+/// let report = EasyReporting::to_buffer(&file).with_catalog(French);
+/// report.emit(Diagnostic::warning().with_message("unused variable"));
+/// report.emit_status();
+/// assert!(report.rendered().starts_with("warning: variable inutilisée"));
+/// assert!(report.rendered().contains("warning: 1 avertissement(s)"));
+/// ```
+pub trait MessageCatalog: Send + Sync
+{
+    /// Translates a message, a label or a note of a diagnostic.
+    #[inline]
+    fn translate<'m>(&self, text: &'m str) -> Cow<'m, str> { Cow::Borrowed(text) }
+
+    /// Gets a message of the reporting.
+    #[inline]
+    fn summary(&self, summary: Summary) -> String { summary.to_string() }
+}
@@ -0,0 +1,141 @@
+use std::fmt::Display;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use codespan_reporting::diagnostic::Severity;
+use crate::PreprocessedFile;
+use crate::codemap::EasyLocation;
+use crate::error::OverlapError;
+use super::{apply_suggestions, suggestions_diff, Diagnostic, EasyReport, Finding, Suggestion};
+
+/// A sink which collects the emitted diagnostics.
+///
+/// The codes are stored as strings. The collected diagnostics could be
+/// checked (in tests) or replayed later to another reporting.
+///
+/// # Example
+/// ```
+/// use codespan_reporting::diagnostic::Severity;
+/// use codespan_preprocessed::PreprocessedFile;
+/// use codespan_preprocessed::reporting::{Diagnostic, DiagnosticCollector, EasyReport, EasyReporting};
+///
+/// let collector = DiagnosticCollector::new();
+/// collector.emit(Diagnostic::warning().with_message("unused"));
+/// collector.emit(Diagnostic::error().with_code("E1").with_message("oops"));
+/// assert_eq!(collector.len(), 2);
+/// assert_eq!(collector.with_severity(Severity::Error)[0].code(), "E1");
+///
+/// let file = PreprocessedFile::new("int a;\n");
+/// let report = EasyReporting::to_buffer(&file);
+/// collector.replay(&report);
+/// assert!(report.rendered().starts_with("warning: unused"));
+/// ```
+#[derive(Debug, Default)]
+pub struct DiagnosticCollector {
+    diagnostics: Mutex<Vec<Diagnostic<String>>>
+}
+
+impl DiagnosticCollector {
+
+    #[inline]
+    pub fn new() -> Self { Self::default() }
+
+    #[inline]
+    fn lock(&self) -> MutexGuard<'_, Vec<Diagnostic<String>>>
+    {
+        self.diagnostics.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Number of collected diagnostics.
+    #[inline]
+    pub fn len(&self) -> usize { self.lock().len() }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool { self.lock().is_empty() }
+
+    /// Gets a copy of the collected diagnostics (in order of emission).
+    #[inline]
+    pub fn diagnostics(&self) -> Vec<Diagnostic<String>> { self.lock().clone() }
+
+    /// Gets the collected diagnostics which satisfy the given predicate.
+    pub fn filter<F:FnMut(&Diagnostic<String>) -> bool>(&self, mut predicate: F) -> Vec<Diagnostic<String>>
+    {
+        self.lock().iter().filter(|diag| predicate(diag)).cloned().collect()
+    }
+
+    /// Gets the collected diagnostics of the given severity.
+    #[inline]
+    pub fn with_severity(&self, severity: Severity) -> Vec<Diagnostic<String>>
+    {
+        self.filter(|diag| diag.severity == severity)
+    }
+
+    /// Gets the collected diagnostics of the given code.
+    #[inline]
+    pub fn with_code(&self, code: &str) -> Vec<Diagnostic<String>>
+    {
+        self.filter(|diag| diag.code == code)
+    }
+
+    /// Removes all the collected diagnostics.
+    #[inline]
+    pub fn clear(&self) { self.lock().clear() }
+
+    /// Gets back the collected diagnostics.
+    #[inline]
+    pub fn into_diagnostics(self) -> Vec<Diagnostic<String>>
+    {
+        self.diagnostics.into_inner().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Gets the suggestions of the collected diagnostics
+    /// (including those of their children).
+    pub fn suggestions(&self) -> Vec<Suggestion>
+    {
+        fn gather(diag: &Diagnostic<String>, suggestions: &mut Vec<Suggestion>) {
+            suggestions.extend(diag.suggestions.iter().cloned());
+            diag.children.iter().for_each(|child| gather(child, suggestions));
+        }
+        let mut suggestions = vec![];
+        self.lock().iter().for_each(|diag| gather(diag, &mut suggestions));
+        suggestions
+    }
+
+    /// Applies the suggestions of the collected diagnostics
+    /// to the preprocessed source (see [`apply_suggestions`]).
+    #[inline]
+    pub fn apply_suggestions<S:AsRef<str>>(&self, file: &PreprocessedFile<S>) -> Result<String, OverlapError>
+    {
+        apply_suggestions(file, &self.suggestions())
+    }
+
+    /// Gets the unified diff of the original files which would be changed
+    /// by the suggestions of the collected diagnostics (see [`suggestions_diff`]).
+    #[inline]
+    pub fn suggestions_diff<S:AsRef<str>>(&self, file: &PreprocessedFile<S>, context: usize) -> Result<String, OverlapError>
+    {
+        suggestions_diff(file, &self.suggestions(), context)
+    }
+
+    /// Identifies the collected diagnostics located in the given source,
+    /// as to compare them with the ones of another run (see [`FindingsDiff`](super::FindingsDiff)).
+    pub fn findings<'a,L:EasyLocation<'a>>(&self, source: &'a L) -> Vec<Finding>
+    {
+        self.lock().iter().map(|diag| Finding::new(source, diag)).collect()
+    }
+
+    /// Emits again the collected diagnostics (in order of emission)
+    /// to another reporting.
+    pub fn replay<R:EasyReport>(&self, report: &R)
+    {
+        self.diagnostics().into_iter().for_each(|diag| report.emit(diag))
+    }
+}
+
+impl EasyReport for DiagnosticCollector
+{
+    fn emit<E:Display>(&self, diag: impl Into<Diagnostic<E>>)
+    {
+        let diag = diag.into();
+        let code = diag.code.to_string();
+        self.lock().push(diag.with_code(code));
+    }
+}
@@ -0,0 +1,102 @@
+use std::process::ExitCode;
+use super::EasyReportingStatus;
+
+/// The exit code of a process according to the status of its reporting.
+///
+/// By default, the process succeeds with `0` if no error was reported,
+/// otherwise it fails with the number of errors, clamped to `255` (as the
+/// exit status is an 8-bit value on most systems).
+///
+/// # Example
+/// ```
+/// use codespan_preprocessed::reporting::{EasyReportingStatus, ExitPolicy};
+///
+/// let policy = ExitPolicy::default();
+/// assert_eq!(policy.code(EasyReportingStatus::Warnings(3)), 0);
+/// assert_eq!(policy.code(EasyReportingStatus::Errors(1000)), 255);
+///
+/// let policy = ExitPolicy::new().failure(2).warnings_fail(true);
+/// assert_eq!(policy.code(EasyReportingStatus::Warnings(3)), 2);
+/// assert_eq!(policy.code(EasyReportingStatus::Errors(1000)), 2);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ExitPolicy {
+    success: u8,
+    failure: Option<u8>,
+    max: u8,
+    warnings_fail: bool
+}
+
+impl Default for ExitPolicy {
+    fn default() -> Self
+    {
+        Self { success: 0, failure: None, max: u8::MAX, warnings_fail: false }
+    }
+}
+
+impl ExitPolicy {
+
+    #[inline]
+    pub fn new() -> Self { Self::default() }
+
+    /// Sets the exit code of a success (`0` by default).
+    #[inline]
+    pub fn success(mut self, code: u8) -> Self
+    {
+        self.success = code;
+        self
+    }
+
+    /// Sets a fixed exit code for a failure (instead of the number of errors).
+    #[inline]
+    pub fn failure(mut self, code: u8) -> Self
+    {
+        self.failure = Some(code);
+        self
+    }
+
+    /// Fails with the number of errors, clamped to the given maximum
+    /// (the number of warnings if they fail the build).
+    #[inline]
+    pub fn error_count(mut self, max: u8) -> Self
+    {
+        self.failure = None;
+        self.max = max;
+        self
+    }
+
+    /// Sets if the warnings fail the build (as the errors).
+    #[inline]
+    pub fn warnings_fail(mut self, enabled: bool) -> Self
+    {
+        self.warnings_fail = enabled;
+        self
+    }
+
+    /// Gets the exit code of the given status.
+    pub fn code(&self, status: EasyReportingStatus) -> u8
+    {
+        let count = match status {
+            EasyReportingStatus::Errors(n) => n,
+            EasyReportingStatus::Warnings(n) if self.warnings_fail => n,
+            _ => return self.success
+        };
+        self.failure.unwrap_or_else(|| count.clamp(1, self.max.max(1) as u32) as u8)
+    }
+
+    /// Gets the exit code of the given status (for `main`).
+    #[inline]
+    pub fn exit_code(&self, status: EasyReportingStatus) -> ExitCode
+    {
+        ExitCode::from(self.code(status))
+    }
+
+    /// Exits the process with the exit code of the given status
+    /// (there is no process to exit on `wasm32-unknown-unknown`).
+    #[inline]
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn exit(&self, status: EasyReportingStatus) -> !
+    {
+        std::process::exit(self.code(status) as i32)
+    }
+}
@@ -0,0 +1,53 @@
+use std::time::Duration;
+use super::{EasyReportingStatus, Statistics};
+
+/// What is known of a reporting when its status is emitted
+/// (see [`SummaryFormatter`]).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StatusSummary {
+    /// The final status
+    pub status: EasyReportingStatus,
+    /// The numbers of reported diagnostics, by severity
+    pub statistics: Statistics,
+    /// The numbers of reported diagnostics, by code (sorted)
+    pub by_code: Vec<(String, u32)>,
+    /// The number of suppressed duplicates
+    pub duplicates: u32,
+    /// The time elapsed since the creation of the reporting
+    /// (zero on `wasm32-unknown-unknown`, without clock)
+    pub elapsed: Duration
+}
+
+/// A formatter of the summary written by
+/// [`EasyReporting::emit_status`](super::EasyReporting::emit_status),
+/// replacing the default one (the `N errors emitted` messages).
+///
+/// The formatted text is written as is (nothing is written if it is empty).
+/// Any closure taking a [`StatusSummary`] and returning a string is a formatter.
+///
+/// # Example
+/// ```
+/// use codespan_preprocessed::PreprocessedFile;
+/// use codespan_preprocessed::reporting::{Diagnostic, EasyReport, EasyReporting, StatusSummary};
+///
+/// let file = PreprocessedFile::new("int a;\n");
+/// let report = EasyReporting::to_buffer(&file).with_summary_formatter(|summary: &StatusSummary| {
+///     let codes = summary.by_code.iter().map(|(code, n)| format!("{}×{}", code, n)).collect::<Vec<_>>();
+///     format!("mycc: {} error(s) [{}]\n", summary.statistics.errors, codes.join(", "))
+/// });
+/// report.emit(Diagnostic::error().with_code("E01").with_message("oops"));
+/// report.emit(Diagnostic::error().with_code("E01").with_message("oops again"));
+/// assert!(report.emit_status().is_failure());
+/// assert!(report.rendered().ends_with("mycc: 2 error(s) [E01×2]\n"));
+/// ```
+pub trait SummaryFormatter: Send + Sync
+{
+    fn format(&self, summary: &StatusSummary) -> String;
+}
+
+impl<F> SummaryFormatter for F
+    where F: Fn(&StatusSummary) -> String + Send + Sync
+{
+    #[inline]
+    fn format(&self, summary: &StatusSummary) -> String { self(summary) }
+}
@@ -0,0 +1,133 @@
+use std::fmt::Display;
+use std::ops::{Deref, Range};
+use super::{Diagnostic, EasyReport, ReportError};
+
+/// A diagnostic under construction, tied to the reporting which
+/// should emit it (see [`EasyReport::build`]).
+///
+/// It should be either emitted or cancelled: a guard dropped without
+/// one of them reports a bug (with the lost diagnostic as child), since
+/// it is most likely an error which is silently ignored.
+///
+/// # Example
+/// ```
+/// use codespan_preprocessed::PreprocessedFile;
+/// use codespan_preprocessed::reporting::{Diagnostic, EasyReport, EasyReporting};
+///
+/// let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a;\n");
+/// let report = EasyReporting::to_buffer(&file);
+/// report.build(Diagnostic::error())
+///     .with_message("oops")
+///     .with_primary_label(18..19, "here")
+///     .emit();
+/// assert_eq!(report.check_status().errors, 1);
+///
+/// // an abandoned diagnostic is a bug
+/// let _ = report.build(Diagnostic::warning().with_message("lost"));
+/// assert_eq!(report.check_status().bugs, 1);
+/// assert!(report.rendered().contains("bug: diagnostic constructed but never emitted"));
+/// ```
+#[must_use = "a diagnostic should be emitted or cancelled"]
+pub struct DiagnosticBuilder<'r,R:EasyReport,E:Display>
+{
+    report: &'r R,
+    diag: Option<Diagnostic<E>>
+}
+
+impl<'r,R:EasyReport,E:Display> DiagnosticBuilder<'r,R,E>
+{
+    #[inline]
+    pub fn new(report: &'r R, diag: impl Into<Diagnostic<E>>) -> Self
+    {
+        Self { report, diag: Some(diag.into()) }
+    }
+
+    /// Modifies the diagnostic under construction.
+    #[inline]
+    pub fn map(mut self, f: impl FnOnce(Diagnostic<E>) -> Diagnostic<E>) -> Self
+    {
+        self.diag = self.diag.take().map(f);
+        self
+    }
+
+    /// See [`Diagnostic::with_message`].
+    #[inline]
+    pub fn with_message(self, msg: impl Into<String>) -> Self
+    {
+        self.map(|diag| diag.with_message(msg))
+    }
+
+    /// See [`Diagnostic::with_note`].
+    #[inline]
+    pub fn with_note(self, note: impl Into<String>) -> Self
+    {
+        self.map(|diag| diag.with_note(note))
+    }
+
+    /// See [`Diagnostic::with_primary_label`].
+    #[inline]
+    pub fn with_primary_label(self, range: impl Into<Range<usize>>, msg: impl Into<String>) -> Self
+    {
+        self.map(|diag| diag.with_primary_label(range, msg))
+    }
+
+    /// See [`Diagnostic::with_secondary_label`].
+    #[inline]
+    pub fn with_secondary_label(self, range: impl Into<Range<usize>>, msg: impl Into<String>) -> Self
+    {
+        self.map(|diag| diag.with_secondary_label(range, msg))
+    }
+
+    /// See [`Diagnostic::with_suggestion`].
+    #[inline]
+    pub fn with_suggestion(self, range: impl Into<Range<usize>>, replacement: impl Into<String>, message: impl Into<String>) -> Self
+    {
+        self.map(|diag| diag.with_suggestion(range, replacement, message))
+    }
+
+    /// Emits the diagnostic (see [`EasyReport::emit`]).
+    #[inline]
+    pub fn emit(mut self)
+    {
+        if let Some(diag) = self.diag.take() { self.report.emit(diag) }
+    }
+
+    /// Emits the diagnostic or fails if it could not be written
+    /// (see [`EasyReport::try_emit`]).
+    #[inline]
+    pub fn try_emit(mut self) -> Result<(), ReportError>
+    {
+        self.diag.take().map_or(Ok(()), |diag| self.report.try_emit(diag))
+    }
+
+    /// Abandons the emission and gets back the diagnostic.
+    #[inline]
+    pub fn cancel(mut self) -> Diagnostic<E>
+    {
+        self.diag.take().expect("diagnostic already emitted")
+    }
+}
+
+impl<R:EasyReport,E:Display> Deref for DiagnosticBuilder<'_,R,E>
+{
+    type Target = Diagnostic<E>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target
+    {
+        self.diag.as_ref().expect("diagnostic already emitted")
+    }
+}
+
+impl<R:EasyReport,E:Display> Drop for DiagnosticBuilder<'_,R,E>
+{
+    fn drop(&mut self)
+    {
+        // no more bug while unwinding
+        if let Some(diag) = self.diag.take().filter(|_| !std::thread::panicking()) {
+            self.report.emit(Diagnostic::bug()
+                .with_message("diagnostic constructed but never emitted")
+                .with_child(diag));
+        }
+    }
+}
@@ -0,0 +1,293 @@
+use std::collections::HashSet;
+use std::fmt::{Display, Write as _};
+use std::io;
+use std::sync::{Mutex, PoisonError};
+use crate::codemap::EasyLocation;
+use super::{Diagnostic, EasyReport};
+use super::resolved::{level, Resolved, ResolvedLabel};
+
+const STYLE: &str = "\
+body { font-family: sans-serif; margin: 2em; }
+.diagnostic { border-left: 4px solid #888; margin: 1em 0; padding: 0.2em 1em; }
+.bug, .error { border-color: #c00; } .warning { border-color: #e90; }
+.note { border-color: #07c; } .help { border-color: #0a0; }
+.level { font-weight: bold; } .error .level, .bug .level { color: #c00; } .warning .level { color: #e90; }
+.location { font-family: monospace; color: #555; }
+pre { background: #f6f6f6; padding: 0.5em; margin: 0.2em 0; }
+.line-number { color: #999; user-select: none; padding-right: 1em; }
+mark.primary { background: #fcc; } mark.secondary { background: #cdf; }
+.label { font-style: italic; color: #555; margin-left: 1em; }
+.keyword { color: #708; font-weight: bold; } .string { color: #a11; }
+.number { color: #164; } .comment { color: #888; font-style: italic; }
+del { background: #fdd; } ins { background: #dfd; text-decoration: none; }
+";
+
+/// The keywords of C (and of most of the C-like languages).
+const KEYWORDS: &[&str] = &[
+    "auto", "break", "case", "char", "const", "continue", "default", "do", "double", "else",
+    "enum", "extern", "float", "for", "goto", "if", "inline", "int", "long", "register",
+    "return", "short", "signed", "sizeof", "static", "struct", "switch", "typedef", "union",
+    "unsigned", "void", "volatile", "while"
+];
+
+/// A reporting of the diagnostics as a standalone HTML document.
+///
+/// Each diagnostic is styled according to its severity, each label is shown
+/// with its source line (the labelled text being marked) and is located
+/// in its original file: the snippets are anchored as `#file:line`.
+///
+/// The preprocessed language is unknown, so the snippets are highlighted
+/// with the tokens common to the C-like languages: the comments, the strings,
+/// the numbers and the C keywords. The suggestions are shown as the
+/// replacement of their span.
+/// ```
+/// use codespan_preprocessed::PreprocessedFile;
+/// use codespan_preprocessed::reporting::{Diagnostic, EasyReport, HtmlReport};
+///
+/// let file = PreprocessedFile::new("#line 3 \"a.c\"\nint a<b;\n");
+/// let report = HtmlReport::new(&file);
+/// report.emit(Diagnostic::error().with_message("oops").with_primary_label(18..19, "here"));
+/// let html = report.to_html();
+/// assert!(html.contains(r#"<pre id="a.c:3"><span class="line-number">3</span><span class="keyword">int</span> <mark class="primary">a</mark>&lt;b;</pre>"#));
+/// ```
+pub struct HtmlReport<'a,L:EasyLocation<'a>>
+{
+    source: &'a L,
+    title: String,
+    rendered: Mutex<(String, HashSet<String>)>
+}
+
+impl<'a,L:EasyLocation<'a>> HtmlReport<'a,L>
+{
+    pub fn new(source: &'a L) -> Self
+    {
+        Self { source, title: "Diagnostics".to_string(), rendered: Mutex::default() }
+    }
+
+    /// Sets the title of the document.
+    #[inline]
+    pub fn with_title(mut self, title: impl Into<String>) -> Self
+    {
+        self.title = title.into();
+        self
+    }
+
+    /// Gets the HTML document of the diagnostics emitted so far.
+    pub fn to_html(&self) -> String
+    {
+        let rendered = self.rendered.lock().unwrap_or_else(PoisonError::into_inner);
+        let title = escape(&self.title);
+        format!("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n{}</style>\n</head>\n<body>\n<h1>{}</h1>\n{}</body>\n</html>\n",
+                title, STYLE, title, rendered.0)
+    }
+
+    /// Writes the HTML document of the diagnostics emitted so far.
+    pub fn write<W:io::Write>(&self, mut writer: W) -> io::Result<()>
+    {
+        writer.write_all(self.to_html().as_bytes())
+    }
+
+    /// Renders a diagnostic (with its children nested in its block).
+    fn diagnostic(&self, out: &mut String, anchors: &mut HashSet<String>, diag: &Resolved)
+    {
+        let level = level(diag.severity);
+        let _ = write!(out, "<div class=\"diagnostic {}\">\n<p><span class=\"level\">{}", level, level);
+        if !diag.code.is_empty() {
+            let _ = write!(out, "[{}]", escape(&diag.code));
+        }
+        let _ = writeln!(out, "</span>: {}</p>", escape(&diag.message));
+        diag.labels.iter().for_each(|label| self.snippet(out, anchors, label));
+        diag.notes.iter().for_each(|note| { let _ = writeln!(out, "<p class=\"note\">= {}</p>", escape(note)); });
+        diag.suggestions.iter().for_each(|(span, replacement)| self.suggestion(out, span, replacement));
+        diag.children.iter().for_each(|child| self.diagnostic(out, anchors, child));
+        out.push_str("</div>\n");
+    }
+
+    /// Gets the source line of a span, with the span within this line.
+    fn line(&self, bytes: &std::ops::Range<usize>) -> Option<(String, std::ops::Range<usize>)>
+    {
+        let id = self.source.file_id(bytes.start);
+        let line = self.source.line_index(id, bytes.start)
+            .and_then(|line| self.source.line_range(id, line)).ok()?;
+        let text = self.source.source(id).ok()?;
+        let start = bytes.start.clamp(line.start, line.end);
+        let end = bytes.end.clamp(start, line.end);
+        Some((text.as_ref()[line.clone()].to_string(), (start - line.start)..(end - line.start)))
+    }
+
+    /// Renders the source line of a label (with the labelled text marked).
+    fn snippet(&self, out: &mut String, anchors: &mut HashSet<String>, label: &ResolvedLabel)
+    {
+        let Some((text, marked)) = self.line(&label.bytes) else { return };
+
+        let anchor = format!("{}:{}", label.file, label.start.line_number);
+        let _ = write!(out, "<p class=\"location\">{}:{}:{}</p>\n<pre",
+                       escape(&label.file), label.start.line_number, label.start.column_number);
+        if anchors.insert(anchor.clone()) {
+            let _ = write!(out, " id=\"{}\"", escape(&anchor));
+        }
+        let style = if label.primary { "primary" } else { "secondary" };
+        let _ = write!(out, "><span class=\"line-number\">{}</span>", label.start.line_number);
+        highlight(out, &text, 0..marked.start);
+        let _ = write!(out, "<mark class=\"{}\">", style);
+        highlight(out, &text, marked.clone());
+        out.push_str("</mark>");
+        highlight(out, &text, marked.end..text.len());
+        out.push_str("</pre>");
+        if !label.message.is_empty() {
+            let _ = write!(out, "\n<p class=\"label\">{}</p>", escape(&label.message));
+        }
+        out.push('\n');
+    }
+
+    /// Renders a suggestion: its source line with the replaced text.
+    fn suggestion(&self, out: &mut String, span: &ResolvedLabel, replacement: &str)
+    {
+        let _ = writeln!(out, "<p class=\"help\"><span class=\"level\">help</span>: {}</p>", escape(&span.message));
+        let Some((text, replaced)) = self.line(&span.bytes) else { return };
+        let _ = writeln!(out, "<p class=\"location\">{}:{}:{}</p>\n<pre><span class=\"line-number\">{}</span>{}<del>{}</del><ins>{}</ins>{}</pre>",
+                         escape(&span.file), span.start.line_number, span.start.column_number, span.start.line_number,
+                         escape(&text[..replaced.start]), escape(&text[replaced.clone()]), escape(replacement),
+                         escape(&text[replaced.end..]));
+    }
+}
+
+impl<'a,L:EasyLocation<'a>> EasyReport for HtmlReport<'a,L>
+{
+    fn emit<E: Display>(&self, diag: impl Into<Diagnostic<E>>)
+    {
+        let diag = Resolved::new(self.source, diag.into());
+        let mut rendered = self.rendered.lock().unwrap_or_else(PoisonError::into_inner);
+        let (out, anchors) = &mut *rendered;
+        self.diagnostic(out, anchors, &diag);
+    }
+}
+
+/// Writes a part of a source line, escaped and highlighted: the line is
+/// split in tokens, then the tokens are cut to the given part.
+fn highlight(out: &mut String, line: &str, part: std::ops::Range<usize>)
+{
+    for (range, class) in tokens(line) {
+        let (start, end) = (range.start.max(part.start), range.end.min(part.end));
+        if start >= end {
+            continue;
+        }
+        match class {
+            Some(class) => { let _ = write!(out, "<span class=\"{}\">{}</span>", class, escape(&line[start..end])); }
+            None => out.push_str(&escape(&line[start..end]))
+        }
+    }
+}
+
+/// Splits a source line in tokens, with the class of the highlighted ones
+/// (the comments, the strings, the numbers and the keywords).
+fn tokens(line: &str) -> Vec<(std::ops::Range<usize>, Option<&'static str>)>
+{
+    let mut tokens = vec![];
+    let mut chars = line.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let rest = &line[start..];
+        let (end, class) = if rest.starts_with("//") {
+            (line.len(), Some("comment"))
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            (comment.find("*/").map_or(line.len(), |end| start + end + 4), Some("comment"))
+        } else if c == '"' || c == '\'' {
+            // the escaped characters are skipped up to the closing quote
+            let mut escaped = false;
+            let end = rest.char_indices().skip(1)
+                .find(|&(_, d)| {
+                    let closing = !escaped && d == c;
+                    escaped = !escaped && d == '\\';
+                    closing
+                })
+                .map_or(line.len(), |(end, _)| start + end + 1);
+            (end, Some("string"))
+        } else if c.is_ascii_digit() || c.is_alphabetic() || c == '_' {
+            let end = rest.find(|d: char| !(d.is_alphanumeric() || d == '_' || (c.is_ascii_digit() && d == '.')))
+                .map_or(line.len(), |end| start + end);
+            let class = match &line[start..end] {
+                _ if c.is_ascii_digit() => Some("number"),
+                word if KEYWORDS.contains(&word) => Some("keyword"),
+                _ => None
+            };
+            (end, class)
+        } else {
+            (start + c.len_utf8(), None)
+        };
+        while chars.next_if(|&(i, _)| i < end).is_some() {}
+        tokens.push((start..end, class));
+    }
+    tokens
+}
+
+/// Escapes the HTML special characters.
+pub(crate) fn escape(text: &str) -> String
+{
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c)
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::PreprocessedFile;
+    use crate::reporting::{Diagnostic, EasyReport};
+    use super::{highlight, HtmlReport};
+
+    #[test]
+    fn html_document()
+    {
+        let file = PreprocessedFile::new("#line 1 \"<a>.c\"\nint a;\nint b;\n");
+        let report = HtmlReport::new(&file).with_title("Report & co");
+        report.emit(Diagnostic::warning().with_code("W1").with_message("first").with_primary_label(20..21, "a"));
+        report.emit(Diagnostic::error().with_message("second").with_primary_label(16..19, "").with_note("n"));
+        let html = report.to_html();
+        assert!(html.contains("<title>Report &amp; co</title>"));
+        assert!(html.contains("<div class=\"diagnostic warning\">\n<p><span class=\"level\">warning[W1]</span>: first</p>"));
+        assert!(html.contains("<p class=\"location\">&lt;a&gt;.c:1:5</p>"));
+        // the anchor of a line is given once
+        assert_eq!(html.matches("id=\"&lt;a&gt;.c:1\"").count(), 1);
+        assert!(html.contains("<mark class=\"primary\"><span class=\"keyword\">int</span></mark> a;</pre>\n<p class=\"note\">= n</p>"));
+
+        let report = HtmlReport::new(&file);
+        report.emit(Diagnostic::error().with_message("parent")
+            .with_child(Diagnostic::note().with_message("child").with_primary_label(20..21, "")));
+        let html = report.to_html();
+        assert!(html.contains("<div class=\"diagnostic note\">\n<p><span class=\"level\">note</span>: child</p>"));
+        assert!(html.contains("<mark class=\"primary\">a</mark>;</pre>\n</div>\n</div>\n"));
+    }
+
+    #[test]
+    fn highlighted_tokens()
+    {
+        let line = r#"if (x < 0x1F) s = "a\"b"; /* c */ y; // d"#;
+        let mut out = String::new();
+        highlight(&mut out, line, 0..line.len());
+        assert_eq!(out, "<span class=\"keyword\">if</span> (x &lt; <span class=\"number\">0x1F</span>) s = \
+                         <span class=\"string\">&quot;a\\&quot;b&quot;</span>; <span class=\"comment\">/* c */</span> y; \
+                         <span class=\"comment\">// d</span>");
+        // a token is cut to the part
+        out.clear();
+        highlight(&mut out, line, 1..10);
+        assert_eq!(out, "<span class=\"keyword\">f</span> (x &lt; <span class=\"number\">0x</span>");
+    }
+
+    #[test]
+    fn html_suggestions()
+    {
+        let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a = b\n");
+        let report = HtmlReport::new(&file);
+        report.emit(Diagnostic::error().with_message("missing `;`").with_suggestion(23..23, ";", "add a semicolon"));
+        let html = report.to_html();
+        assert!(html.contains("<p class=\"help\"><span class=\"level\">help</span>: add a semicolon</p>\n<p class=\"location\">a.c:1:10</p>"));
+        assert!(html.contains("<span class=\"line-number\">1</span>int a = b<del></del><ins>;</ins></pre>"));
+    }
+}
@@ -0,0 +1,273 @@
+use std::fmt::{Display, Write as _};
+use std::io;
+use std::sync::{Mutex, PoisonError};
+use crate::codemap::EasyLocation;
+use crate::json;
+use super::{degrade, Diagnostic, EasyReport, ReportError};
+use super::resolved::{level, Resolved, ResolvedLabel};
+use super::timestamp::{now, rfc3339};
+
+/// A reporting of the diagnostics as JSON objects (one per line),
+/// close to the `--error-format=json` output of `rustc`.
+///
+/// Each object holds the `level`, the `code` (or `null`), the `message`,
+/// the `spans` of the labels (resolved to their original file, line and
+/// column) and the notes, the suggestions and the child diagnostics
+/// as `children`.
+///
+/// The lines are written (and flushed) as soon as the diagnostics are emitted,
+/// so this NDJSON stream could be consumed by an editor during a long analysis,
+/// while the diagnostics are rendered elsewhere (see [`EasyReport::tee`]):
+/// ```no_run
+/// use codespan_preprocessed::PreprocessedFile;
+/// use codespan_preprocessed::reporting::{Diagnostic, EasyReport, EasyReporting, JsonReport};
+///
+/// let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a;\n");
+/// let report = EasyReporting::new(&file).tee(JsonReport::to_stdout(&file));
+/// report.emit(Diagnostic::warning().with_message("unused").with_primary_label(18..19, ""));
+/// ```
+///
+/// # Example
+/// ```
+/// use codespan_preprocessed::PreprocessedFile;
+/// use codespan_preprocessed::reporting::{Diagnostic, EasyReport, JsonReport};
+///
+/// let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a;\n");
+/// let report = JsonReport::new(&file, vec![]);
+/// report.emit(Diagnostic::error().with_code("E01").with_message("oops").with_primary_label(18..19, "here"));
+/// let output = String::from_utf8(report.into_writer()).unwrap();
+/// assert!(output.starts_with(r#"{"$message_type":"diagnostic","message":"oops","code":{"code":"E01","explanation":null},"level":"error","spans":[{"file_name":"a.c","byte_start":18,"byte_end":19,"line_start":1,"line_end":1,"column_start":5,"column_end":6,"is_primary":true,"label":"here"}]"#));
+/// ```
+pub struct JsonReport<'a,L:EasyLocation<'a>,W:io::Write>
+{
+    writer: Mutex<W>,
+    source: &'a L,
+    timestamps: bool
+}
+
+impl<'a,L:EasyLocation<'a>,W:io::Write> JsonReport<'a,L,W>
+{
+    pub fn new(source: &'a L, writer: W) -> Self
+    {
+        Self { writer: Mutex::new(writer), source, timestamps: false }
+    }
+
+    /// Records the time of emission of each diagnostic
+    /// (as an RFC 3339 `timestamp` in UTC), except on `wasm32-unknown-unknown`
+    /// (without clock).
+    #[inline]
+    pub fn with_timestamps(mut self, enabled: bool) -> Self
+    {
+        self.timestamps = enabled;
+        self
+    }
+
+    /// Gets back the writer of this reporting.
+    #[inline]
+    pub fn into_writer(self) -> W
+    {
+        self.writer.into_inner().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<'a,L:EasyLocation<'a>> JsonReport<'a,L,io::Stdout>
+{
+    /// Builds a reporting streamed to the standard output.
+    #[inline]
+    pub fn to_stdout(source: &'a L) -> Self
+    {
+        Self::new(source, io::stdout())
+    }
+}
+
+impl<'a,L:EasyLocation<'a>,W:io::Write> EasyReport for JsonReport<'a,L,W>
+{
+    #[inline]
+    fn emit<E: Display>(&self, diag: impl Into<Diagnostic<E>>)
+    {
+        degrade(self.try_emit(diag))
+    }
+
+    fn try_emit<E: Display>(&self, diag: impl Into<Diagnostic<E>>) -> Result<(), ReportError>
+    {
+        let mut diag = Resolved::new(self.source, diag.into());
+        diag.timestamp = self.timestamps.then(now).flatten();
+        let line = to_json(&diag);
+        let mut writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        writeln!(writer, "{}", line)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Serializes a resolved diagnostic as a JSON object.
+pub(crate) fn to_json(diag: &Resolved) -> String
+{
+    let mut out = String::from(r#"{"$message_type":"diagnostic","#);
+    write_fields(&mut out, diag);
+    out.push_str(r#","rendered":null"#);
+    if let Some(time) = diag.timestamp {
+        let _ = write!(out, r#","timestamp":"{}""#, rfc3339(time));
+    }
+    out.push('}');
+    out
+}
+
+/// Serializes the message, the code, the level, the spans and the children
+/// of a diagnostic (the child diagnostics are nested in the same way).
+fn write_fields(out: &mut String, diag: &Resolved)
+{
+    out.push_str(r#""message":"#);
+    json::write_string(out, &diag.message);
+    out.push_str(r#","code":"#);
+    if diag.code.is_empty() {
+        out.push_str("null");
+    } else {
+        out.push_str(r#"{"code":"#);
+        json::write_string(out, &diag.code);
+        out.push_str(r#","explanation":null}"#);
+    }
+    let _ = write!(out, r#","level":"{}","spans":["#, level(diag.severity));
+    for (i, label) in diag.labels.iter().enumerate() {
+        if i > 0 { out.push(','); }
+        write_span(out, label, None);
+    }
+    out.push_str(r#"],"children":["#);
+    let mut first = true;
+    let mut separate = |out: &mut String| if !std::mem::take(&mut first) { out.push(','); };
+    for note in &diag.notes {
+        separate(out);
+        out.push_str(r#"{"message":"#);
+        json::write_string(out, note);
+        out.push_str(r#","code":null,"level":"note","spans":[],"children":[],"rendered":null}"#);
+    }
+    // the suggestions are helps with a suggested replacement (as rustc)
+    for (span, replacement) in &diag.suggestions {
+        separate(out);
+        out.push_str(r#"{"message":"#);
+        json::write_string(out, &span.message);
+        out.push_str(r#","code":null,"level":"help","spans":["#);
+        write_span(out, span, Some(replacement));
+        out.push_str(r#"],"children":[],"rendered":null}"#);
+    }
+    for child in &diag.children {
+        separate(out);
+        out.push('{');
+        write_fields(out, child);
+        out.push_str(r#","rendered":null}"#);
+    }
+    out.push(']');
+}
+
+/// Serializes a span (with its suggested replacement, if any).
+fn write_span(out: &mut String, label: &ResolvedLabel, replacement: Option<&str>)
+{
+    out.push_str(r#"{"file_name":"#);
+    json::write_string(out, &label.file);
+    let _ = write!(out, r#","byte_start":{},"byte_end":{},"line_start":{},"line_end":{},"column_start":{},"column_end":{},"is_primary":{},"label":"#,
+                   label.bytes.start, label.bytes.end, label.start.line_number, label.end.line_number,
+                   label.start.column_number, label.end.column_number, label.primary || replacement.is_some());
+    match label.message.is_empty() || replacement.is_some() {
+        true => out.push_str("null"),
+        false => json::write_string(out, &label.message)
+    }
+    if let Some(replacement) = replacement {
+        out.push_str(r#","suggested_replacement":"#);
+        json::write_string(out, replacement);
+    }
+    out.push('}');
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json;
+    use crate::PreprocessedFile;
+    use crate::reporting::{Diagnostic, EasyReport};
+    use super::JsonReport;
+
+    #[test]
+    fn json_lines()
+    {
+        let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a;\n#line 7 \"b \\\"q\\\".h\"\nint b;\n");
+        let report = JsonReport::new(&file, vec![]);
+        report.emit(Diagnostic::warning().with_message("two\nlines").with_primary_label(39..42, "").with_note("a note"));
+        report.emit(Diagnostic::error().with_message("second"));
+        let output = String::from_utf8(report.into_writer()).unwrap();
+        let lines = output.lines().map(|line| json::parse(line).unwrap()).collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].get("message").and_then(json::Value::as_str), Some("two\nlines"));
+        assert_eq!(lines[0].get("code"), Some(&json::Value::Null));
+        let span = &lines[0].get("spans").and_then(json::Value::as_array).unwrap()[0];
+        assert_eq!(span.get("file_name").and_then(json::Value::as_str), Some("b \\\"q\\\".h"));
+        assert_eq!(span.get("line_start"), Some(&json::Value::Number(7.)));
+        assert_eq!(span.get("label"), Some(&json::Value::Null));
+        let notes = lines[0].get("children").and_then(json::Value::as_array).unwrap();
+        assert_eq!(notes[0].get("message").and_then(json::Value::as_str), Some("a note"));
+        assert_eq!(lines[1].get("level").and_then(json::Value::as_str), Some("error"));
+        assert_eq!(lines[1].get("timestamp"), None);
+
+        let report = JsonReport::new(&file, vec![]).with_timestamps(true);
+        report.emit(Diagnostic::error().with_message("timed"));
+        let line = json::parse(&String::from_utf8(report.into_writer()).unwrap()).unwrap();
+        assert!(line.get("timestamp").and_then(json::Value::as_str).is_some_and(|time| time.ends_with('Z')));
+    }
+
+    #[test]
+    fn json_streaming()
+    {
+        struct Stream(Vec<u8>, usize);
+        impl std::io::Write for Stream {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> { self.0.write(buf) }
+            fn flush(&mut self) -> std::io::Result<()> { self.1 += 1; Ok(()) }
+        }
+        let file = PreprocessedFile::new("int a;\n");
+        let report = JsonReport::new(&file, Stream(vec![], 0));
+        report.emit(Diagnostic::error().with_message("first"));
+        report.emit(Diagnostic::error().with_message("second"));
+        let stream = report.into_writer();
+        assert_eq!(stream.1, 2);
+        assert_eq!(stream.0.iter().filter(|b| **b == b'\n').count(), 2);
+    }
+
+    #[test]
+    fn json_suggestions()
+    {
+        let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a\n");
+        let report = JsonReport::new(&file, vec![]);
+        report.emit(Diagnostic::error().with_message("missing `;`").with_note("a note")
+            .with_suggestion(19..19, ";", "add a semicolon"));
+        let output = String::from_utf8(report.into_writer()).unwrap();
+        let line = json::parse(&output).unwrap();
+        let children = line.get("children").and_then(json::Value::as_array).unwrap();
+        assert_eq!(children.len(), 2);
+        assert_eq!(children[1].get("level").and_then(json::Value::as_str), Some("help"));
+        let span = &children[1].get("spans").and_then(json::Value::as_array).unwrap()[0];
+        assert_eq!(span.get("suggested_replacement").and_then(json::Value::as_str), Some(";"));
+        assert_eq!(span.get("column_start"), Some(&json::Value::Number(6.)));
+    }
+
+    #[test]
+    fn json_children()
+    {
+        let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a;\nint a;\n");
+        let report = JsonReport::new(&file, vec![]);
+        report.emit(Diagnostic::error().with_message("redefinition of `a`").with_note("a note")
+            .with_primary_label(25..26, "redefined here")
+            .with_child(Diagnostic::note().with_code("N01").with_message("previous definition")
+                .with_primary_label(18..19, "defined here").with_note("nested")));
+        let output = String::from_utf8(report.into_writer()).unwrap();
+        let line = json::parse(&output).unwrap();
+        let children = line.get("children").and_then(json::Value::as_array).unwrap();
+        assert_eq!(children.len(), 2);
+        let child = &children[1];
+        assert_eq!(child.get("message").and_then(json::Value::as_str), Some("previous definition"));
+        assert_eq!(child.get("level").and_then(json::Value::as_str), Some("note"));
+        assert_eq!(child.get("code").and_then(|code| code.get("code")).and_then(json::Value::as_str), Some("N01"));
+        assert_eq!(child.get("$message_type"), None);
+        let span = &child.get("spans").and_then(json::Value::as_array).unwrap()[0];
+        assert_eq!(span.get("line_start"), Some(&json::Value::Number(1.)));
+        assert_eq!(span.get("label").and_then(json::Value::as_str), Some("defined here"));
+        let nested = child.get("children").and_then(json::Value::as_array).unwrap();
+        assert_eq!(nested[0].get("message").and_then(json::Value::as_str), Some("nested"));
+    }
+}
@@ -0,0 +1,184 @@
+use std::fmt::{Display, Write as _};
+use std::io;
+use std::sync::{Mutex, PoisonError};
+use codespan_reporting::diagnostic::Severity;
+use crate::codemap::EasyLocation;
+use super::{Diagnostic, EasyReport};
+use super::html::escape;
+use super::resolved::{level, Resolved};
+
+/// The grouping of the diagnostics into test cases
+/// (see [`JunitReport::with_grouping`]).
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum JunitGrouping {
+    /// One test case per original file (of the primary label)
+    #[default]
+    File,
+    /// One test case per diagnostic code
+    Code
+}
+
+/// A reporting of the diagnostics as a JUnit XML report.
+///
+/// The diagnostics are accumulated and the report is written at once
+/// (see [`JunitReport::write`]). They are grouped into test cases (by file
+/// or by code), and a test case fails if it holds errors (or bugs): all of
+/// them are listed in its `failure`, the other diagnostics being its output.
+/// ```
+/// use codespan_preprocessed::PreprocessedFile;
+/// use codespan_preprocessed::reporting::{Diagnostic, EasyReport, JunitReport};
+///
+/// let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a;\n#line 1 \"b.c\"\nint b;\n");
+/// let report = JunitReport::new(&file, "mycc");
+/// report.emit(Diagnostic::error().with_code("E01").with_message("oops").with_primary_label(18..19, ""));
+/// report.emit(Diagnostic::warning().with_message("unused").with_primary_label(39..40, ""));
+/// let xml = report.to_junit();
+/// assert!(xml.contains(r#"<testsuite name="mycc" tests="2" failures="1" errors="0">"#));
+/// assert!(xml.contains(r#"<failure type="E01" message="oops">a.c:1:5: error[E01]: oops"#));
+/// assert!(xml.contains(r#"<testcase classname="mycc" name="b.c">"#));
+/// ```
+pub struct JunitReport<'a,L:EasyLocation<'a>>
+{
+    source: &'a L,
+    name: String,
+    grouping: JunitGrouping,
+    results: Mutex<Vec<Resolved>>
+}
+
+impl<'a,L:EasyLocation<'a>> JunitReport<'a,L>
+{
+    /// Builds a reporting whose test suite is named as the given tool.
+    pub fn new(source: &'a L, name: impl Into<String>) -> Self
+    {
+        Self { source, name: name.into(), grouping: JunitGrouping::default(), results: Mutex::default() }
+    }
+
+    /// Sets the grouping of the diagnostics into test cases.
+    #[inline]
+    pub fn with_grouping(mut self, grouping: JunitGrouping) -> Self
+    {
+        self.grouping = grouping;
+        self
+    }
+
+    /// Gets the JUnit report of the diagnostics emitted so far.
+    pub fn to_junit(&self) -> String
+    {
+        let results = self.results.lock().unwrap_or_else(PoisonError::into_inner);
+        // the test cases, in order of first appearance
+        let mut cases: Vec<(String, Vec<&Resolved>)> = vec![];
+        for result in results.iter() {
+            let key = match self.grouping {
+                JunitGrouping::File => result.labels.iter().find(|label| label.primary)
+                    .map_or("(no location)", |label| label.file.as_str()),
+                JunitGrouping::Code if result.code.is_empty() => "(no code)",
+                JunitGrouping::Code => result.code.as_str()
+            };
+            match cases.iter_mut().find(|(name, _)| name == key) {
+                Some((_, list)) => list.push(result),
+                None => cases.push((key.to_string(), vec![result]))
+            }
+        }
+        let failures = cases.iter().filter(|(_, list)| list.iter().any(|r| is_failure(r))).count();
+        let name = xml(&self.name);
+        let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let _ = writeln!(out, r#"<testsuites name="{}" tests="{}" failures="{}" errors="0">"#, name, cases.len(), failures);
+        let _ = writeln!(out, r#"  <testsuite name="{}" tests="{}" failures="{}" errors="0">"#, name, cases.len(), failures);
+        for (case, list) in &cases {
+            let _ = writeln!(out, r#"    <testcase classname="{}" name="{}">"#, name, xml(case));
+            let (errors, others): (Vec<&Resolved>, Vec<&Resolved>) = list.iter().partition(|r| is_failure(r));
+            if let Some(first) = errors.first() {
+                let lines = errors.iter().map(|r| xml(&describe(r))).collect::<Vec<_>>();
+                let _ = writeln!(out, r#"      <failure type="{}" message="{}">{}</failure>"#,
+                                 xml(&first.code), xml(&first.message), lines.join("\n"));
+            }
+            if !others.is_empty() {
+                let lines = others.iter().map(|r| xml(&describe(r))).collect::<Vec<_>>();
+                let _ = writeln!(out, "      <system-out>{}</system-out>", lines.join("\n"));
+            }
+            out.push_str("    </testcase>\n");
+        }
+        out.push_str("  </testsuite>\n</testsuites>\n");
+        out
+    }
+
+    /// Writes the JUnit report of the diagnostics emitted so far.
+    pub fn write<W:io::Write>(&self, mut writer: W) -> io::Result<()>
+    {
+        writer.write_all(self.to_junit().as_bytes())
+    }
+}
+
+impl<'a,L:EasyLocation<'a>> EasyReport for JunitReport<'a,L>
+{
+    fn emit<E: Display>(&self, diag: impl Into<Diagnostic<E>>)
+    {
+        let result = Resolved::new(self.source, diag.into());
+        self.results.lock().unwrap_or_else(PoisonError::into_inner).push(result);
+    }
+}
+
+#[inline]
+fn is_failure(result: &Resolved) -> bool
+{
+    matches!(result.severity, Severity::Bug | Severity::Error)
+}
+
+/// Describes a diagnostic on one line (as `file:line:column: level[code]: message`),
+/// followed by its children (one per indented line).
+fn describe(result: &Resolved) -> String
+{
+    let mut out = String::new();
+    describe_into(&mut out, result, 0);
+    out
+}
+
+fn describe_into(out: &mut String, result: &Resolved, depth: usize)
+{
+    if depth > 0 {
+        let _ = write!(out, "\n{:1$}", "", 2 * depth);
+    }
+    if let Some(label) = result.labels.iter().find(|label| label.primary) {
+        let _ = write!(out, "{}:{}:{}: ", label.file, label.start.line_number, label.start.column_number);
+    }
+    out.push_str(level(result.severity));
+    if !result.code.is_empty() {
+        let _ = write!(out, "[{}]", result.code);
+    }
+    let _ = write!(out, ": {}", result.message);
+    result.children.iter().for_each(|child| describe_into(out, child, depth + 1));
+}
+
+/// Escapes a text for XML (the control characters are not allowed).
+fn xml(text: &str) -> String
+{
+    escape(text).chars()
+        .filter(|c| !c.is_control() || matches!(c, '\t' | '\n' | '\r'))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::PreprocessedFile;
+    use crate::reporting::{Diagnostic, EasyReport};
+    use super::{JunitGrouping, JunitReport};
+
+    #[test]
+    fn junit_by_code()
+    {
+        let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a;\n");
+        let report = JunitReport::new(&file, "tool").with_grouping(JunitGrouping::Code);
+        report.emit(Diagnostic::error().with_code("E01").with_message("a < b"));
+        report.emit(Diagnostic::warning().with_code("W01").with_message("\x1B[1mbold\x1B[0m"));
+        report.emit(Diagnostic::error().with_code("E01").with_message("again").with_primary_label(18..19, ""));
+        let xml = report.to_junit();
+        assert!(xml.contains(r#"tests="2" failures="1""#));
+        assert!(xml.contains("<failure type=\"E01\" message=\"a &lt; b\">error[E01]: a &lt; b\na.c:1:5: error[E01]: again</failure>"));
+        assert!(xml.contains("<system-out>warning[W01]: [1mbold[0m</system-out>"));
+
+        let report = JunitReport::new(&file, "tool");
+        report.emit(Diagnostic::error().with_message("parent")
+            .with_child(Diagnostic::note().with_message("child").with_primary_label(18..19, "")));
+        assert!(report.to_junit().contains(">error: parent\n  a.c:1:5: note: child</failure>"));
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+/// The level of the diagnostics of a given code.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum LintLevel {
+    /// The diagnostics are not reported (`-A`)
+    Allow,
+    /// The diagnostics are reported as warnings (`-W`)
+    Warn,
+    /// The diagnostics are reported as errors (`-D`)
+    Deny
+}
+
+/// A table of the lint levels, keyed by diagnostic code.
+///
+/// It is consulted by [`EasyReporting`](super::EasyReporting) when a warning
+/// or an error is emitted (see [`EasyReporting::with_lint_levels`](super::EasyReporting::with_lint_levels)).
+/// The notes, helps and bugs are never affected.
+///
+/// # Example
+/// ```
+/// use codespan_preprocessed::reporting::{LintLevel, LintLevels};
+///
+/// let mut levels = LintLevels::new().deny("W001");
+/// // as given by the command-line flag `-A W002`
+/// levels.set_flag("-A", "W002").unwrap();
+/// assert_eq!(levels.get("W001"), Some(LintLevel::Deny));
+/// assert_eq!(levels.get("W002"), Some(LintLevel::Allow));
+/// assert_eq!(levels.get("W003"), None);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LintLevels {
+    levels: HashMap<String, LintLevel>
+}
+
+impl LintLevels {
+
+    #[inline]
+    pub fn new() -> Self { Self::default() }
+
+    /// Sets the level of the given code (overriding the previous one).
+    #[inline]
+    pub fn set(&mut self, code: impl Display, level: LintLevel)
+    {
+        self.levels.insert(code.to_string(), level);
+    }
+
+    /// Sets the level of the given code from a command-line flag
+    /// (`-A`, `-W` or `-D`, or their long forms `--allow`, `--warn` and `--deny`).
+    ///
+    /// Returns an error (the unknown flag) if the flag is not a level.
+    pub fn set_flag<'f>(&mut self, flag: &'f str, code: impl Display) -> Result<(), &'f str>
+    {
+        let level = match flag {
+            "-A" | "--allow" => LintLevel::Allow,
+            "-W" | "--warn" => LintLevel::Warn,
+            "-D" | "--deny" => LintLevel::Deny,
+            _ => return Err(flag)
+        };
+        self.set(code, level);
+        Ok(())
+    }
+
+    /// Gets the level of the given code (if set).
+    #[inline]
+    pub fn get(&self, code: &str) -> Option<LintLevel>
+    {
+        self.levels.get(code).copied()
+    }
+
+    /// Allows the diagnostics of the given code.
+    #[inline]
+    pub fn allow(mut self, code: impl Display) -> Self
+    {
+        self.set(code, LintLevel::Allow);
+        self
+    }
+
+    /// Reports the diagnostics of the given code as warnings.
+    #[inline]
+    pub fn warn(mut self, code: impl Display) -> Self
+    {
+        self.set(code, LintLevel::Warn);
+        self
+    }
+
+    /// Reports the diagnostics of the given code as errors.
+    #[inline]
+    pub fn deny(mut self, code: impl Display) -> Self
+    {
+        self.set(code, LintLevel::Deny);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::PreprocessedFile;
+    use crate::reporting::{Diagnostic, EasyReport, EasyReporting, EasyReportingStatus, LintLevels};
+
+    #[test]
+    fn lint_levels()
+    {
+        let file = PreprocessedFile::new("int a;\n");
+        let report = EasyReporting::to_buffer(&file)
+            .with_lint_levels(LintLevels::new().allow("A").warn("W").deny("D"));
+        report.emit(Diagnostic::warning().with_code("A").with_message("allowed"));
+        report.emit(Diagnostic::error().with_code("W").with_message("downgraded"));
+        report.emit(Diagnostic::warning().with_code("D").with_message("upgraded"));
+        report.emit(Diagnostic::note().with_code("D").with_message("kept"));
+        let rendered = report.rendered();
+        assert!(!rendered.contains("allowed"));
+        assert!(rendered.contains("warning[W]: downgraded"));
+        assert!(rendered.contains("error[D]: upgraded"));
+        assert!(rendered.contains("note[D]: kept"));
+        assert_eq!(report.status(), EasyReportingStatus::Errors(1));
+    }
+}
@@ -0,0 +1,55 @@
+/// Emits an error labeled at the given span, with a formatted message.
+///
+/// The code of the diagnostic is optionally given as `code = ...` before the message.
+/// ```
+/// use codespan_preprocessed::{report_error, report_warning, PreprocessedFile};
+/// use codespan_preprocessed::reporting::EasyReporting;
+///
+/// let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a = 'b';\n");
+/// let report = EasyReporting::to_buffer(&file);
+/// report_error!(report, 22..25, "expected {}, found {}", "int", "char");
+/// report_warning!(report, 18..19, code = "W01", "unused variable `{}`", "a");
+/// assert!(report.rendered().contains("error: expected int, found char"));
+/// assert!(report.rendered().contains("warning[W01]: unused variable `a`"));
+/// ```
+#[macro_export]
+macro_rules! report_error {
+    ($($arg:tt)+) => { $crate::__report!(error, $($arg)+) };
+}
+
+/// Emits a warning labeled at the given span, with a formatted message
+/// (see [`report_error!`]).
+#[macro_export]
+macro_rules! report_warning {
+    ($($arg:tt)+) => { $crate::__report!(warning, $($arg)+) };
+}
+
+/// Emits a note labeled at the given span, with a formatted message
+/// (see [`report_error!`]).
+#[macro_export]
+macro_rules! report_note {
+    ($($arg:tt)+) => { $crate::__report!(note, $($arg)+) };
+}
+
+/// Emits a help labeled at the given span, with a formatted message
+/// (see [`report_error!`]).
+#[macro_export]
+macro_rules! report_help {
+    ($($arg:tt)+) => { $crate::__report!(help, $($arg)+) };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __report {
+    ($severity:ident, $report:expr, $span:expr, code = $code:expr, $($arg:tt)+) => {
+        $crate::reporting::EasyReport::emit(&$report, $crate::reporting::Diagnostic::$severity()
+            .with_code($code)
+            .with_message(::std::format!($($arg)+))
+            .with_primary_label($span, ""))
+    };
+    ($severity:ident, $report:expr, $span:expr, $($arg:tt)+) => {
+        $crate::reporting::EasyReport::emit(&$report, $crate::reporting::Diagnostic::$severity()
+            .with_message(::std::format!($($arg)+))
+            .with_primary_label($span, ""))
+    };
+}
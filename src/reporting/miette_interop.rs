@@ -0,0 +1,101 @@
+use std::fmt::Display;
+use miette::{LabeledSpan, MietteDiagnostic, MietteError, MietteSpanContents, SourceCode, SourceSpan, SpanContents};
+use codespan_reporting::diagnostic::{LabelStyle, Severity};
+use crate::PreprocessedFile;
+use crate::codemap::EasyLocation;
+use super::Diagnostic;
+
+/// The spans are read in the preprocessed source but are named and
+/// numbered as in their original file. The context lines are restricted
+/// to the file slice of the span (they never show a directive).
+impl<S:AsRef<str> + Send + Sync> SourceCode for PreprocessedFile<S>
+{
+    fn read_span<'a>(&'a self, span: &SourceSpan, context_lines_before: usize, context_lines_after: usize)
+        -> Result<Box<dyn SpanContents<'a> + 'a>, MietteError>
+    {
+        if span.offset() + span.len() > self.len() {
+            return Err(MietteError::OutOfBounds);
+        }
+        let id = self.file_id(span.offset());
+        let first = self.lines.line_within(span.offset(), 0..self.lines.len());
+        let last = self.lines.line_within(span.offset() + span.len().saturating_sub(1), 0..self.lines.len());
+        let before = context_lines_before.min(first.saturating_sub(id.lines.start));
+        let after = context_lines_after.min(id.lines.end.saturating_sub(last+1));
+        let contents = self.source().read_span(span, before, after)?;
+        let name = codespan_reporting::files::Files::name(self, id).unwrap_or_default().to_string();
+        let line = (contents.line() as isize - id.offset).max(0) as usize;
+        let column = (contents.column() as isize + id.column).max(0) as usize;
+        Ok(Box::new(MietteSpanContents::new_named(name, contents.data(), *contents.span(), line, column, contents.line_count())))
+    }
+}
+
+/// The notes are gathered as the help of the miette diagnostic.
+impl<E:Display> From<Diagnostic<E>> for MietteDiagnostic
+{
+    fn from(diag: Diagnostic<E>) -> Self
+    {
+        let code = diag.code.to_string();
+        let mut miette = MietteDiagnostic::new(diag.message)
+            .with_severity(match diag.severity {
+                Severity::Bug | Severity::Error => miette::Severity::Error,
+                Severity::Warning => miette::Severity::Warning,
+                Severity::Note | Severity::Help => miette::Severity::Advice
+            })
+            .with_labels(diag.labels.into_iter().map(|(style, range, message)| {
+                let message = (!message.is_empty()).then_some(message);
+                match style {
+                    LabelStyle::Primary => LabeledSpan::new_primary_with_span(message, range),
+                    LabelStyle::Secondary => LabeledSpan::new_with_span(message, range)
+                }
+            }));
+        if !code.is_empty() {
+            miette = miette.with_code(code);
+        }
+        if !diag.notes.is_empty() {
+            miette = miette.with_help(diag.notes.join("\n"));
+        }
+        miette
+    }
+}
+
+impl<E:Display> Diagnostic<E>
+{
+    /// Converts this diagnostic into a miette report of the given source
+    /// (as a [`PreprocessedFile`], possibly shared).
+    /// ```
+    /// use codespan_preprocessed::PreprocessedFile;
+    /// use codespan_preprocessed::reporting::Diagnostic;
+    ///
+    /// let file = PreprocessedFile::new("#line 7 \"a.c\"\nint a;\n".to_string());
+    /// let report = Diagnostic::error().with_message("oops")
+    ///     .with_primary_label(18..19, "here")
+    ///     .into_miette_report(file);
+    /// assert_eq!(report.to_string(), "oops");
+    /// ```
+    pub fn into_miette_report(self, source: impl SourceCode + 'static) -> miette::Report
+    {
+        miette::Report::new(MietteDiagnostic::from(self)).with_source_code(source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use miette::{SourceCode, SourceSpan};
+    use crate::PreprocessedFile;
+
+    #[test]
+    fn miette_spans()
+    {
+        let file = PreprocessedFile::new("#line 7 \"a.c\"\nint a;\nint b;\n#line 1 \"b.c\"\nint c;\n");
+        let contents = file.read_span(&SourceSpan::from(25..26), 5, 5).unwrap();
+        assert_eq!(contents.name(), Some("a.c"));
+        // the context lines stop at the directives
+        assert_eq!(std::str::from_utf8(contents.data()).unwrap(), "int a;\nint b");
+        assert_eq!((contents.line(), contents.column()), (6, 0));
+
+        let contents = file.read_span(&SourceSpan::from(42..45), 1, 1).unwrap();
+        assert_eq!(contents.name(), Some("b.c"));
+        assert_eq!((contents.line(), contents.column()), (0, 0));
+        assert!(file.read_span(&SourceSpan::from(45..60), 0, 0).is_err());
+    }
+}
@@ -0,0 +1,2397 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Debug, Display, Formatter};
+use std::io::{IsTerminal, Write as _};
+use std::ops::Range;
+use std::process::{ExitCode, Termination};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+use codespan_reporting::diagnostic;
+use codespan_reporting::diagnostic::Severity;
+use codespan_reporting::files::{Files, Location};
+use codespan_reporting::term;
+use codespan_reporting::term::Config;
+use codespan_reporting::term::termcolor::{Buffer, ColorChoice, StandardStream, WriteColor};
+use crate::codemap::EasyLocation;
+use crate::{EasyLocated, PreprocessedFile};
+
+#[cfg(feature = "ariadne")]
+mod ariadne_interop;
+mod baseline;
+mod builder;
+mod catalog;
+mod collector;
+mod exit;
+mod footer;
+mod guard;
+mod html;
+mod json;
+mod junit;
+mod lints;
+mod macros;
+mod multi;
+mod pager;
+mod registry;
+#[cfg(feature = "miette")]
+mod miette_interop;
+mod resolved;
+#[cfg(feature = "sarif")]
+mod sarif;
+mod snippet;
+mod sink;
+mod std_errors;
+mod suggestion;
+mod tap;
+mod tee;
+mod template;
+mod theme;
+mod timestamp;
+
+#[cfg(feature = "ariadne")]
+pub use ariadne_interop::{AriadneCache, AriadneSpan};
+pub use baseline::{Finding, FindingsDiff};
+pub use builder::{EasyReportingBuilder, Output, Renderer};
+pub use catalog::{MessageCatalog, Summary};
+pub use collector::DiagnosticCollector;
+pub use exit::ExitPolicy;
+pub use footer::{StatusSummary, SummaryFormatter};
+pub use guard::DiagnosticBuilder;
+pub use html::HtmlReport;
+pub use json::JsonReport;
+pub use junit::{JunitGrouping, JunitReport};
+pub use lints::{LintLevel, LintLevels};
+pub use multi::{DynEasyReport, MultiReport, SeverityFilter};
+pub use pager::Pagination;
+pub use registry::{CodeInfo, CodeRegistry};
+#[cfg(feature = "sarif")]
+pub use sarif::SarifReport;
+pub use sink::CallbackWriter;
+pub use suggestion::{apply_suggestions, original_edits, suggestions_diff, OriginalEdit, Suggestion};
+pub use tap::TapReport;
+pub use tee::Tee;
+pub use template::DiagnosticTemplate;
+pub use theme::Theme;
+pub use crate::error::{ErrorsReported, OverlapError, ReportError};
+
+
+type AbortHook = Box<dyn Fn() + Send + Sync>;
+type EmitHook = Box<dyn for<'e> Fn(&EmittedDiagnostic<'e>) + Send + Sync>;
+type SeverityHook = Box<dyn Fn(&Diagnostic<String>) -> Option<Severity> + Send + Sync>;
+type OrderHook = Box<dyn for<'e> Fn(&EmittedDiagnostic<'e>, &EmittedDiagnostic<'e>) -> std::cmp::Ordering + Send + Sync>;
+type DedupKey = (Severity, String, String, Option<Range<usize>>);
+
+pub trait EasyReport
+{
+    /// Reports a diagnostic.
+    ///
+    /// The I/O errors (as a closed pipe) are ignored but an invalid
+    /// location is a bug which panics (see [`EasyReport::try_emit`]).
+    fn emit<E:Display>(&self, diag: impl Into<Diagnostic<E>>);
+
+    /// Reports a diagnostic or fails if it could not be written.
+    ///
+    /// By default, the diagnostic is reported with [`EasyReport::emit`].
+    #[inline]
+    fn try_emit<E:Display>(&self, diag: impl Into<Diagnostic<E>>) -> Result<(), ReportError>
+    {
+        self.emit(diag);
+        Ok(())
+    }
+
+    /// Reports a batch of diagnostics (in order).
+    ///
+    /// By default, each diagnostic is reported with [`EasyReport::emit`].
+    #[inline]
+    fn emit_all<E:Display,D:Into<Diagnostic<E>>>(&self, diags: impl IntoIterator<Item=D>)
+    {
+        diags.into_iter().for_each(|diag| self.emit(diag))
+    }
+
+    /// Reports a batch of diagnostics or fails at the first one
+    /// which could not be written.
+    ///
+    /// By default, each diagnostic is reported with [`EasyReport::try_emit`].
+    #[inline]
+    fn try_emit_all<E:Display,D:Into<Diagnostic<E>>>(&self, diags: impl IntoIterator<Item=D>) -> Result<(), ReportError>
+    {
+        diags.into_iter().try_for_each(|diag| self.try_emit(diag))
+    }
+
+    /// Reports a global diagnostic: a message which is not related
+    /// to any location of the source (as an invalid configuration).
+    #[inline]
+    fn emit_global(&self, severity: Severity, message: impl Into<String>)
+    {
+        self.emit(Diagnostic::new("", severity).with_message(message))
+    }
+
+    /// Forwards the diagnostics to this reporting and to another one
+    /// (see [`Tee`]).
+    #[inline]
+    fn tee<R:EasyReport>(self, other: R) -> Tee<Self,R> where Self: Sized
+    {
+        Tee::new(self, other)
+    }
+
+    /// Starts a diagnostic which should be emitted to this reporting
+    /// (see [`DiagnosticBuilder`]).
+    #[inline]
+    fn build<E:Display>(&self, diag: impl Into<Diagnostic<E>>) -> DiagnosticBuilder<'_,Self,E> where Self: Sized
+    {
+        DiagnosticBuilder::new(self, diag)
+    }
+}
+
+
+/// Policy for the warnings located in system headers.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum SystemHeaderPolicy {
+    /// The warnings are reported as usual
+    #[default]
+    Report,
+    /// The warnings are downgraded to notes
+    Downgrade,
+    /// The warnings are not reported at all
+    Suppress
+}
+
+/// The order of the diagnostics of a deferred emission
+/// (see [`EasyReporting::with_deferred_emission`]).
+///
+/// The diagnostics without location are emitted last and the
+/// order of emission is kept between equivalent diagnostics.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum SortKey {
+    /// By original file name, then line and column of the primary label
+    #[default]
+    Location,
+    /// By severity (the most severe first), then location
+    Severity,
+    /// By code, then location
+    Code,
+    /// By original file name of the primary label only
+    /// (the order of emission is kept within a file)
+    File
+}
+
+/// The verbosity of the rendered diagnostics.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Only the message line (with its location)
+    Quiet,
+    /// The message and the snippets
+    #[default]
+    Normal,
+    /// The message and the snippets, with notes on the preprocessed
+    /// bytes and the file slices of the labels
+    Verbose
+}
+
+/// A view of an emitted diagnostic (see [`EasyReporting::on_emit`]
+/// and [`EasyReporting::with_deferred_ordering`]).
+#[derive(Clone, Debug)]
+pub struct EmittedDiagnostic<'e> {
+    /// The severity (after the policies of the reporting)
+    pub severity: Severity,
+    /// The code (possibly empty)
+    pub code: &'e str,
+    /// The main message
+    pub message: &'e str,
+    /// The original file name and location of the primary label (if any)
+    pub location: Option<(String, Location)>
+}
+
+/// Rank of a severity (from the least severe).
+fn rank(severity: Severity) -> u8
+{
+    match severity {
+        Severity::Help => 0,
+        Severity::Note => 1,
+        Severity::Warning => 2,
+        Severity::Error => 3,
+        Severity::Bug => 4
+    }
+}
+
+/// A reporting of the diagnostics located in a source.
+///
+/// The diagnostics are written to the standard error by default
+/// but any colored writer could be used (see [`EasyReporting::with_writer`]).
+///
+/// # Thread safety
+/// A reporting is `Send + Sync` (as soon as its source is `Sync` and
+/// its writer is `Send`) so it could be shared by several threads.
+/// Each diagnostic is rendered apart and then written at once:
+/// the diagnostics emitted concurrently are never interleaved.
+pub struct EasyReporting<'a,L:EasyLocation<'a>,W:WriteColor=StandardStream>
+{
+    writer: Mutex<Option<W>>, // taken back only by `into_writer`
+    config: Config,
+    source: &'a L,
+    system_headers: SystemHeaderPolicy,
+    renderer: Renderer,
+    lint_levels: LintLevels,
+    severity_map: Option<SeverityHook>,
+    warnings_as_errors: bool,
+    warnings_as_errors_codes: HashMap<String,bool>,
+    max_errors: Option<u32>,
+    on_abort: Option<AbortHook>,
+    on_emit: Option<EmitHook>,
+    stopped: AtomicBool, // interior mutability
+    deduplicate: bool,
+    emitted: Mutex<HashSet<DedupKey>>,
+    duplicates: AtomicU32, // interior mutability
+    summarized: AtomicBool, // interior mutability
+    deferred: Option<SortKey>,
+    ordering: Option<OrderHook>,
+    pagination: Option<Pagination>,
+    interactive: bool, // the writer is the standard error of an interactive run
+    pending: Mutex<Vec<Diagnostic<String>>>,
+    errors: AtomicU32, // interior mutability (bugs included)
+    warnings: AtomicU32, // interior mutability
+    notes: AtomicU32, // interior mutability
+    helps: AtomicU32, // interior mutability
+    bugs: AtomicU32, // interior mutability
+    by_code: Mutex<HashMap<String,u32>>,
+    exit_policy: ExitPolicy,
+    verbosity: Verbosity,
+    catalog: Option<Box<dyn MessageCatalog>>,
+    registry: Option<CodeRegistry>,
+    sorted_labels: bool,
+    limit_per_code: Option<u32>,
+    summary_formatter: Option<Box<dyn SummaryFormatter>>,
+    timestamps: bool,
+    theme: Option<Theme>,
+    ascii: bool,
+    icons: bool,
+    context_lines: usize,
+    snippet_width: Option<usize>,
+    created: Option<Instant>
+}
+
+/// Gets the icon of a severity.
+fn icon(severity: Severity, ascii: bool) -> &'static str
+{
+    match (severity, ascii) {
+        (Severity::Bug, false) => "🐞",
+        (Severity::Error, false) => "✖",
+        (Severity::Warning, false) => "⚠",
+        (Severity::Note, false) => "ℹ",
+        (Severity::Help, false) => "💡",
+        (Severity::Bug, true) => "#",
+        (Severity::Error, true) => "x",
+        (Severity::Warning, true) => "!",
+        (Severity::Note, true) => "i",
+        (Severity::Help, true) => "?"
+    }
+}
+
+/// Ignores the I/O errors of an infallible reporting (see [`EasyReport::emit`]).
+pub(crate) fn degrade(result: Result<(), ReportError>)
+{
+    if let Err(err @ ReportError::InvalidLocation(_)) = result {
+        panic!("BUG when reporting errors... {}", err)
+    }
+}
+
+/// Starts the clock of the elapsed time (there is no clock on `wasm32-unknown-unknown`).
+#[inline]
+fn clock() -> Option<Instant>
+{
+    #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+    { None }
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    { Some(Instant::now()) }
+}
+
+impl <'a,L:EasyLocation<'a>,W:WriteColor> EasyReport for EasyReporting<'a,L,W>
+{
+    #[inline]
+    fn emit<E: Display>(&self, diag: impl Into<Diagnostic<E>>)
+    {
+        degrade(self.try_emit(diag))
+    }
+
+    fn try_emit<E: Display>(&self, diag: impl Into<Diagnostic<E>>) -> Result<(), ReportError>
+    {
+        let (mut rendered, mut aborting) = (vec![], false);
+        let result = self.accept(diag.into(), &mut rendered, &mut aborting);
+        self.write_rendered(&rendered, aborting).and(result)
+    }
+
+    /// Reports a batch of diagnostics, written at once: the diagnostics
+    /// emitted concurrently are not interleaved with the batch.
+    #[inline]
+    fn emit_all<E:Display,D:Into<Diagnostic<E>>>(&self, diags: impl IntoIterator<Item=D>)
+    {
+        degrade(self.try_emit_all(diags))
+    }
+
+    fn try_emit_all<E:Display,D:Into<Diagnostic<E>>>(&self, diags: impl IntoIterator<Item=D>) -> Result<(), ReportError>
+    {
+        let (mut rendered, mut aborting) = (vec![], false);
+        let result = diags.into_iter().try_for_each(|diag| self.accept(diag.into(), &mut rendered, &mut aborting));
+        self.write_rendered(&rendered, aborting).and(result)
+    }
+}
+
+/// The locked writer of a reporting.
+struct WriterGuard<'g,W>(MutexGuard<'g, Option<W>>);
+
+impl<W> std::ops::Deref for WriterGuard<'_,W>
+{
+    type Target = W;
+
+    #[inline]
+    fn deref(&self) -> &W { self.0.as_ref().unwrap() }
+}
+
+impl<W> std::ops::DerefMut for WriterGuard<'_,W>
+{
+    #[inline]
+    fn deref_mut(&mut self) -> &mut W { self.0.as_mut().unwrap() }
+}
+
+/// The deferred diagnostics which were not flushed are rendered
+/// when the reporting is dropped (the I/O errors are ignored),
+/// without pagination.
+impl<'a,L:EasyLocation<'a>,W:WriteColor> Drop for EasyReporting<'a,L,W>
+{
+    fn drop(&mut self)
+    {
+        // nothing is rendered while unwinding (the source could be inconsistent)
+        // nor once the writer is taken back
+        if !std::thread::panicking() && self.writer.get_mut().unwrap_or_else(PoisonError::into_inner).is_some() {
+            // no prompt nor pager while dropping
+            self.pagination = None;
+            let _ = self.try_flush();
+        }
+    }
+}
+
+/// The numbers of reported diagnostics, by severity
+/// (see [`EasyReporting::check_status`]).
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Statistics {
+    /// The errors (excluding the bugs)
+    pub errors: u32,
+    pub warnings: u32,
+    pub notes: u32,
+    pub helps: u32,
+    pub bugs: u32
+}
+
+impl Statistics {
+
+    /// Total number of reported diagnostics.
+    #[inline]
+    pub fn total(&self) -> u32
+    {
+        [self.warnings, self.notes, self.helps, self.bugs].into_iter()
+            .fold(self.errors, u32::saturating_add)
+    }
+
+    /// Gets the status of the reporting (the bugs are errors).
+    pub fn status(&self) -> EasyReportingStatus
+    {
+        match self.errors.saturating_add(self.bugs) {
+            0 => match self.warnings {
+                0 => EasyReportingStatus::Faultless,
+                n => EasyReportingStatus::Warnings(n)
+            }
+            n => EasyReportingStatus::Errors(n)
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EasyReportingStatus {
+    Faultless,
+    Warnings(u32),
+    Errors(u32)
+}
+
+impl EasyReportingStatus {
+
+    /// Checks if some errors were reported.
+    #[inline]
+    pub fn is_failure(self) -> bool { matches!(self, EasyReportingStatus::Errors(_)) }
+
+    /// Gets the exit code of this status with the default [`ExitPolicy`]:
+    /// the number of errors (clamped to 255) if some errors were reported,
+    /// [`ExitCode::SUCCESS`] otherwise.
+    ///
+    /// This is also the exit code of `main` returning a status and, unless
+    /// another policy is set, the one of [`EasyReporting::exit_code`].
+    /// ```
+    /// use std::process::ExitCode;
+    /// use codespan_preprocessed::PreprocessedFile;
+    /// use codespan_preprocessed::reporting::{Diagnostic, EasyReport, EasyReporting};
+    ///
+    /// let file = PreprocessedFile::new("int a;\n");
+    /// let report = EasyReporting::to_buffer(&file);
+    /// report.emit(Diagnostic::error().with_message("oops"));
+    /// report.emit(Diagnostic::error().with_message("oops again"));
+    /// assert_eq!(report.emit_status().exit_code(), ExitCode::from(2));
+    /// assert_eq!(report.exit_code(), ExitCode::from(2));
+    /// ```
+    #[inline]
+    pub fn exit_code(self) -> ExitCode
+    {
+        ExitPolicy::default().exit_code(self)
+    }
+
+    /// Exits the process if some errors were reported, with the number
+    /// of errors (clamped to 255) as exit code (see [`ExitPolicy`]).
+    ///
+    /// There is no process to exit on `wasm32-unknown-unknown`.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn exit_on_failure(self)
+    {
+        if self.is_failure() {
+            ExitPolicy::default().exit(self)
+        }
+    }
+}
+
+impl Termination for EasyReportingStatus
+{
+    #[inline]
+    fn report(self) -> ExitCode { self.exit_code() }
+}
+
+impl EasyReporting<'_,crate::PreprocessedFile<String>>
+{
+    /// Gets a builder to customize the output stream and the colors.
+    ///
+    /// The builder is not tied to the type of the source
+    /// (see [`EasyReportingBuilder::build`]).
+    #[inline]
+    pub fn builder() -> EasyReportingBuilder { EasyReportingBuilder::new() }
+}
+
+impl<'a,L:EasyLocation<'a>> EasyReporting<'a,L>
+{
+    pub fn new(source: &'a L) -> Self
+    {
+        Self::with_config(source,codespan_reporting::term::Config::default())
+    }
+
+    /// Builds a reporting to the standard error with the given configuration.
+    ///
+    /// The colors are chosen automatically, according to the environment
+    /// (`NO_COLOR`, `CLICOLOR` and `CLICOLOR_FORCE`) and to the standard
+    /// error being a terminal (see [`EasyReportingBuilder::color`]).
+    pub fn with_config(source: &'a L, config: Config) -> Self
+    {
+        let color = builder::color_choice(ColorChoice::Auto, |var| std::env::var_os(var), std::io::stderr().is_terminal());
+        let mut report = Self::with_writer(source, StandardStream::stderr(color), config);
+        report.interactive = pager::is_interactive();
+        report
+    }
+}
+
+impl<'a,L:EasyLocation<'a>,F:FnMut(&str)> EasyReporting<'a,L,CallbackWriter<F>>
+{
+    /// Builds an uncolored reporting passing the rendered lines to the
+    /// given callback (as to report in a browser, see [`CallbackWriter`]).
+    pub fn with_callback(source: &'a L, config: Config, callback: F) -> Self
+    {
+        Self::with_writer(source, CallbackWriter::new(callback), config)
+    }
+}
+
+impl<'a,L:EasyLocation<'a>> EasyReporting<'a,L,Buffer>
+{
+    /// Builds a reporting into an uncolored in-memory buffer
+    /// (useful to check the reported diagnostics in tests).
+    /// ```
+    /// use codespan_preprocessed::PreprocessedFile;
+    /// use codespan_preprocessed::reporting::{Diagnostic, EasyReport, EasyReporting};
+    ///
+    /// let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a;\n");
+    /// let report = EasyReporting::to_buffer(&file);
+    /// report.emit(Diagnostic::warning().with_message("unused").with_primary_label(18..19, "here"));
+    /// assert_eq!(report.rendered(), unindent::unindent("
+    ///     warning: unused
+    ///       ┌─ a.c:1:5
+    ///       │
+    ///     1 │ int a;
+    ///       │     ^ here
+    ///
+    /// "));
+    /// ```
+    pub fn to_buffer(source: &'a L) -> Self
+    {
+        Self::with_writer(source, Buffer::no_color(), Config::default())
+    }
+
+    /// Gets the text rendered so far.
+    pub fn rendered(&self) -> String
+    {
+        String::from_utf8_lossy(self.writer().as_slice()).into_owned()
+    }
+}
+
+impl<'a,L:EasyLocation<'a>,W:WriteColor> EasyReporting<'a,L,W>
+{
+    /// Builds a reporting to the given writer (a file, a pipe, a buffer...).
+    /// ```
+    /// use codespan_reporting::term::{Config, termcolor::NoColor};
+    /// use codespan_preprocessed::PreprocessedFile;
+    /// use codespan_preprocessed::reporting::{Diagnostic, EasyReport, EasyReporting};
+    ///
+    /// let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a;\n");
+    /// let report = EasyReporting::with_writer(&file, NoColor::new(vec![]), Config::default());
+    /// report.emit(Diagnostic::error().with_message("oops").with_primary_label(14..17, "here"));
+    /// let output = String::from_utf8(report.into_writer().into_inner()).unwrap();
+    /// assert!(output.starts_with("error: oops\n"));
+    /// ```
+    pub fn with_writer(source: &'a L, writer: W, config: Config) -> Self
+    {
+        Self {
+            writer: Mutex::new(Some(writer)),
+            config,
+            source,
+            system_headers: SystemHeaderPolicy::default(),
+            renderer: Renderer::default(),
+            lint_levels: LintLevels::default(),
+            severity_map: None,
+            warnings_as_errors: false,
+            warnings_as_errors_codes: HashMap::new(),
+            max_errors: None,
+            on_abort: None,
+            on_emit: None,
+            stopped: AtomicBool::new(false),
+            deduplicate: false,
+            emitted: Mutex::default(),
+            duplicates: AtomicU32::default(),
+            summarized: AtomicBool::default(),
+            deferred: None,
+            ordering: None,
+            pagination: None,
+            interactive: false,
+            pending: Mutex::default(),
+            errors: AtomicU32::default(),
+            warnings: AtomicU32::default(),
+            notes: AtomicU32::default(),
+            helps: AtomicU32::default(),
+            bugs: AtomicU32::default(),
+            by_code: Mutex::default(),
+            exit_policy: ExitPolicy::default(),
+            verbosity: Verbosity::default(),
+            catalog: None,
+            registry: None,
+            sorted_labels: false,
+            limit_per_code: None,
+            summary_formatter: None,
+            timestamps: false,
+            theme: None,
+            ascii: false,
+            icons: false,
+            context_lines: 0,
+            snippet_width: None,
+            created: clock()
+        }
+    }
+
+    /// Gets back the writer of this reporting
+    /// (after the rendering of the deferred diagnostics).
+    pub fn into_writer(self) -> W
+    {
+        self.flush();
+        self.writer.lock().unwrap_or_else(PoisonError::into_inner).take().unwrap()
+    }
+
+    /// Locks the writer (a diagnostic is written at once).
+    #[inline]
+    fn writer(&self) -> WriterGuard<'_, W>
+    {
+        WriterGuard(self.writer.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Sets the policy for the warnings located in system headers
+    /// (according to their primary label).
+    #[inline]
+    pub fn with_system_headers(mut self, policy: SystemHeaderPolicy) -> Self
+    {
+        self.system_headers = policy;
+        self
+    }
+
+    /// Sets the renderer of the diagnostics (codespan by default).
+    ///
+    /// The status messages are always rendered by codespan.
+    #[inline]
+    pub fn with_renderer(mut self, renderer: Renderer) -> Self
+    {
+        self.renderer = renderer;
+        self
+    }
+
+    /// Sets the levels of the diagnostics according to their code
+    /// (applied after the system-header policy).
+    #[inline]
+    pub fn with_lint_levels(mut self, levels: LintLevels) -> Self
+    {
+        self.lint_levels = levels;
+        self
+    }
+
+    /// Sets a hook which changes the severity of the emitted diagnostics
+    /// (or keeps it, by returning `None`), as to turn all the notes of a
+    /// phase into warnings or some warnings into notes after a date.
+    ///
+    /// The hook is applied after the system-header policy and the lint levels,
+    /// but before the upgrade of the warnings to errors.
+    /// ```
+    /// use codespan_reporting::diagnostic::Severity;
+    /// use codespan_preprocessed::PreprocessedFile;
+    /// use codespan_preprocessed::reporting::{Diagnostic, EasyReport, EasyReporting};
+    ///
+    /// let file = PreprocessedFile::new("int a;\n");
+    /// let report = EasyReporting::to_buffer(&file).with_severity_map(|diag| {
+    ///     (diag.code().starts_with("DEPR") && *diag.severity() == Severity::Warning).then_some(Severity::Note)
+    /// });
+    /// report.emit(Diagnostic::warning().with_code("DEPR01").with_message("deprecated"));
+    /// assert!(report.rendered().starts_with("note[DEPR01]: deprecated"));
+    /// ```
+    #[inline]
+    pub fn with_severity_map<F>(mut self, map: F) -> Self
+        where F: Fn(&Diagnostic<String>) -> Option<Severity> + Send + Sync + 'static
+    {
+        self.severity_map = Some(Box::new(map));
+        self
+    }
+
+    /// Upgrades the emitted warnings to errors (as `-Werror`),
+    /// unless overridden for their code.
+    ///
+    /// The system-header policy applies first, so a downgraded
+    /// warning is not upgraded.
+    /// ```
+    /// use codespan_preprocessed::PreprocessedFile;
+    /// use codespan_preprocessed::reporting::{Diagnostic, EasyReport, EasyReporting, EasyReportingStatus};
+    ///
+    /// let file = PreprocessedFile::new("int a;\n");
+    /// let report = EasyReporting::to_buffer(&file)
+    ///     .treat_warnings_as_errors(true)
+    ///     .treat_warning_as_error("W2", false);
+    /// report.emit(Diagnostic::warning().with_code("W1").with_message("unused"));
+    /// report.emit(Diagnostic::warning().with_code("W2").with_message("shadowed"));
+    /// assert!(report.rendered().starts_with("error[W1]: unused"));
+    /// assert_eq!(report.status(), EasyReportingStatus::Errors(1));
+    /// ```
+    #[inline]
+    pub fn treat_warnings_as_errors(mut self, enabled: bool) -> Self
+    {
+        self.warnings_as_errors = enabled;
+        self
+    }
+
+    /// Overrides the upgrade of the warnings of the given code
+    /// (see [`EasyReporting::treat_warnings_as_errors`]).
+    #[inline]
+    pub fn treat_warning_as_error(mut self, code: impl Display, enabled: bool) -> Self
+    {
+        self.warnings_as_errors_codes.insert(code.to_string(), enabled);
+        self
+    }
+
+    /// Checks if the warnings of the given code are upgraded to errors.
+    fn is_warning_as_error(&self, code: &str) -> bool
+    {
+        self.warnings_as_errors_codes.get(code).copied().unwrap_or(self.warnings_as_errors)
+    }
+
+    /// Sets the theme of the rendered diagnostics (see [`Theme`]),
+    /// applied on the styles of the configuration.
+    #[inline]
+    pub fn with_theme(mut self, theme: Theme) -> Self
+    {
+        self.theme = Some(theme);
+        self
+    }
+
+    /// Draws the snippets with ASCII characters only (for the dumb terminals
+    /// and the logs), instead of the box drawing ones.
+    ///
+    /// Disabling it restores the box drawing characters only if it was
+    /// enabled: the characters of a custom configuration are kept.
+    /// ```
+    /// use codespan_preprocessed::PreprocessedFile;
+    /// use codespan_preprocessed::reporting::{Diagnostic, EasyReport, EasyReporting};
+    ///
+    /// let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a;\n");
+    /// let report = EasyReporting::to_buffer(&file).with_ascii(true).with_icons(true);
+    /// report.emit(Diagnostic::error().with_message("oops").with_primary_label(18..19, "here"));
+    /// assert!(report.rendered().starts_with("x error: oops\n  --> a.c:1:5\n"));
+    /// assert!(report.rendered().is_ascii());
+    /// ```
+    #[inline]
+    pub fn with_ascii(mut self, enabled: bool) -> Self
+    {
+        if enabled {
+            self.config.chars = term::Chars::ascii();
+        } else if self.ascii {
+            self.config.chars = term::Chars::box_drawing();
+        }
+        self.ascii = enabled;
+        self
+    }
+
+    /// Prefixes each rendered diagnostic with an icon of its severity
+    /// (`✖`, `⚠`, `ℹ`, `💡` and `🐞`, or `x`, `!`, `i`, `?` and `#` in ASCII).
+    #[inline]
+    pub fn with_icons(mut self, enabled: bool) -> Self
+    {
+        self.icons = enabled;
+        self
+    }
+
+    /// Shows the given number of lines of context before and after each
+    /// labeled line (none by default), as the preprocessed output is hardly
+    /// readable line by line.
+    ///
+    /// Only the codespan renderer shows the context: within the snippets
+    /// of multi-line labels, it is shown before and after the snippet only.
+    /// ```
+    /// use codespan_preprocessed::PreprocessedFile;
+    /// use codespan_preprocessed::reporting::{Diagnostic, EasyReport, EasyReporting};
+    ///
+    /// let file = PreprocessedFile::new("#line 8 \"a.c\"\nint a;\nint b;\nint c;\nint d;\n");
+    /// let report = EasyReporting::to_buffer(&file).with_context_lines(1);
+    /// report.emit(Diagnostic::error().with_message("oops").with_primary_label(25..26, "here"));
+    /// assert_eq!(report.rendered(), "\
+    /// error: oops
+    ///    ┌─ a.c:9:5
+    ///    │
+    ///  8 │ int a;
+    ///  9 │ int b;
+    ///    │     ^ here
+    /// 10 │ int c;
+    ///
+    /// ");
+    /// ```
+    #[inline]
+    pub fn with_context_lines(mut self, lines: usize) -> Self
+    {
+        self.context_lines = lines;
+        self
+    }
+
+    /// Elides the source lines wider than the given number of columns
+    /// around their labels (with `…` markers), as the lines expanded by
+    /// the preprocessor could be huge. The carets stay aligned.
+    ///
+    /// Only the codespan renderer elides the lines.
+    /// ```
+    /// use codespan_preprocessed::PreprocessedFile;
+    /// use codespan_preprocessed::reporting::{Diagnostic, EasyReport, EasyReporting};
+    ///
+    /// let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a = 0 + 1 + 2 + 3 + 4 + x + 6 + 7 + 8 + 9;\n");
+    /// let report = EasyReporting::to_buffer(&file).with_max_snippet_width(16);
+    /// report.emit(Diagnostic::error().with_message("oops").with_primary_label(42..43, "here"));
+    /// assert_eq!(report.rendered(), "\
+    /// error: oops
+    ///   ┌─ a.c:1:29
+    ///   │
+    /// 1 │ …+ 4 + x + 6 + …
+    ///   │        ^ here
+    ///
+    /// ");
+    /// ```
+    #[inline]
+    pub fn with_max_snippet_width(mut self, width: usize) -> Self
+    {
+        self.snippet_width = Some(width);
+        self
+    }
+
+    /// Prefixes each rendered diagnostic with its time of rendering
+    /// (an RFC 3339 timestamp in UTC, as `[2024-03-01T12:30:05.042Z] error: ...`),
+    /// as to correlate the logs of a long-running process.
+    ///
+    /// There is no timestamp on `wasm32-unknown-unknown` (without clock).
+    /// ```
+    /// use codespan_preprocessed::PreprocessedFile;
+    /// use codespan_preprocessed::reporting::{Diagnostic, EasyReport, EasyReporting};
+    ///
+    /// let file = PreprocessedFile::new("int a;\n");
+    /// let report = EasyReporting::to_buffer(&file).with_timestamps(true);
+    /// report.emit(Diagnostic::error().with_message("oops"));
+    /// let rendered = report.rendered();
+    /// assert!(rendered.starts_with('['));
+    /// assert!(rendered.contains("Z] error: oops"));
+    /// ```
+    #[inline]
+    pub fn with_timestamps(mut self, enabled: bool) -> Self
+    {
+        self.timestamps = enabled;
+        self
+    }
+
+    /// Sets the formatter of the summary written by [`EasyReporting::emit_status`]
+    /// (see [`SummaryFormatter`]).
+    #[inline]
+    pub fn with_summary_formatter<F:SummaryFormatter + 'static>(mut self, formatter: F) -> Self
+    {
+        self.summary_formatter = Some(Box::new(formatter));
+        self
+    }
+
+    /// Renders at most the given number of diagnostics of each code.
+    ///
+    /// The following ones are counted but not rendered, and their
+    /// numbers are noted by [`EasyReporting::emit_status`].
+    /// ```
+    /// use codespan_preprocessed::PreprocessedFile;
+    /// use codespan_preprocessed::reporting::{Diagnostic, EasyReport, EasyReporting};
+    ///
+    /// let file = PreprocessedFile::new("int a;\n");
+    /// let report = EasyReporting::to_buffer(&file).limit_per_code(2);
+    /// (0..5).for_each(|_| report.emit(Diagnostic::warning().with_code("W042").with_message("oops")));
+    /// report.emit_status();
+    /// assert_eq!(report.rendered().matches("warning[W042]: oops").count(), 2);
+    /// assert!(report.rendered().contains("note: suppressed 3 additional W042 diagnostics"));
+    /// assert!(report.rendered().contains("5 warnings emitted"));
+    /// ```
+    #[inline]
+    pub fn limit_per_code(mut self, limit: u32) -> Self
+    {
+        self.limit_per_code = Some(limit);
+        self
+    }
+
+    /// Stops the reporting after the given number of errors.
+    ///
+    /// Once the limit is reached, a final error is emitted and the
+    /// abort hook (if any) is called. The following diagnostics are
+    /// then ignored (see [`EasyReporting::too_many_errors`]).
+    /// With a limit of 0, the first error is counted but not
+    /// rendered and stops the reporting.
+    /// ```
+    /// use codespan_preprocessed::PreprocessedFile;
+    /// use codespan_preprocessed::reporting::{Diagnostic, EasyReport, EasyReporting};
+    ///
+    /// let file = PreprocessedFile::new("int a;\n");
+    /// let report = EasyReporting::to_buffer(&file).with_max_errors(2);
+    /// (0..5).for_each(|_| report.emit(Diagnostic::error().with_message("oops")));
+    /// assert!(report.too_many_errors());
+    /// assert_eq!(report.rendered().matches("error: oops").count(), 2);
+    /// assert!(report.rendered().ends_with("error: too many errors emitted, stopping now\n\n"));
+    /// ```
+    #[inline]
+    pub fn with_max_errors(mut self, max: u32) -> Self
+    {
+        self.max_errors = Some(max);
+        self
+    }
+
+    /// Sets a hook called when the maximal number of errors is reached
+    /// (see [`EasyReporting::with_max_errors`]), as to exit the process.
+    ///
+    /// The hook is called once the diagnostics are written and flushed
+    /// (the deferred ones included, see [`EasyReporting::flush`]).
+    #[inline]
+    pub fn on_abort<F>(mut self, hook: F) -> Self
+        where F: Fn() + Send + Sync + 'static
+    {
+        self.on_abort = Some(Box::new(hook));
+        self
+    }
+
+    /// Emits only the first occurrence of identical diagnostics
+    /// (same severity, code, message and primary location).
+    ///
+    /// The number of suppressed duplicates is noted by [`EasyReporting::emit_status`].
+    /// ```
+    /// use codespan_preprocessed::PreprocessedFile;
+    /// use codespan_preprocessed::reporting::{Diagnostic, EasyReport, EasyReporting};
+    ///
+    /// let file = PreprocessedFile::new("int a;\n");
+    /// let report = EasyReporting::to_buffer(&file).with_deduplication(true);
+    /// (0..3).for_each(|_| report.emit(Diagnostic::warning().with_message("w").with_primary_label(4..5, "")));
+    /// report.emit_status();
+    /// assert_eq!(report.rendered().matches("warning: w").count(), 1);
+    /// assert!(report.rendered().contains("note: 2 duplicate diagnostics suppressed"));
+    /// ```
+    #[inline]
+    pub fn with_deduplication(mut self, enabled: bool) -> Self
+    {
+        self.deduplicate = enabled;
+        self
+    }
+
+    /// Defers the emission of the diagnostics up to the next
+    /// [`EasyReporting::flush`] which renders them in the given order.
+    ///
+    /// The diagnostics are still counted when emitted. Those which
+    /// are not flushed are rendered when the reporting is dropped.
+    /// ```
+    /// use codespan_preprocessed::PreprocessedFile;
+    /// use codespan_preprocessed::reporting::{Diagnostic, EasyReport, EasyReporting, SortKey};
+    ///
+    /// let file = PreprocessedFile::new("#line 1 \"b.c\"\nint b;\n#line 1 \"a.c\"\nint a;\n");
+    /// let report = EasyReporting::to_buffer(&file).with_deferred_emission(SortKey::Location);
+    /// report.emit(Diagnostic::error().with_message("in b").with_primary_label(14..17, ""));
+    /// report.emit(Diagnostic::error().with_message("in a").with_primary_label(35..38, ""));
+    /// assert!(report.rendered().is_empty());
+    /// report.flush();
+    /// assert!(report.rendered().starts_with("error: in a"));
+    /// ```
+    #[inline]
+    pub fn with_deferred_emission(mut self, order: SortKey) -> Self
+    {
+        self.deferred = Some(order);
+        self
+    }
+
+    /// Defers the emission of the diagnostics up to the next
+    /// [`EasyReporting::flush`] which renders them in the order of
+    /// the given comparator (instead of a [`SortKey`]).
+    ///
+    /// The sort is stable: the order of emission is kept between
+    /// equivalent diagnostics.
+    /// ```
+    /// use codespan_preprocessed::PreprocessedFile;
+    /// use codespan_preprocessed::reporting::{Diagnostic, EasyReport, EasyReporting, EmittedDiagnostic};
+    ///
+    /// let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a;\nint b;\n");
+    /// // by line, from the bottom of the file
+    /// let report = EasyReporting::to_buffer(&file).with_deferred_ordering(|a, b| {
+    ///     let line = |diag: &EmittedDiagnostic| diag.location.as_ref().map(|(_, l)| l.line_number);
+    ///     line(b).cmp(&line(a))
+    /// });
+    /// report.emit(Diagnostic::error().with_message("on a").with_primary_label(14..17, ""));
+    /// report.emit(Diagnostic::error().with_message("on b").with_primary_label(21..24, ""));
+    /// report.flush();
+    /// assert!(report.rendered().starts_with("error: on b"));
+    /// ```
+    #[inline]
+    pub fn with_deferred_ordering<F>(mut self, compare: F) -> Self
+        where F: for<'e> Fn(&EmittedDiagnostic<'e>, &EmittedDiagnostic<'e>) -> std::cmp::Ordering + Send + Sync + 'static
+    {
+        self.deferred.get_or_insert_with(SortKey::default);
+        self.ordering = Some(Box::new(compare));
+        self
+    }
+
+    /// Paginates the deferred diagnostics rendered by [`EasyReporting::flush`]
+    /// when the run is interactive (the standard input and error being
+    /// terminals), as to explore hundreds of findings.
+    ///
+    /// Only the reportings written to the standard error are paginated (as
+    /// built by [`EasyReporting::new`], [`EasyReporting::with_config`] or
+    /// by the [builder](EasyReportingBuilder) with [`Output::Stderr`]):
+    /// the diagnostics given to any other writer are written as usual.
+    ///
+    /// If the pager could not be started, the diagnostics are written
+    /// as usual. Quitting at the prompt drops the remaining diagnostics
+    /// (they are still counted).
+    #[inline]
+    pub fn with_pagination(mut self, pagination: Pagination) -> Self
+    {
+        self.pagination = Some(pagination);
+        self
+    }
+
+    /// Renders the deferred diagnostics (if any).
+    ///
+    /// The I/O errors are ignored (see [`EasyReporting::try_flush`]).
+    #[inline]
+    pub fn flush(&self)
+    {
+        degrade(self.try_flush())
+    }
+
+    /// Renders the deferred diagnostics (if any) or fails
+    /// at the first one which could not be written.
+    pub fn try_flush(&self) -> Result<(), ReportError>
+    {
+        let Some(order) = self.deferred else { return Ok(()) };
+        let mut pending = std::mem::take(&mut *self.pending.lock().unwrap_or_else(PoisonError::into_inner));
+        if let Some(compare) = &self.ordering {
+            let views = pending.iter()
+                .map(|diag| EmittedDiagnostic {
+                    severity: diag.severity,
+                    code: &diag.code,
+                    message: &diag.message,
+                    location: diag.primary_location().and_then(|range| self.original_location(range.start))
+                })
+                .collect::<Vec<_>>();
+            let mut order = (0..views.len()).collect::<Vec<_>>();
+            order.sort_by(|&a, &b| compare(&views[a], &views[b]));
+            let mut sorted = pending.into_iter().map(Some).collect::<Vec<_>>();
+            pending = order.into_iter().filter_map(|i| sorted[i].take()).collect();
+        } else {
+            pending.sort_by_cached_key(|diag| {
+                let location = diag.primary_location().map(|range| self.original_position(range.start));
+                let severity = match order {
+                    SortKey::Severity => Some(std::cmp::Reverse(rank(diag.severity))),
+                    _ => None
+                };
+                let code = (order == SortKey::Code).then(|| diag.code.clone());
+                let location = match order {
+                    SortKey::File => location.map(|(name, _)| (name, None)),
+                    _ => location
+                };
+                (severity, code, location.is_none(), location)
+            });
+        }
+        match self.pagination {
+            Some(pagination) if pending.len() > pagination.page() && self.interactive => self.paginate(pagination, pending),
+            _ => pending.into_iter().try_for_each(|diag| self.render(diag))
+        }
+    }
+
+    /// Renders the deferred diagnostics page by page.
+    fn paginate(&self, pagination: Pagination, pending: Vec<Diagnostic<String>>) -> Result<(), ReportError>
+    {
+        match pagination {
+            Pagination::Prompt(_) => {
+                for (i, diag) in pending.into_iter().enumerate() {
+                    if i > 0 && i % pagination.page() == 0 {
+                        let next = pager::prompt(&mut *self.writer(), &mut std::io::stdin().lock())?;
+                        if !next {
+                            break;
+                        }
+                    }
+                    self.render(diag)?;
+                }
+                Ok(())
+            }
+            Pagination::Pager(_) => {
+                let mut rendered = vec![];
+                for diag in pending {
+                    rendered.extend_from_slice(self.render_buffer(diag)?.as_slice());
+                }
+                // the diagnostics are still written if the pager is missing
+                if pager::page(&rendered).is_err() {
+                    self.writer().write_all(&rendered)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Gets the original file name, line and column of a byte (as a sort key).
+    fn original_position(&self, byte_index: usize) -> (String, Option<(usize, usize)>)
+    {
+        let id = self.source.file_id(byte_index);
+        let name = self.source.name(id).map(|name| name.to_string()).unwrap_or_default();
+        let location = self.source.location(id, byte_index).ok();
+        (name, location.map(|l| (l.line_number, l.column_number)))
+    }
+
+    /// Gets the original file name and location of a byte.
+    fn original_location(&self, byte_index: usize) -> Option<(String, Location)>
+    {
+        let id = self.source.file_id(byte_index);
+        let location = self.source.location(id, byte_index).ok()?;
+        Some((self.source.name(id).ok()?.to_string(), location))
+    }
+
+    /// Applies the policies of the reporting to a diagnostic, then renders
+    /// it into the given bytes (unless deferred).
+    fn accept<E:Display>(&self, diag: Diagnostic<E>, rendered: &mut Vec<u8>, aborting: &mut bool) -> Result<(), ReportError>
+    {
+        if self.stopped.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        let code = diag.code.to_string();
+        let mut diag = diag.with_code(code);
+        if diag.severity == Severity::Warning && self.system_headers != SystemHeaderPolicy::Report
+            && diag.primary_location().is_some_and(|range| self.source.is_system_header(self.source.file_id(range.start))) {
+            match self.system_headers {
+                SystemHeaderPolicy::Suppress => return Ok(()),
+                _ => diag.severity = Severity::Note
+            }
+        }
+        if matches!(diag.severity, Severity::Warning | Severity::Error) {
+            match self.lint_levels.get(&diag.code.to_string()) {
+                Some(LintLevel::Allow) => return Ok(()),
+                Some(LintLevel::Warn) => diag.severity = Severity::Warning,
+                Some(LintLevel::Deny) => diag.severity = Severity::Error,
+                None => {}
+            }
+        }
+        if let Some(severity) = self.severity_map.as_ref().and_then(|map| map(&diag)) {
+            diag.severity = severity;
+        }
+        if diag.severity == Severity::Warning && self.is_warning_as_error(&diag.code.to_string()) {
+            diag.severity = Severity::Error;
+        }
+        if self.deduplicate {
+            let key = (diag.severity, diag.code.to_string(), diag.message.clone(), diag.primary_location().cloned());
+            let mut emitted = self.emitted.lock().unwrap_or_else(PoisonError::into_inner);
+            if !emitted.insert(key) {
+                self.duplicates.fetch_add(1, Ordering::SeqCst);
+                return Ok(());
+            }
+        }
+        let mut suppressed = false;
+        let mut stopping = false;
+        match diag.severity {
+            Severity::Bug | Severity::Error => {
+                let errors = self.errors.fetch_add(1, Ordering::SeqCst) + 1;
+                if let Some(max) = self.max_errors {
+                    // decided from the count of this error only, so the concurrent
+                    // emissions stop once and skip the errors beyond the limit
+                    let last = max.max(1);
+                    if errors > last {
+                        self.errors.fetch_sub(1, Ordering::SeqCst);
+                        return Ok(());
+                    }
+                    stopping = errors == last;
+                    suppressed |= errors > max;
+                }
+                if diag.severity == Severity::Bug {
+                    self.bugs.fetch_add(1, Ordering::SeqCst);
+                }
+            }
+            Severity::Warning => {
+                self.warnings.fetch_add(1, Ordering::SeqCst);
+            }
+            Severity::Note => {
+                self.notes.fetch_add(1, Ordering::SeqCst);
+            }
+            Severity::Help => {
+                self.helps.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+        // counted once the diagnostic is kept (as the statistics)
+        let code = diag.code.to_string();
+        if !code.is_empty() {
+            let mut by_code = self.by_code.lock().unwrap_or_else(PoisonError::into_inner);
+            let count = by_code.entry(code).or_default();
+            *count += 1;
+            suppressed |= self.limit_per_code.is_some_and(|limit| *count > limit);
+        }
+        if let Some(hook) = &self.on_emit {
+            let code = diag.code.to_string();
+            let location = diag.primary_location().and_then(|range| self.original_location(range.start));
+            hook(&EmittedDiagnostic { severity: diag.severity, code: &code, message: &diag.message, location });
+        }
+        let result = if suppressed { Ok(()) } else { self.output(diag, rendered) };
+        if stopping {
+            self.stopped.store(true, Ordering::SeqCst);
+            let stop = self.output(Diagnostic::error().with_message(self.summary(Summary::TooManyErrors)), rendered);
+            *aborting = true;
+            return result.and(stop);
+        }
+        result
+    }
+
+    /// Writes the bytes rendered by an emission, then calls the abort hook
+    /// (once the diagnostics, the deferred ones included, are written).
+    fn write_rendered(&self, rendered: &[u8], aborting: bool) -> Result<(), ReportError>
+    {
+        let mut written = match rendered.is_empty() {
+            true => Ok(()),
+            false => self.writer().write_all(rendered).map_err(ReportError::from)
+        };
+        if aborting {
+            // the hook could exit the process, so the output is completed first
+            written = written.and_then(|_| {
+                self.try_flush()?;
+                self.writer().flush()?;
+                Ok(())
+            });
+            if let Some(hook) = &self.on_abort { hook() }
+        }
+        written
+    }
+
+    /// Renders a diagnostic into the given bytes or defers it.
+    fn output<E:Display>(&self, diag: Diagnostic<E>, rendered: &mut Vec<u8>) -> Result<(), ReportError>
+    {
+        if self.deferred.is_some() {
+            let code = diag.code.to_string();
+            self.pending.lock().unwrap_or_else(PoisonError::into_inner).push(diag.with_code(code));
+        } else {
+            rendered.extend_from_slice(self.render_buffer(diag)?.as_slice());
+        }
+        Ok(())
+    }
+
+    /// Renders a diagnostic with the selected renderer.
+    fn render<E:Display>(&self, diag: Diagnostic<E>) -> Result<(), ReportError>
+    {
+        // the diagnostic is rendered apart (with its children), then written at once
+        let buffer = self.render_buffer(diag)?;
+        self.writer().write_all(buffer.as_slice())?;
+        Ok(())
+    }
+
+    /// Renders a diagnostic (with its children) into a new buffer.
+    fn render_buffer<E:Display>(&self, diag: Diagnostic<E>) -> Result<Buffer, ReportError>
+    {
+        let colored = self.writer().supports_color();
+        let mut buffer = if colored { Buffer::ansi() } else { Buffer::no_color() };
+        if let Some(time) = self.timestamps.then(timestamp::now).flatten() {
+            write!(buffer, "[{}] ", timestamp::rfc3339(time))?;
+        }
+        if self.icons {
+            write!(buffer, "{} ", icon(diag.severity, self.ascii))?;
+        }
+        self.render_into(&mut buffer, diag)?;
+        Ok(buffer)
+    }
+
+    /// Renders a diagnostic and its children into a buffer.
+    fn render_into<E:Display>(&self, buffer: &mut Buffer, mut diag: Diagnostic<E>) -> Result<(), ReportError>
+    {
+        // the suggestions are rendered as helps, before the children
+        let children = std::mem::take(&mut diag.suggestions).into_iter()
+            .map(|suggestion| {
+                let mut help = Diagnostic::help().with_message(suggestion.message).with_code(String::new());
+                let label = match suggestion.replacement.as_str() {
+                    "" => "remove this".to_string(),
+                    replacement => format!("try `{}`", replacement)
+                };
+                help.labels.push((diagnostic::LabelStyle::Primary, suggestion.range, label));
+                help
+            })
+            .chain(std::mem::take(&mut diag.children))
+            .collect::<Vec<_>>();
+        if let Some(catalog) = &self.catalog {
+            diag.message = catalog.translate(&diag.message).into_owned();
+            diag.labels.iter_mut().for_each(|(_, _, message)| *message = catalog.translate(message).into_owned());
+            diag.notes.iter_mut().for_each(|note| *note = catalog.translate(note).into_owned());
+        }
+        if self.sorted_labels {
+            diag.labels.sort_by_cached_key(|(style, range, _)| {
+                (*style != diagnostic::LabelStyle::Primary, self.original_position(range.start))
+            });
+        }
+        if let Some(hint) = self.registry.as_ref().and_then(|registry| registry.hint(&diag.code.to_string())) {
+            diag.notes.push(hint);
+        }
+        if self.verbosity == Verbosity::Verbose {
+            let notes = diag.labels.iter()
+                .flat_map(|(_, range, _)| self.source.file_spans(range.clone()))
+                .map(|(id, range)| {
+                    let name = self.source.name(id).map(|name| name.to_string()).unwrap_or_default();
+                    let slice = self.source.file_bytes(id);
+                    format!("bytes {}..{} of the preprocessed source, in {} (bytes {}..{})",
+                            range.start, range.end, name, slice.start, slice.end)
+                })
+                .collect::<Vec<_>>();
+            diag.notes.extend(notes);
+        }
+        // the include chain is noted in the language of the catalog
+        if std::mem::take(&mut diag.include_notes) {
+            let included = diag.primary_location()
+                .map(|range| self.source.included_from(self.source.file_id(range.start)))
+                .unwrap_or_default();
+            diag.notes.extend(included.into_iter()
+                .map(|(name, line)| self.summary(Summary::IncludedFrom { file: name.to_string(), line })));
+        }
+        let config = match &self.theme {
+            Some(theme) => {
+                let styles = theme.styles(&self.config.styles, diag.severity, &diag.code.to_string());
+                Cow::Owned(Config { styles, ..self.config.clone() })
+            }
+            None => Cow::Borrowed(&self.config)
+        };
+        match self.renderer {
+            _ if self.verbosity == Verbosity::Quiet => {
+                let config = Config { display_style: term::DisplayStyle::Short, ..config.into_owned() };
+                let diag = diag.to_diagnostic(self.source);
+                term::emit(buffer, &config, self.source, &diag)?;
+            }
+            // ariadne drops the notes of a diagnostic without label
+            #[cfg(feature = "ariadne")]
+            Renderer::Ariadne if !diag.labels.is_empty() => {
+                let charset = if self.ascii { ariadne::CharSet::Ascii } else { ariadne::CharSet::Unicode };
+                let config = ariadne::Config::default().with_color(buffer.supports_color()).with_char_set(charset);
+                diag.to_ariadne(self.source, config)
+                    .write(AriadneCache::new(self.source), &mut *buffer)?;
+            }
+            _ if self.context_lines > 0 || self.snippet_width.is_some() => {
+                let diag = diag.to_diagnostic(self.source);
+                snippet::emit(buffer, &config, self.source, &diag, self.context_lines, self.snippet_width)?;
+            }
+            _ => {
+                let diag = diag.to_diagnostic(self.source);
+                term::emit(buffer, &config, self.source, &diag)?;
+            }
+        }
+        children.into_iter().try_for_each(|child| self.render_into(buffer, child))
+    }
+
+    /// Gets a message of the reporting (translated by the catalog, if any).
+    fn summary(&self, summary: Summary) -> String
+    {
+        match &self.catalog {
+            Some(catalog) => catalog.summary(summary),
+            None => summary.to_string()
+        }
+    }
+
+    /// Writes a status message (ignoring the I/O errors).
+    fn emit_message(&self, diag: diagnostic::Diagnostic<<L as Files<'a>>::FileId>)
+    {
+        degrade(term::emit(&mut *self.writer(), &self.config, self.source, &diag).map_err(ReportError::from))
+    }
+
+    /// Sets the verbosity of the rendered diagnostics.
+    ///
+    /// The quiet diagnostics are always rendered by codespan.
+    /// ```
+    /// use codespan_preprocessed::PreprocessedFile;
+    /// use codespan_preprocessed::reporting::{Diagnostic, EasyReport, EasyReporting, Verbosity};
+    ///
+    /// let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a;\n");
+    /// let report = EasyReporting::to_buffer(&file).with_verbosity(Verbosity::Quiet);
+    /// report.emit(Diagnostic::error().with_message("oops").with_primary_label(18..19, "here"));
+    /// assert_eq!(report.rendered(), "a.c:1:5: error: oops\n");
+    ///
+    /// let report = EasyReporting::to_buffer(&file).with_verbosity(Verbosity::Verbose);
+    /// report.emit(Diagnostic::error().with_message("oops").with_primary_label(18..19, "here"));
+    /// assert!(report.rendered().contains("= bytes 18..19 of the preprocessed source, in a.c (bytes 14..20)"));
+    /// ```
+    #[inline]
+    pub fn with_verbosity(mut self, verbosity: Verbosity) -> Self
+    {
+        self.verbosity = verbosity;
+        self
+    }
+
+    /// Sets the catalog translating the rendered messages
+    /// (see [`MessageCatalog`]).
+    #[inline]
+    pub fn with_catalog<C:MessageCatalog + 'static>(mut self, catalog: C) -> Self
+    {
+        self.catalog = Some(Box::new(catalog));
+        self
+    }
+
+    /// Sorts the labels of the rendered diagnostics: the primary ones first
+    /// (so their file is rendered first), then the secondary ones by
+    /// original file name, line and column. By default, the labels are
+    /// rendered in order of insertion.
+    /// ```
+    /// use codespan_preprocessed::PreprocessedFile;
+    /// use codespan_preprocessed::reporting::{Diagnostic, EasyReport, EasyReporting};
+    ///
+    /// let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a;\n#line 1 \"b.c\"\nint b;\n");
+    /// let report = EasyReporting::to_buffer(&file).with_sorted_labels(true);
+    /// report.emit(Diagnostic::error().with_message("oops")
+    ///     .with_secondary_label(18..19, "first")
+    ///     .with_primary_label(39..40, "here"));
+    /// let rendered = report.rendered();
+    /// assert!(rendered.find("b.c:1:5").unwrap() < rendered.find("a.c").unwrap());
+    /// ```
+    #[inline]
+    pub fn with_sorted_labels(mut self, enabled: bool) -> Self
+    {
+        self.sorted_labels = enabled;
+        self
+    }
+
+    /// Sets the registry of the diagnostic codes: the rendered diagnostics
+    /// of a registered code end with a hint to explain it.
+    /// ```
+    /// use codespan_preprocessed::PreprocessedFile;
+    /// use codespan_preprocessed::reporting::{CodeInfo, CodeRegistry, Diagnostic, EasyReport, EasyReporting};
+    ///
+    /// let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a;\n");
+    /// let registry = CodeRegistry::new("mycc").with_code("E001", CodeInfo::new("oops", "Something went wrong."));
+    /// let report = EasyReporting::to_buffer(&file).with_registry(registry);
+    /// report.emit(Diagnostic::error().with_code("E001").with_message("oops"));
+    /// assert!(report.rendered().contains("= for more information, run `mycc --explain E001`"));
+    /// ```
+    #[inline]
+    pub fn with_registry(mut self, registry: CodeRegistry) -> Self
+    {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Gets the registry of the diagnostic codes (if any).
+    #[inline]
+    pub fn registry(&self) -> Option<&CodeRegistry>
+    {
+        self.registry.as_ref()
+    }
+
+    /// Sets a hook called for each emitted diagnostic (once the policies
+    /// of the reporting are applied), as to feed metrics or progress bars.
+    /// ```
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::sync::Arc;
+    /// use codespan_preprocessed::PreprocessedFile;
+    /// use codespan_preprocessed::reporting::{Diagnostic, EasyReport, EasyReporting};
+    ///
+    /// let file = PreprocessedFile::new("#line 3 \"a.c\"\nint a;\n");
+    /// let lines = Arc::new(AtomicUsize::new(0));
+    /// let hook = lines.clone();
+    /// let report = EasyReporting::to_buffer(&file).on_emit(move |diag| {
+    ///     let (name, location) = diag.location.as_ref().unwrap();
+    ///     assert_eq!(name, "a.c");
+    ///     hook.store(location.line_number, Ordering::SeqCst);
+    /// });
+    /// report.emit(Diagnostic::error().with_message("oops").with_primary_label(18..19, ""));
+    /// assert_eq!(lines.load(Ordering::SeqCst), 3);
+    /// ```
+    #[inline]
+    pub fn on_emit<F>(mut self, hook: F) -> Self
+        where F: for<'e> Fn(&EmittedDiagnostic<'e>) + Send + Sync + 'static
+    {
+        self.on_emit = Some(Box::new(hook));
+        self
+    }
+
+    /// Checks if the reporting was stopped by the maximal number of errors.
+    #[inline]
+    pub fn too_many_errors(&self) -> bool
+    {
+        self.stopped.load(Ordering::SeqCst)
+    }
+
+    /// Number of reported errors (including bugs).
+    #[inline]
+    pub fn error_count(&self) -> u32 { self.errors.load(Ordering::SeqCst) }
+
+    /// Number of reported warnings.
+    #[inline]
+    pub fn warning_count(&self) -> u32 { self.warnings.load(Ordering::SeqCst) }
+
+    #[inline]
+    pub fn has_errors(&self) -> bool { self.error_count() > 0 }
+
+    #[inline]
+    pub fn has_warnings(&self) -> bool { self.warning_count() > 0 }
+
+    /// Gets the numbers of reported diagnostics by code, sorted by code
+    /// (the diagnostics without code are not counted).
+    /// ```
+    /// use codespan_preprocessed::PreprocessedFile;
+    /// use codespan_preprocessed::reporting::{Diagnostic, EasyReport, EasyReporting};
+    ///
+    /// let file = PreprocessedFile::new("int a;\n");
+    /// let report = EasyReporting::to_buffer(&file);
+    /// report.emit(Diagnostic::error().with_code("E042").with_message("oops"));
+    /// report.emit(Diagnostic::error().with_code("E042").with_message("oops again"));
+    /// report.emit(Diagnostic::warning().with_code("W001").with_message("unused"));
+    /// assert_eq!(report.stats_by_code(), [("E042".to_string(), 2), ("W001".to_string(), 1)]);
+    /// ```
+    pub fn stats_by_code(&self) -> Vec<(String, u32)>
+    {
+        let mut stats = self.by_code.lock().unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .map(|(code, count)| (code.clone(), *count))
+            .collect::<Vec<_>>();
+        stats.sort_unstable();
+        stats
+    }
+
+    /// Sets the policy of the exit code (see [`EasyReporting::exit_code`]).
+    #[inline]
+    pub fn with_exit_policy(mut self, policy: ExitPolicy) -> Self
+    {
+        self.exit_policy = policy;
+        self
+    }
+
+    /// Gets the exit code of the current status according to the exit policy
+    /// (by default, the one of [`EasyReportingStatus::exit_code`]).
+    /// ```
+    /// use std::process::ExitCode;
+    /// use codespan_preprocessed::PreprocessedFile;
+    /// use codespan_preprocessed::reporting::{Diagnostic, EasyReport, EasyReporting, ExitPolicy};
+    ///
+    /// let file = PreprocessedFile::new("int a;\n");
+    /// let report = EasyReporting::to_buffer(&file)
+    ///     .with_exit_policy(ExitPolicy::new().warnings_fail(true).failure(3));
+    /// report.emit(Diagnostic::warning().with_message("unused"));
+    /// assert_eq!(report.exit_code(), ExitCode::from(3));
+    /// ```
+    #[inline]
+    pub fn exit_code(&self) -> ExitCode
+    {
+        self.exit_policy.exit_code(self.status())
+    }
+
+    /// Gets the current status (without emitting it).
+    #[inline]
+    pub fn status(&self) -> EasyReportingStatus { self.check_status().status() }
+
+    /// Gets the numbers of reported diagnostics, by severity
+    /// (once the policies of the reporting are applied),
+    /// from which the status is given by [`Statistics::status`].
+    /// ```
+    /// use codespan_preprocessed::PreprocessedFile;
+    /// use codespan_preprocessed::reporting::{Diagnostic, EasyReport, EasyReporting, EasyReportingStatus};
+    ///
+    /// let file = PreprocessedFile::new("int a;\n");
+    /// let report = EasyReporting::to_buffer(&file);
+    /// report.emit(Diagnostic::note().with_message("fyi"));
+    /// report.emit(Diagnostic::bug().with_message("ice"));
+    /// let stats = report.check_status();
+    /// assert_eq!((stats.notes, stats.bugs, stats.errors, stats.total()), (1, 1, 0, 2));
+    /// assert_eq!(stats.status(), EasyReportingStatus::Errors(1));
+    /// ```
+    pub fn check_status(&self) -> Statistics
+    {
+        let bugs = self.bugs.load(Ordering::SeqCst);
+        Statistics {
+            errors: self.errors.load(Ordering::SeqCst).saturating_sub(bugs),
+            warnings: self.warnings.load(Ordering::SeqCst),
+            notes: self.notes.load(Ordering::SeqCst),
+            helps: self.helps.load(Ordering::SeqCst),
+            bugs
+        }
+    }
+
+    /// Emits the status and fails if some errors were reported,
+    /// as to stop a driver between two phases.
+    ///
+    /// The status is emitted only once: if it was already emitted
+    /// (by this method or [`EasyReporting::emit_status`]), the errors
+    /// are only checked.
+    /// ```
+    /// use codespan_preprocessed::PreprocessedFile;
+    /// use codespan_preprocessed::reporting::{Diagnostic, EasyReport, EasyReporting};
+    ///
+    /// let file = PreprocessedFile::new("int a;\n");
+    /// let report = EasyReporting::to_buffer(&file);
+    /// report.emit(Diagnostic::warning().with_message("unused"));
+    /// assert!(report.abort_if_errors().is_ok());
+    /// report.emit(Diagnostic::error().with_message("oops"));
+    /// assert_eq!(report.abort_if_errors().unwrap_err().errors, 1);
+    /// assert_eq!(report.abort_if_errors().unwrap_err().errors, 1);
+    /// assert_eq!(report.rendered().matches("1 error emitted").count(), 1);
+    /// ```
+    pub fn abort_if_errors(&self) -> Result<(), ErrorsReported>
+    {
+        let errors = match self.errors.load(Ordering::SeqCst) {
+            0 => return Ok(()),
+            _ if self.summarized.swap(true, Ordering::SeqCst) => self.errors.load(Ordering::SeqCst),
+            _ => match self.summarize() {
+                EasyReportingStatus::Errors(errors) => errors,
+                _ => return Ok(())
+            }
+        };
+        Err(ErrorsReported { errors })
+    }
+
+    /// Emits the status and exits the process with the given code
+    /// if some errors were reported.
+    ///
+    /// There is no process to exit on `wasm32-unknown-unknown`
+    /// (see [`EasyReporting::exit_code`]).
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn exit_if_errors(&self, code: i32)
+    {
+        if self.abort_if_errors().is_err() {
+            std::process::exit(code)
+        }
+    }
+
+    /// Displays the current status and returns it.
+    ///
+    /// The default summary could be replaced (see [`EasyReporting::with_summary_formatter`]).
+    ///
+    /// If this report contains only warnings, then the exit code of the status is [`ExitCode::SUCCESS`]
+    /// but if it contains one or more errors, it is the number of errors (see [`EasyReportingStatus::exit_code`]).
+    pub fn emit_status(&self) -> EasyReportingStatus
+    {
+        self.summarized.store(true, Ordering::SeqCst);
+        self.summarize()
+    }
+
+    /// Emits the summary of the reported diagnostics.
+    fn summarize(&self) -> EasyReportingStatus
+    {
+        self.flush();
+        if let Some(formatter) = &self.summary_formatter {
+            let statistics = self.check_status();
+            let summary = StatusSummary {
+                status: statistics.status(),
+                statistics,
+                by_code: self.stats_by_code(),
+                duplicates: self.duplicates.load(Ordering::SeqCst),
+                elapsed: self.created.map_or(Duration::ZERO, |created| created.elapsed())
+            };
+            let text = formatter.format(&summary);
+            if !text.is_empty() {
+                degrade(self.writer().write_all(text.as_bytes()).map_err(ReportError::from));
+            }
+            return summary.status;
+        }
+        let duplicates = self.duplicates.load(Ordering::SeqCst);
+        if duplicates > 0 {
+            self.emit_message(diagnostic::Diagnostic::note().with_message(self.summary(Summary::Duplicates(duplicates))));
+        }
+        if let Some(limit) = self.limit_per_code {
+            for (code, count) in self.stats_by_code().into_iter().filter(|(_, count)| *count > limit) {
+                let summary = Summary::Suppressed { count: count - limit, code };
+                self.emit_message(diagnostic::Diagnostic::note().with_message(self.summary(summary)));
+            }
+        }
+        let warns = self.warnings.load(Ordering::SeqCst);
+        if warns > 0 {
+            self.emit_message(diagnostic::Diagnostic::warning().with_message(self.summary(Summary::Warnings(warns))));
+        }
+        match self.errors.load(Ordering::SeqCst) {
+            0 => {
+                /* no errors was emmitted, good ! */
+                if warns == 0 { EasyReportingStatus::Faultless } else { EasyReportingStatus::Warnings(warns)}
+            },
+            n => {
+                self.emit_message(diagnostic::Diagnostic::error().with_message(self.summary(Summary::Errors(n))));
+                EasyReportingStatus::Errors(n)
+            }
+        }
+    }
+}
+
+impl<R:EasyReport> EasyReport for &R
+{
+    #[inline]
+    fn emit<E: Display>(&self, diag: impl Into<Diagnostic<E>>) {
+        EasyReport::emit(*self, diag)
+    }
+
+    #[inline]
+    fn try_emit<E: Display>(&self, diag: impl Into<Diagnostic<E>>) -> Result<(), ReportError> {
+        EasyReport::try_emit(*self, diag)
+    }
+
+    #[inline]
+    fn emit_all<E:Display,D:Into<Diagnostic<E>>>(&self, diags: impl IntoIterator<Item=D>) {
+        EasyReport::emit_all(*self, diags)
+    }
+
+    #[inline]
+    fn try_emit_all<E:Display,D:Into<Diagnostic<E>>>(&self, diags: impl IntoIterator<Item=D>) -> Result<(), ReportError> {
+        EasyReport::try_emit_all(*self, diags)
+    }
+}
+
+
+/// A diagnostic located in the preprocessed source.
+///
+/// With the `serde` feature, a diagnostic could be serialized (as to ship
+/// it from an analysis worker to an aggregator) and rendered later against
+/// the same codemap: its spans are bytes of the preprocessed source.
+/// The diagnostics are deserialized with owned codes (as `Diagnostic<String>`).
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Diagnostic<E:Display> {
+    code: E,
+    severity: Severity,
+    message: String,
+    labels: Vec<(diagnostic::LabelStyle,Range<usize>,String)>,
+    notes: Vec<String>,
+    include_notes: bool,
+    children: Vec<Diagnostic<String>>,
+    suggestions: Vec<Suggestion>,
+}
+
+impl Diagnostic<&'static str>
+{
+    #[inline]
+    pub fn bug() -> Self { Self::new("", Severity::Bug) }
+    #[inline]
+    pub fn error() -> Self { Self::new("", Severity::Error) }
+    #[inline]
+    pub fn warning() -> Self { Self::new("", Severity::Warning) }
+    #[inline]
+    pub fn note() -> Self { Self::new("", Severity::Note) }
+    #[inline]
+    pub fn help() -> Self { Self::new("", Severity::Help) }
+}
+
+impl<E:Display> Diagnostic<E>
+{
+    #[inline]
+    pub fn new(code: E, severity: Severity) -> Self
+    {
+        Self { code, severity, message: String::new(), labels: vec![], notes: vec![], include_notes: false, children: vec![], suggestions: vec![] }
+    }
+
+    #[inline]
+    pub fn code(&self) -> &E { &self.code }
+
+    /// Gets the location of the first primary label (if any).
+    #[inline]
+    pub fn primary_location(&self) -> Option<&Range<usize>>
+    {
+        self.labels.iter()
+            .find(|(style, _, _)| *style == diagnostic::LabelStyle::Primary)
+            .map(|(_, range, _)| range)
+    }
+
+    #[inline]
+    pub fn severity(&self) -> &Severity { &self.severity }
+
+    #[inline]
+    pub fn message(&self) -> &str { &self.message }
+
+    #[inline]
+    pub fn with_code<EE:Display>(self, code: EE) -> Diagnostic<EE>
+    {
+        Diagnostic {
+            code,
+            severity: self.severity,
+            message: self.message,
+            labels: self.labels,
+            notes: self.notes,
+            include_notes: self.include_notes,
+            children: self.children,
+            suggestions: self.suggestions
+        }
+    }
+
+    #[inline]
+    pub fn with_message(mut self, msg: impl Into<String>) -> Self
+    {
+        self.message = msg.into();
+        self
+    }
+
+    #[inline]
+    pub fn with_note(mut self, note: impl Into<String>) -> Self
+    {
+        self.notes.push(note.into());
+        self
+    }
+
+    #[inline]
+    pub fn with_labeled_note(mut self, label: impl AsRef<str>, note: impl Into<String>) -> Self
+    {
+        // add a note with a bold label
+        self.notes.push(format!("\x1B[1m{}\x1B[0m: {}", label.as_ref(), note.into()));
+        self
+    }
+
+
+    /// Adds a sub-diagnostic (as a related note) rendered right after
+    /// this one. The children are not counted by the reporting.
+    /// ```
+    /// use codespan_preprocessed::PreprocessedFile;
+    /// use codespan_preprocessed::reporting::{Diagnostic, EasyReport, EasyReporting};
+    ///
+    /// let file = PreprocessedFile::new("int a;\nint a;\n");
+    /// let report = EasyReporting::to_buffer(&file);
+    /// report.emit(Diagnostic::error().with_message("redefinition of `a`")
+    ///     .with_primary_label(11..12, "redefined here")
+    ///     .with_child(Diagnostic::note().with_message("previous definition")
+    ///         .with_primary_label(4..5, "defined here")));
+    /// assert_eq!(report.check_status().total(), 1);
+    /// assert!(report.rendered().contains("note: previous definition"));
+    /// ```
+    #[inline]
+    pub fn with_child<EE:Display>(mut self, child: impl Into<Diagnostic<EE>>) -> Self
+    {
+        let child = child.into();
+        let code = child.code.to_string();
+        self.children.push(child.with_code(code));
+        self
+    }
+
+    /// Gets the sub-diagnostics (see [`Diagnostic::with_child`]).
+    #[inline]
+    pub fn children(&self) -> &[Diagnostic<String>] { &self.children }
+
+    /// Adds a fix-it suggestion: the replacement of the given span.
+    ///
+    /// It is rendered as a help with the replacement shown inline
+    /// and it is exposed by the structured outputs.
+    /// ```
+    /// use codespan_preprocessed::PreprocessedFile;
+    /// use codespan_preprocessed::reporting::{Diagnostic, EasyReport, EasyReporting};
+    ///
+    /// let file = PreprocessedFile::new("int a\n");
+    /// let report = EasyReporting::to_buffer(&file);
+    /// report.emit(Diagnostic::error().with_message("missing `;`")
+    ///     .with_primary_label(4..5, "")
+    ///     .with_suggestion(5..5, ";", "add a semicolon"));
+    /// assert!(report.rendered().contains("help: add a semicolon"));
+    /// assert!(report.rendered().contains("try `;`"));
+    /// ```
+    #[inline]
+    pub fn with_suggestion(mut self, range: impl Into<Range<usize>>, replacement: impl Into<String>, message: impl Into<String>) -> Self
+    {
+        self.suggestions.push(Suggestion::new(range, replacement, message));
+        self
+    }
+
+    #[inline]
+    pub fn suggestions(&self) -> &[Suggestion] { &self.suggestions }
+
+    /// Appends the include chain of the primary label as notes.
+    ///
+    /// Each inclusion is noted as `in file included from foo.h:12`
+    /// (see [`EasyLocation::included_from`]), or as given by the
+    /// catalog of the reporting (see [`Summary::IncludedFrom`]).
+    #[inline]
+    pub fn with_include_notes(mut self) -> Self
+    {
+        self.include_notes = true;
+        self
+    }
+
+    /// Labels the given span as the location of the diagnostic.
+    ///
+    /// An empty span is a point (as the place of a missing token),
+    /// rendered as a caret at its column.
+    /// ```
+    /// use codespan_preprocessed::PreprocessedFile;
+    /// use codespan_preprocessed::reporting::{Diagnostic, EasyReport, EasyReporting};
+    ///
+    /// let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a\n");
+    /// let report = EasyReporting::to_buffer(&file);
+    /// report.emit(Diagnostic::error().with_message("missing `;`").with_primary_label(19..19, "expected `;`"));
+    /// assert!(report.rendered().contains("a.c:1:6"));
+    /// assert!(report.rendered().contains("│      ^ expected `;`"));
+    /// ```
+    #[inline]
+    pub fn with_primary_label(mut self, range: impl Into<Range<usize>>, msg: impl Into<String>) -> Self
+    {
+        self.labels.push((diagnostic::LabelStyle::Primary, range.into(), msg.into()));
+        self
+    }
+
+    #[inline]
+    pub fn with_primary_located_label<L:ToString>(self, label: EasyLocated<L>) -> Self
+    {
+        self.with_primary_label(label.location().clone(), label.to_string())
+    }
+
+    /// Labels a span given by its original file name, line and columns
+    /// (see [`PreprocessedFile::range_at`]) as the location of the diagnostic.
+    ///
+    /// A position which is not in the preprocessed source is
+    /// noted as `at NAME:LINE:COLUMN` instead.
+    /// ```
+    /// use codespan_preprocessed::PreprocessedFile;
+    /// use codespan_preprocessed::reporting::Diagnostic;
+    ///
+    /// let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a;\n");
+    /// let diag = Diagnostic::error().with_primary_label_at(&file, "a.c", 1, 5..6, "here");
+    /// assert_eq!(diag.primary_location(), Some(&(18..19)));
+    /// let diag = Diagnostic::error().with_message("oops").with_primary_label_at(&file, "b.c", 3, 1..2, "");
+    /// assert_eq!(diag.to_string(), "error: oops\n  = at b.c:3:1");
+    /// ```
+    #[inline]
+    pub fn with_primary_label_at<S:AsRef<str>>(self, file: &PreprocessedFile<S>, name: &str, line_number: usize,
+                                                columns: Range<usize>, msg: impl Into<String>) -> Self
+    {
+        match file.range_at(name, line_number, columns.clone()) {
+            Some(range) => self.with_primary_label(range, msg),
+            None => self.with_note(format!("at {}:{}:{}", name, line_number, columns.start))
+        }
+    }
+
+    #[inline]
+    pub fn with_secondary_label(mut self, range: impl Into<Range<usize>>, msg: impl Into<String>) -> Self
+    {
+        self.labels.push((diagnostic::LabelStyle::Secondary, range.into(), msg.into()));
+        self
+    }
+
+    #[inline]
+    pub fn with_secondary_located_label<L:ToString>(self, label: EasyLocated<L>) -> Self
+    {
+        self.with_secondary_label(label.location().clone(), label.to_string())
+    }
+
+    /// Labels a span given by its original file name, line and columns
+    /// (see [`Diagnostic::with_primary_label_at`]).
+    #[inline]
+    pub fn with_secondary_label_at<S:AsRef<str>>(self, file: &PreprocessedFile<S>, name: &str, line_number: usize,
+                                                  columns: Range<usize>, msg: impl Into<String>) -> Self
+    {
+        match file.range_at(name, line_number, columns.clone()) {
+            Some(range) => self.with_secondary_label(range, msg),
+            None => self.with_note(format!("at {}:{}:{}", name, line_number, columns.start))
+        }
+    }
+
+    /// Takes the notes, followed by the include chain (if required).
+    fn take_notes<'a,L:EasyLocation<'a>>(&mut self, src: &'a L) -> Vec<String>
+    {
+        let included = self.primary_location()
+            .filter(|_| self.include_notes)
+            .map(|range| src.included_from(src.file_id(range.start)))
+            .unwrap_or_default();
+        let mut notes = std::mem::take(&mut self.notes);
+        notes.extend(included
+            .into_iter()
+            .map(|(name, line)| Summary::IncludedFrom { file: name.to_string(), line }.to_string()));
+        notes
+    }
+
+    /// Renders the diagnostic (with its children and suggestions) without
+    /// color, as to embed it in another error, to log it or to check it in tests.
+    /// ```
+    /// use codespan_reporting::term::Config;
+    /// use codespan_preprocessed::PreprocessedFile;
+    /// use codespan_preprocessed::reporting::Diagnostic;
+    ///
+    /// let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a;\n");
+    /// let diag = Diagnostic::error().with_message("oops").with_primary_label(18..19, "here");
+    /// assert_eq!(diag.render_to_string(&file, &Config::default()), "\
+    /// error: oops
+    ///   ┌─ a.c:1:5
+    ///   │
+    /// 1 │ int a;
+    ///   │     ^ here
+    ///
+    /// ");
+    /// ```
+    pub fn render_to_string<'a,L:EasyLocation<'a>>(&self, src: &'a L, config: &Config) -> String
+    {
+        let diag = Diagnostic {
+            code: self.code.to_string(),
+            severity: self.severity,
+            message: self.message.clone(),
+            labels: self.labels.clone(),
+            notes: self.notes.clone(),
+            include_notes: self.include_notes,
+            children: self.children.clone(),
+            suggestions: self.suggestions.clone()
+        };
+        let report = EasyReporting::with_writer(src, Buffer::no_color(), config.clone());
+        report.emit(diag);
+        report.rendered()
+    }
+
+    pub fn to_diagnostic<'a,L:EasyLocation<'a>>(mut self, src: &'a L) -> diagnostic::Diagnostic<<L as Files<'a>>::FileId>
+    {
+        let notes = self.take_notes(src);
+        let diag = diagnostic::Diagnostic::new(self.severity)
+            .with_code(self.code.to_string())
+            .with_message(self.message)
+            .with_notes(notes);
+        // a global diagnostic is rendered without any snippet
+        if self.labels.is_empty() {
+            return diag;
+        }
+        diag.with_labels(self.labels
+                .into_iter()
+                .flat_map(|(style, range, message)| {
+                    // a label crossing a file boundary is split into one label per file,
+                    // the message being attached to the first one
+                    let mut message = Some(message);
+                    src.file_spans(range)
+                        .into_iter()
+                        .map(move |(id, range)| (diagnostic::Label::new(style, id, range), message.take().unwrap_or_default()))
+                })
+                .map(|(diag, message)| {
+                    if message.is_empty() { diag } else { diag.with_message(message) }
+                })
+                .collect()
+            )
+    }
+
+    #[inline]
+    pub fn report<R:EasyReport>(self, report: &R) { report.emit(self) }
+}
+
+impl<E:Display> Debug for Diagnostic<E>
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}: {}", self.code, self.message)?;
+        self.notes.iter().try_for_each(|note| writeln!(f,"   {}", note))?;
+        self.children.iter().try_for_each(|child| write!(f, "{:?}", child))
+    }
+}
+
+/// Displays the severity, the code and the message, followed by the notes
+/// (as a diagnostic without its labels).
+///
+/// So a diagnostic is also an error, which could be returned through `?`
+/// as a `Box<dyn Error>` (or an `anyhow::Error`) and emitted later.
+/// ```
+/// use std::error::Error;
+/// use codespan_preprocessed::reporting::Diagnostic;
+///
+/// fn check(value: i32) -> Result<(), Box<dyn Error + Send + Sync>> {
+///     if value < 0 {
+///         Err(Diagnostic::error().with_code("E01").with_message("negative value").with_note("expected a natural"))?;
+///     }
+///     Ok(())
+/// }
+/// let error = check(-1).unwrap_err();
+/// assert_eq!(error.to_string(), "error[E01]: negative value\n  = expected a natural");
+/// assert!(error.downcast_ref::<Diagnostic<&str>>().is_some());
+/// ```
+impl<E:Display> Display for Diagnostic<E>
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let code = self.code.to_string();
+        write!(f, "{}", resolved::level(self.severity))?;
+        if !code.is_empty() { write!(f, "[{}]", code)?; }
+        write!(f, ": {}", self.message)?;
+        self.notes.iter().try_for_each(|note| write!(f, "\n  = {}", note))
+    }
+}
+
+impl<E:Display> std::error::Error for Diagnostic<E> {}
+#[cfg(test)]
+mod tests {
+    use crate::PreprocessedFile;
+    use super::{Diagnostic, EasyReport, EasyReporting, SystemHeaderPolicy};
+
+    #[test]
+    fn system_header_warnings()
+    {
+        let file = PreprocessedFile::new("# 1 \"a.c\"\nint a;\n# 1 \"/usr/include/s.h\" 1 3\nint s;\n");
+        let warn = |start| Diagnostic::warning().with_message("w").with_primary_label(start..start+3, "");
+
+        let report = EasyReporting::to_buffer(&file).with_system_headers(SystemHeaderPolicy::Suppress);
+        report.emit(warn(10));
+        report.emit(warn(44));
+        assert_eq!(report.rendered().matches("warning: w").count(), 1);
+
+        let report = EasyReporting::to_buffer(&file).with_system_headers(SystemHeaderPolicy::Downgrade);
+        report.emit(warn(44));
+        assert!(report.rendered().starts_with("note: w"));
+    }
+
+    #[test]
+    fn translated_include_notes()
+    {
+        use super::{MessageCatalog, Summary};
+
+        struct French;
+
+        impl MessageCatalog for French {
+            fn summary(&self, summary: Summary) -> String {
+                match summary {
+                    Summary::IncludedFrom { file, line } => format!("dans le fichier inclus depuis {}:{}", file, line),
+                    _ => summary.to_string()
+                }
+            }
+        }
+
+        let file = PreprocessedFile::new("# 1 \"a.c\"\nint a;\n# 1 \"b.h\" 1\nint b;\n");
+        let diag = || Diagnostic::error().with_message("oops").with_primary_label(33..36, "").with_include_notes();
+        let report = EasyReporting::to_buffer(&file).with_catalog(French);
+        report.emit(diag());
+        assert!(report.rendered().contains("= dans le fichier inclus depuis a.c:2"));
+        assert!(!report.rendered().contains("in file included from"));
+
+        let report = EasyReporting::to_buffer(&file);
+        report.emit(diag());
+        assert_eq!(report.rendered().matches("= in file included from a.c:2").count(), 1);
+    }
+
+    #[test]
+    fn global_diagnostics()
+    {
+        use codespan_reporting::diagnostic::Severity;
+
+        let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a;\n");
+        let report = EasyReporting::to_buffer(&file);
+        report.emit_global(Severity::Error, "invalid configuration");
+        report.emit(Diagnostic::warning().with_message("unused").with_primary_label(18..19, ""));
+        report.emit(Diagnostic::note().with_message("no label").with_include_notes().with_note("a note"));
+        assert_eq!(report.rendered().matches("┌─").count(), 1);
+        assert!(report.rendered().starts_with("error: invalid configuration\n\n"));
+        assert!(report.rendered().ends_with("note: no label\n = a note\n\n"));
+    }
+
+    #[test]
+    fn themed_rendering()
+    {
+        use codespan_reporting::term::Config;
+        use codespan_reporting::term::termcolor::{Buffer, Color, ColorSpec};
+        use super::Theme;
+
+        let file = PreprocessedFile::new("int a;\n");
+        let theme = Theme::new().code_prefix("DEP", ColorSpec::new().set_fg(Some(Color::Magenta)).clone());
+        let report = EasyReporting::with_writer(&file, Buffer::ansi(), Config::default()).with_theme(theme);
+        report.emit(Diagnostic::warning().with_code("DEP1").with_message("deprecated"));
+        report.emit(Diagnostic::warning().with_code("W1").with_message("other"));
+        let rendered = String::from_utf8(report.into_writer().into_inner()).unwrap();
+        // magenta (35) for the deprecation only
+        assert!(rendered.starts_with("\x1b[0m\x1b[35mwarning[DEP1]"));
+        assert_eq!(rendered.matches("\x1b[35m").count(), 1);
+    }
+
+    #[test]
+    fn ascii_chars()
+    {
+        use codespan_reporting::term::{Chars, Config};
+        use codespan_reporting::term::termcolor::Buffer;
+
+        let file = PreprocessedFile::new("int a;\n");
+        let mut config = Config::default();
+        config.chars.source_border_left = '!';
+        let report = EasyReporting::with_writer(&file, Buffer::no_color(), config).with_ascii(false);
+        report.emit(Diagnostic::error().with_message("custom").with_primary_label(4..5, ""));
+        assert!(report.rendered().contains("1 ! int a;"));
+
+        let report = EasyReporting::to_buffer(&file).with_ascii(true).with_ascii(false);
+        report.emit(Diagnostic::error().with_message("boxed").with_primary_label(4..5, ""));
+        assert!(report.rendered().contains(&format!("1 {} int a;", Chars::box_drawing().source_border_left)));
+    }
+
+    #[test]
+    fn paginated_writer()
+    {
+        use super::{Pagination, SortKey};
+
+        // a buffer is never paginated (no prompt is written)
+        let file = PreprocessedFile::new("int a;\n");
+        let report = EasyReporting::to_buffer(&file).with_deferred_emission(SortKey::Location).with_pagination(Pagination::Prompt(1));
+        report.emit(Diagnostic::error().with_message("first"));
+        report.emit(Diagnostic::error().with_message("second"));
+        report.flush();
+        assert_eq!(report.rendered(), "error: first\n\nerror: second\n\n");
+    }
+
+    #[test]
+    fn abort_hook()
+    {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        let file = PreprocessedFile::new("int a;\n");
+        let aborted = Arc::new(AtomicU32::new(0));
+        let hook = aborted.clone();
+        let report = EasyReporting::to_buffer(&file)
+            .with_max_errors(1)
+            .on_abort(move || { hook.fetch_add(1, Ordering::SeqCst); });
+        report.emit(Diagnostic::warning().with_message("w"));
+        assert!(!report.too_many_errors());
+        report.emit(Diagnostic::error().with_message("e"));
+        report.emit(Diagnostic::error().with_message("e"));
+        assert!(report.too_many_errors());
+        assert_eq!(aborted.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn abort_after_output()
+    {
+        use std::io;
+        use std::sync::{Arc, Mutex};
+        use codespan_reporting::term::Config;
+        use codespan_reporting::term::termcolor::NoColor;
+        use super::SortKey;
+
+        #[derive(Clone, Default)]
+        struct Shared(Arc<Mutex<Vec<u8>>>);
+        impl io::Write for Shared {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> { self.0.lock().unwrap().write(buf) }
+            fn flush(&mut self) -> io::Result<()> { Ok(()) }
+        }
+
+        let file = PreprocessedFile::new("int a;\n");
+        for deferred in [false, true] {
+            let (output, seen) = (Shared::default(), Shared::default());
+            let (written, hook) = (output.clone(), seen.clone());
+            let mut report = EasyReporting::with_writer(&file, NoColor::new(output), Config::default())
+                .with_max_errors(2)
+                .on_abort(move || hook.0.lock().unwrap().extend(written.0.lock().unwrap().iter()));
+            if deferred {
+                report = report.with_deferred_emission(SortKey::Location);
+            }
+            report.emit_all([Diagnostic::error().with_message("first"), Diagnostic::error().with_message("second")]);
+            // the hook sees the diagnostics and the stop message
+            let seen = String::from_utf8(seen.0.lock().unwrap().clone()).unwrap();
+            assert!(seen.contains("error: first") && seen.contains("error: second"));
+            assert!(seen.ends_with("error: too many errors emitted, stopping now\n\n"));
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialized_diagnostics()
+    {
+        let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a;\n");
+        let diag = Diagnostic::error().with_code("E01").with_message("oops")
+            .with_primary_label(18..19, "here")
+            .with_note("a note")
+            .with_child(Diagnostic::help().with_message("try again"));
+        let json = serde_json::to_string(&diag).unwrap();
+        let shipped: Diagnostic<String> = serde_json::from_str(&json).unwrap();
+
+        let expected = EasyReporting::to_buffer(&file);
+        expected.emit(diag);
+        let report = EasyReporting::to_buffer(&file);
+        report.emit(shipped);
+        assert_eq!(report.rendered(), expected.rendered());
+    }
+
+    #[test]
+    fn saturated_statistics()
+    {
+        use super::{EasyReportingStatus, Statistics};
+
+        let stats = Statistics { errors: u32::MAX, warnings: 1, bugs: 1, ..Statistics::default() };
+        assert_eq!(stats.total(), u32::MAX);
+        assert_eq!(stats.status(), EasyReportingStatus::Errors(u32::MAX));
+    }
+
+    #[test]
+    fn no_error_allowed()
+    {
+        let file = PreprocessedFile::new("int a;\n");
+        let report = EasyReporting::to_buffer(&file).with_max_errors(0);
+        report.emit(Diagnostic::warning().with_message("w"));
+        assert!(!report.too_many_errors());
+        report.emit(Diagnostic::error().with_message("e"));
+        report.emit(Diagnostic::error().with_message("e"));
+        assert!(report.too_many_errors());
+        assert_eq!(report.rendered().matches("error: e").count(), 0);
+        assert_eq!(report.rendered().matches("too many errors").count(), 1);
+        assert_eq!(report.check_status().errors, 1);
+    }
+
+    #[test]
+    fn deferred_order()
+    {
+        use super::SortKey;
+
+        let file = PreprocessedFile::new("int a;\nint b;\n");
+        let report = EasyReporting::to_buffer(&file).with_deferred_emission(SortKey::Severity);
+        report.emit(Diagnostic::error().with_message("global"));
+        report.emit(Diagnostic::warning().with_message("first").with_primary_label(0..3, ""));
+        report.emit(Diagnostic::error().with_message("second").with_primary_label(7..10, ""));
+        report.emit(Diagnostic::error().with_message("first").with_primary_label(0..3, ""));
+        report.flush();
+        let order = report.rendered().lines()
+            .filter(|line| line.starts_with("error") || line.starts_with("warning"))
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(order, ["error: first", "error: second", "error: global", "warning: first"]);
+
+        let file = PreprocessedFile::new("#line 1 \"b.c\"\nint b;\n#line 1 \"a.c\"\nint a;\n");
+        let report = EasyReporting::to_buffer(&file).with_deferred_emission(SortKey::File);
+        report.emit(Diagnostic::error().with_message("second").with_primary_label(35..38, ""));
+        report.emit(Diagnostic::error().with_message("in b").with_primary_label(14..17, ""));
+        report.emit(Diagnostic::error().with_message("first").with_primary_label(39..40, ""));
+        report.flush();
+        let order = report.rendered().lines()
+            .filter(|line| line.starts_with("error"))
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(order, ["error: second", "error: first", "error: in b"]);
+    }
+
+    #[test]
+    fn flush_on_drop()
+    {
+        use codespan_reporting::term::Config;
+        use codespan_reporting::term::termcolor::NoColor;
+        use super::SortKey;
+
+        let file = PreprocessedFile::new("#line 1 \"b.c\"\nint b;\n#line 1 \"a.c\"\nint a;\n");
+        let mut out = vec![];
+        {
+            let report = EasyReporting::with_writer(&file, NoColor::new(&mut out), Config::default())
+                .with_deferred_emission(SortKey::Location);
+            report.emit(Diagnostic::error().with_message("in b").with_primary_label(14..17, ""));
+            report.emit(Diagnostic::error().with_message("in a").with_primary_label(35..38, ""));
+        }
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("error: in a"));
+        assert_eq!(out.matches("error: in").count(), 2);
+
+        // nor prompted, even in an interactive run
+        let mut out = vec![];
+        {
+            let mut report = EasyReporting::with_writer(&file, NoColor::new(&mut out), Config::default())
+                .with_deferred_emission(SortKey::Location)
+                .with_pagination(super::Pagination::Prompt(1));
+            report.interactive = true;
+            report.emit(Diagnostic::error().with_message("in b").with_primary_label(14..17, ""));
+            report.emit(Diagnostic::error().with_message("in a").with_primary_label(35..38, ""));
+        }
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.matches("error: in").count(), 2);
+        assert!(!out.contains("-- more --"));
+
+        // the writer taken back gets the deferred diagnostics (once)
+        let report = EasyReporting::to_buffer(&file).with_deferred_emission(SortKey::Location);
+        report.emit(Diagnostic::error().with_message("in b").with_primary_label(14..17, ""));
+        let buffer = report.into_writer();
+        assert!(String::from_utf8_lossy(buffer.as_slice()).starts_with("error: in b"));
+    }
+
+    #[test]
+    fn batched_emission()
+    {
+        let file = PreprocessedFile::new("int a;\n");
+        let report = EasyReporting::to_buffer(&file).with_max_errors(2);
+        // nothing is written before the end of the batch
+        report.emit_all((0..5).map(|i| {
+            assert!(report.rendered().is_empty());
+            Diagnostic::error().with_message(format!("oops {}", i))
+        }));
+        assert_eq!(report.rendered().matches("error: oops").count(), 2);
+        assert!(report.rendered().ends_with("error: too many errors emitted, stopping now\n\n"));
+    }
+
+    #[test]
+    fn concurrent_emission()
+    {
+        use rayon::prelude::*;
+
+        fn shared<T:Send + Sync>(_: &T) {}
+
+        let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a;\nint b;\n");
+        let report = EasyReporting::to_buffer(&file);
+        shared(&report);
+        (0..200).into_par_iter().for_each(|i| {
+            let (start, name) = if i % 2 == 0 { (14, "a") } else { (21, "b") };
+            report.emit(Diagnostic::error().with_message(format!("oops {}", i))
+                .with_primary_label(start+4..start+5, name)
+                .with_note(format!("note {}", i)));
+        });
+        let rendered = report.rendered();
+        let blocks = rendered.split("\n\n").filter(|block| !block.is_empty()).collect::<Vec<_>>();
+        assert_eq!(blocks.len(), 200);
+        for block in blocks {
+            let i = block.lines().next().unwrap().strip_prefix("error: oops ").unwrap();
+            assert!(block.ends_with(&format!("= note {}", i)));
+        }
+    }
+
+    #[test]
+    fn concurrent_limit()
+    {
+        use rayon::prelude::*;
+
+        let file = PreprocessedFile::new("int a;\n");
+        let report = EasyReporting::to_buffer(&file).with_max_errors(10);
+        (0..200).into_par_iter().for_each(|i| report.emit(Diagnostic::error().with_code("E1").with_message(format!("oops {}", i))));
+        assert_eq!(report.rendered().matches("error[E1]: oops").count(), 10);
+        assert_eq!(report.rendered().matches("too many errors").count(), 1);
+        assert_eq!(report.check_status().errors, 10);
+        // the skipped errors are not counted by code either
+        assert_eq!(report.stats_by_code(), [("E1".to_string(), 10)]);
+    }
+
+    #[test]
+    fn broken_pipe()
+    {
+        use std::io;
+        use codespan_reporting::term::Config;
+        use codespan_reporting::term::termcolor::NoColor;
+        use super::ReportError;
+
+        struct Closed;
+        impl io::Write for Closed {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> { Err(io::ErrorKind::BrokenPipe.into()) }
+            fn flush(&mut self) -> io::Result<()> { Ok(()) }
+        }
+
+        let file = PreprocessedFile::new("int a;\n");
+        let report = EasyReporting::with_writer(&file, NoColor::new(Closed), Config::default());
+        let result = report.try_emit(Diagnostic::error().with_message("oops"));
+        assert!(matches!(result, Err(ReportError::Io(err)) if err.kind() == io::ErrorKind::BrokenPipe));
+        // the infallible emission goes on
+        report.emit(Diagnostic::error().with_message("oops"));
+        report.emit_status();
+    }
+}
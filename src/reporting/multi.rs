@@ -0,0 +1,155 @@
+use std::fmt::Display;
+use codespan_reporting::diagnostic::Severity;
+use super::{rank, Diagnostic, EasyReport, ReportError};
+
+/// An object-safe version of [`EasyReport`]
+/// (implemented by any reporting).
+///
+/// The codes of the diagnostics are given as strings.
+pub trait DynEasyReport
+{
+    fn emit_dyn(&self, diag: Diagnostic<String>);
+
+    fn try_emit_dyn(&self, diag: Diagnostic<String>) -> Result<(), ReportError>;
+}
+
+impl<R:EasyReport> DynEasyReport for R
+{
+    #[inline]
+    fn emit_dyn(&self, diag: Diagnostic<String>) { self.emit(diag) }
+
+    #[inline]
+    fn try_emit_dyn(&self, diag: Diagnostic<String>) -> Result<(), ReportError> { self.try_emit(diag) }
+}
+
+/// A set of severities.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SeverityFilter(u8);
+
+impl SeverityFilter {
+
+    /// Accepts all the severities.
+    #[inline]
+    pub fn all() -> Self { Self(0b11111) }
+
+    /// Accepts only the given severity.
+    #[inline]
+    pub fn only(severity: Severity) -> Self { Self(1 << rank(severity)) }
+
+    /// Accepts the given severity and the more severe ones.
+    #[inline]
+    pub fn at_least(severity: Severity) -> Self { Self(0b11111 & !((1 << rank(severity)) - 1)) }
+
+    /// Accepts the given severities.
+    #[inline]
+    pub fn any_of(severities: &[Severity]) -> Self
+    {
+        Self(severities.iter().fold(0, |set, severity| set | (1 << rank(*severity))))
+    }
+
+    #[inline]
+    pub fn contains(&self, severity: Severity) -> bool
+    {
+        self.0 & (1 << rank(severity)) != 0
+    }
+}
+
+impl Default for SeverityFilter {
+    #[inline]
+    fn default() -> Self { Self::all() }
+}
+
+/// A sink which forwards each diagnostic to the reportings
+/// which accept its severity.
+///
+/// # Example
+/// ```
+/// use codespan_reporting::diagnostic::Severity;
+/// use codespan_preprocessed::PreprocessedFile;
+/// use codespan_preprocessed::reporting::{Diagnostic, DiagnosticCollector, EasyReport, MultiReport, SeverityFilter};
+///
+/// let (errors, verbose) = (DiagnosticCollector::new(), DiagnosticCollector::new());
+/// let report = MultiReport::new()
+///     .with_report(&errors, SeverityFilter::at_least(Severity::Error))
+///     .with_report(&verbose, SeverityFilter::only(Severity::Note));
+/// report.emit(Diagnostic::error().with_message("oops"));
+/// report.emit(Diagnostic::note().with_message("by the way"));
+/// report.emit(Diagnostic::warning().with_message("dropped"));
+/// assert_eq!(errors.diagnostics()[0].message(), "oops");
+/// assert_eq!(verbose.diagnostics()[0].message(), "by the way");
+/// ```
+#[derive(Default)]
+pub struct MultiReport<'a> {
+    reports: Vec<(Box<dyn DynEasyReport + 'a>, SeverityFilter)>
+}
+
+impl<'a> MultiReport<'a> {
+
+    #[inline]
+    pub fn new() -> Self { Self::default() }
+
+    /// Adds a reporting of the diagnostics of the given severities.
+    #[inline]
+    pub fn with_report<R:EasyReport + 'a>(mut self, report: R, filter: SeverityFilter) -> Self
+    {
+        self.push(report, filter);
+        self
+    }
+
+    /// Adds a reporting of the diagnostics of the given severities.
+    #[inline]
+    pub fn push<R:EasyReport + 'a>(&mut self, report: R, filter: SeverityFilter)
+    {
+        self.reports.push((Box::new(report), filter));
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize { self.reports.len() }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool { self.reports.is_empty() }
+}
+
+impl EasyReport for MultiReport<'_>
+{
+    fn emit<E:Display>(&self, diag: impl Into<Diagnostic<E>>)
+    {
+        let diag = diag.into();
+        let code = diag.code.to_string();
+        let diag = diag.with_code(code);
+        self.reports.iter()
+            .filter(|(_, filter)| filter.contains(diag.severity))
+            .for_each(|(report, _)| report.emit_dyn(diag.clone()));
+    }
+
+    /// The diagnostic is forwarded to all the reportings,
+    /// the first error (if any) is returned.
+    fn try_emit<E:Display>(&self, diag: impl Into<Diagnostic<E>>) -> Result<(), ReportError>
+    {
+        let diag = diag.into();
+        let code = diag.code.to_string();
+        let diag = diag.with_code(code);
+        self.reports.iter()
+            .filter(|(_, filter)| filter.contains(diag.severity))
+            .map(|(report, _)| report.try_emit_dyn(diag.clone()))
+            .fold(Ok(()), Result::and)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use codespan_reporting::diagnostic::Severity;
+    use super::SeverityFilter;
+
+    #[test]
+    fn severity_filters()
+    {
+        let filter = SeverityFilter::at_least(Severity::Warning);
+        assert!(filter.contains(Severity::Bug) && filter.contains(Severity::Warning));
+        assert!(!filter.contains(Severity::Note) && !filter.contains(Severity::Help));
+        let filter = SeverityFilter::any_of(&[Severity::Help, Severity::Error]);
+        assert!(filter.contains(Severity::Help) && filter.contains(Severity::Error));
+        assert!(!filter.contains(Severity::Warning));
+        assert!(SeverityFilter::default().contains(Severity::Note));
+    }
+}
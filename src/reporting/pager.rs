@@ -0,0 +1,87 @@
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::process::{Command, Stdio};
+
+/// The pagination of the deferred diagnostics of an interactive run
+/// (see [`EasyReporting::with_pagination`](super::EasyReporting::with_pagination)).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Pagination {
+    /// Prompts `-- more -- (q to quit)` after each page of
+    /// the given number of diagnostics
+    Prompt(usize),
+    /// Pipes the diagnostics through `$PAGER` (`less -R` by default)
+    /// if there are more than the given number of them
+    Pager(usize)
+}
+
+impl Pagination {
+    /// Gets the number of diagnostics of a page.
+    #[inline]
+    pub(crate) fn page(self) -> usize
+    {
+        match self {
+            Pagination::Prompt(page) | Pagination::Pager(page) => page.max(1)
+        }
+    }
+}
+
+/// Checks if the run is interactive (the diagnostics written to the
+/// standard error are paginated only then).
+#[inline]
+pub(crate) fn is_interactive() -> bool
+{
+    io::stderr().is_terminal() && io::stdin().is_terminal()
+}
+
+/// Prompts for the next page: gets `false` if the user quits.
+///
+/// The end of the input shows all the remaining pages.
+pub(crate) fn prompt<W:Write,R:BufRead>(writer: &mut W, input: &mut R) -> io::Result<bool>
+{
+    write!(writer, "-- more -- (q to quit)")?;
+    writer.flush()?;
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+    Ok(!answer.trim().eq_ignore_ascii_case("q"))
+}
+
+/// Pipes a rendered text through the pager of the user, which writes
+/// to the standard error (as the diagnostics, even if the standard output
+/// is redirected).
+///
+/// An error is returned if the pager could not be started
+/// (but not if the user quits before the end).
+pub(crate) fn page(text: &[u8]) -> io::Result<()>
+{
+    let pager = std::env::var("PAGER").ok()
+        .filter(|pager| !pager.trim().is_empty())
+        .unwrap_or_else(|| "less -R".to_string());
+    let mut args = pager.split_whitespace();
+    let program = args.next().unwrap_or("less");
+    let mut child = Command::new(program).args(args)
+        .stdin(Stdio::piped())
+        .stdout(io::stderr())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        match stdin.write_all(text) {
+            Err(err) if err.kind() != io::ErrorKind::BrokenPipe => return Err(err),
+            _ => {}
+        }
+    }
+    child.wait()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::prompt;
+
+    #[test]
+    fn prompts()
+    {
+        let mut output = vec![];
+        assert!(prompt(&mut output, &mut "\n".as_bytes()).unwrap());
+        assert!(!prompt(&mut output, &mut " Q\n".as_bytes()).unwrap());
+        assert!(prompt(&mut output, &mut "".as_bytes()).unwrap());
+        assert_eq!(String::from_utf8(output).unwrap(), "-- more -- (q to quit)".repeat(3));
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+/// The documentation of a diagnostic code.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CodeInfo {
+    /// A short title (one line)
+    pub title: String,
+    /// The long explanation (in markdown)
+    pub explanation: String,
+    /// The URL of the online documentation
+    pub url: Option<String>
+}
+
+impl CodeInfo {
+
+    #[inline]
+    pub fn new(title: impl Into<String>, explanation: impl Into<String>) -> Self
+    {
+        Self { title: title.into(), explanation: explanation.into(), url: None }
+    }
+
+    #[inline]
+    pub fn with_url(mut self, url: impl Into<String>) -> Self
+    {
+        self.url = Some(url.into());
+        self
+    }
+}
+
+/// A registry of the diagnostic codes of a tool, with their explanations.
+///
+/// Once given to a reporting (see [`EasyReporting::with_registry`](super::EasyReporting::with_registry)),
+/// the diagnostics of a registered code end with a hint to run
+/// `tool --explain CODE`, whose output is given by [`CodeRegistry::explain`].
+///
+/// # Example
+/// ```
+/// use codespan_preprocessed::reporting::{CodeInfo, CodeRegistry};
+///
+/// let registry = CodeRegistry::new("mycc")
+///     .with_code("E001", CodeInfo::new("undeclared variable", "A variable is used before its declaration.")
+///         .with_url("https://example.org/E001"));
+/// assert_eq!(registry.hint("E001").unwrap(), "for more information, run `mycc --explain E001`");
+/// assert_eq!(registry.explain("E001").unwrap(),
+///            "# E001: undeclared variable\n\nA variable is used before its declaration.\n\nSee https://example.org/E001\n");
+/// assert!(registry.explain("E002").is_none());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CodeRegistry {
+    tool: String,
+    codes: HashMap<String, CodeInfo>
+}
+
+impl CodeRegistry {
+
+    /// Builds an empty registry for the given tool (as named in the hints).
+    #[inline]
+    pub fn new(tool: impl Into<String>) -> Self
+    {
+        Self { tool: tool.into(), codes: HashMap::new() }
+    }
+
+    /// Registers a code (overriding its previous documentation).
+    #[inline]
+    pub fn insert(&mut self, code: impl Display, info: CodeInfo)
+    {
+        self.codes.insert(code.to_string(), info);
+    }
+
+    /// Registers a code.
+    #[inline]
+    pub fn with_code(mut self, code: impl Display, info: CodeInfo) -> Self
+    {
+        self.insert(code, info);
+        self
+    }
+
+    /// Gets the documentation of a code (if registered).
+    #[inline]
+    pub fn get(&self, code: &str) -> Option<&CodeInfo>
+    {
+        self.codes.get(code)
+    }
+
+    /// Gets the registered codes (sorted).
+    pub fn codes(&self) -> Vec<&str>
+    {
+        let mut codes = self.codes.keys().map(String::as_str).collect::<Vec<_>>();
+        codes.sort_unstable();
+        codes
+    }
+
+    /// Gets the hint appended to the diagnostics of a registered code.
+    pub fn hint(&self, code: &str) -> Option<String>
+    {
+        self.codes.get(code)
+            .map(|_| format!("for more information, run `{} --explain {}`", self.tool, code))
+    }
+
+    /// Renders the explanation of a registered code (as markdown).
+    pub fn explain(&self, code: &str) -> Option<String>
+    {
+        self.codes.get(code).map(|info| {
+            let mut out = format!("# {}: {}\n\n{}\n", code, info.title, info.explanation.trim_end());
+            if let Some(url) = &info.url {
+                out.push_str(&format!("\nSee {}\n", url));
+            }
+            out
+        })
+    }
+}
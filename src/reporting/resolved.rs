@@ -0,0 +1,96 @@
+use std::time::SystemTime;
+use codespan_reporting::diagnostic::{LabelStyle, Severity};
+use codespan_reporting::files::{Files, Location};
+use crate::codemap::EasyLocation;
+use super::Diagnostic;
+
+/// A diagnostic whose labels are resolved to their file, line and column
+/// (as needed by the structured outputs).
+#[derive(Clone, Debug)]
+pub(crate) struct Resolved {
+    pub(crate) severity: Severity,
+    pub(crate) code: String,
+    pub(crate) message: String,
+    pub(crate) labels: Vec<ResolvedLabel>,
+    pub(crate) notes: Vec<String>,
+    pub(crate) suggestions: Vec<(ResolvedLabel, String)>,
+    /// The child diagnostics (resolved in the same way)
+    pub(crate) children: Vec<Resolved>,
+    /// The time of emission (if recorded by the sink)
+    pub(crate) timestamp: Option<SystemTime>
+}
+
+/// A label resolved in its original file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ResolvedLabel {
+    pub(crate) primary: bool,
+    pub(crate) file: String,
+    pub(crate) bytes: std::ops::Range<usize>,
+    pub(crate) start: Location,
+    pub(crate) end: Location,
+    pub(crate) message: String
+}
+
+impl Resolved {
+
+    /// Resolves the labels of a diagnostic with the given codemap
+    /// (the labels are split as for the rendering, see [`Diagnostic::to_diagnostic`]).
+    ///
+    /// The span of a suggestion is resolved in the file of its start
+    /// (with the message of the suggestion as label).
+    /// The child diagnostics are resolved recursively.
+    pub(crate) fn new<'a,L:EasyLocation<'a>,E:std::fmt::Display>(source: &'a L, mut diag: Diagnostic<E>) -> Self
+    {
+        let suggestions = std::mem::take(&mut diag.suggestions).into_iter()
+            .map(|suggestion| {
+                let id = source.file_id(suggestion.range().start);
+                let label = resolve(source, id, false, suggestion.range().clone(), suggestion.message().to_string());
+                (label, suggestion.replacement().to_string())
+            })
+            .collect();
+        let children = std::mem::take(&mut diag.children).into_iter()
+            .map(|child| Resolved::new(source, child))
+            .collect();
+        let diag = diag.to_diagnostic(source);
+        let labels = diag.labels.into_iter()
+            .map(|label| resolve(source, label.file_id, label.style == LabelStyle::Primary, label.range, label.message))
+            .collect();
+        Resolved {
+            severity: diag.severity,
+            code: diag.code.unwrap_or_default(),
+            message: diag.message,
+            labels,
+            notes: diag.notes,
+            suggestions,
+            children,
+            timestamp: None
+        }
+    }
+}
+
+/// Resolves a span located in the given file.
+pub(crate) fn resolve<'a,L:EasyLocation<'a>>(source: &'a L, id: <L as Files<'a>>::FileId, primary: bool,
+                                   bytes: std::ops::Range<usize>, message: String) -> ResolvedLabel
+{
+    let unknown = Location { line_number: 0, column_number: 0 };
+    ResolvedLabel {
+        primary,
+        file: source.name(id).map_or_else(|_| String::new(), |name| name.to_string()),
+        start: source.location(id, bytes.start).unwrap_or(unknown),
+        end: source.location(id, bytes.end).unwrap_or(unknown),
+        bytes,
+        message
+    }
+}
+
+/// Gets the usual name of a severity level.
+pub(crate) fn level(severity: Severity) -> &'static str
+{
+    match severity {
+        Severity::Bug => "bug",
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Note => "note",
+        Severity::Help => "help"
+    }
+}
@@ -0,0 +1,234 @@
+use std::fmt::{Display, Write as _};
+use std::io;
+use std::sync::{Mutex, PoisonError};
+use codespan_reporting::diagnostic::Severity;
+use crate::codemap::EasyLocation;
+use crate::json;
+use super::{Diagnostic, EasyReport};
+use super::resolved::{Resolved, ResolvedLabel};
+use super::timestamp::{now, rfc3339};
+
+/// A reporting of the diagnostics as a SARIF 2.1.0 log.
+///
+/// The diagnostics are accumulated and the log is written at once
+/// (see [`SarifReport::write`]). The locations of the results are
+/// those of the original files (as given by the codemap): the primary
+/// labels are the `locations` and the secondary ones the `relatedLocations`.
+/// The child diagnostics are appended to the message of their result and
+/// their labels are related locations too (with the message of the child
+/// when they are not labelled).
+/// ```
+/// use codespan_preprocessed::PreprocessedFile;
+/// use codespan_preprocessed::reporting::{Diagnostic, EasyReport, SarifReport};
+///
+/// let file = PreprocessedFile::new("#line 12 \"src/a.c\"\nint a;\n");
+/// let report = SarifReport::new(&file, "mytool", "1.0");
+/// report.emit(Diagnostic::warning().with_code("W01").with_message("unused").with_primary_label(23..24, "a"));
+/// let log = report.to_sarif();
+/// assert!(log.contains(r#""region":{"startLine":12,"startColumn":5,"endLine":12,"endColumn":6}"#));
+/// ```
+pub struct SarifReport<'a,L:EasyLocation<'a>>
+{
+    source: &'a L,
+    tool: (String, String),
+    results: Mutex<Vec<Resolved>>,
+    timestamps: bool
+}
+
+impl<'a,L:EasyLocation<'a>> SarifReport<'a,L>
+{
+    /// Builds a reporting for the given tool (its name and version).
+    pub fn new(source: &'a L, name: impl Into<String>, version: impl Into<String>) -> Self
+    {
+        Self { source, tool: (name.into(), version.into()), results: Mutex::default(), timestamps: false }
+    }
+
+    /// Records the time of emission of each result (as an RFC 3339
+    /// `timestamp` in UTC, in the properties of the result), except on
+    /// `wasm32-unknown-unknown` (without clock).
+    #[inline]
+    pub fn with_timestamps(mut self, enabled: bool) -> Self
+    {
+        self.timestamps = enabled;
+        self
+    }
+
+    /// Gets the SARIF log of the diagnostics emitted so far.
+    pub fn to_sarif(&self) -> String
+    {
+        let results = self.results.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut out = String::from(r#"{"version":"2.1.0","$schema":"https://json.schemastore.org/sarif-2.1.0.json","runs":[{"tool":{"driver":{"name":"#);
+        json::write_string(&mut out, &self.tool.0);
+        out.push_str(r#","version":"#);
+        json::write_string(&mut out, &self.tool.1);
+        out.push_str(r#","rules":["#);
+        let mut rules: Vec<&str> = results.iter().map(|r| r.code.as_str()).filter(|code| !code.is_empty()).collect();
+        rules.sort_unstable();
+        rules.dedup();
+        for (i, rule) in rules.iter().enumerate() {
+            if i > 0 { out.push(','); }
+            out.push_str(r#"{"id":"#);
+            json::write_string(&mut out, rule);
+            out.push('}');
+        }
+        out.push_str(r#"]}},"columnKind":"unicodeCodePoints","results":["#);
+        for (i, result) in results.iter().enumerate() {
+            if i > 0 { out.push(','); }
+            write_result(&mut out, result);
+        }
+        out.push_str("]}]}");
+        out
+    }
+
+    /// Writes the SARIF log of the diagnostics emitted so far.
+    pub fn write<W:io::Write>(&self, mut writer: W) -> io::Result<()>
+    {
+        writer.write_all(self.to_sarif().as_bytes())
+    }
+}
+
+impl<'a,L:EasyLocation<'a>> EasyReport for SarifReport<'a,L>
+{
+    fn emit<E: Display>(&self, diag: impl Into<Diagnostic<E>>)
+    {
+        let mut result = Resolved::new(self.source, diag.into());
+        result.timestamp = self.timestamps.then(now).flatten();
+        self.results.lock().unwrap_or_else(PoisonError::into_inner).push(result);
+    }
+}
+
+fn write_result(out: &mut String, result: &Resolved)
+{
+    let level = match result.severity {
+        Severity::Bug | Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Note | Severity::Help => "note"
+    };
+    out.push('{');
+    if !result.code.is_empty() {
+        out.push_str(r#""ruleId":"#);
+        json::write_string(out, &result.code);
+        out.push(',');
+    }
+    let _ = write!(out, r#""level":"{}","message":{{"text":"#, level);
+    let mut text = result.message.clone();
+    result.notes.iter().for_each(|note| { text.push('\n'); text.push_str(note); });
+    let mut related = result.labels.iter().filter(|label| !label.primary).cloned().collect();
+    result.children.iter().for_each(|child| flatten(child, &mut text, &mut related));
+    json::write_string(out, &text);
+    out.push_str(r#"},"locations":["#);
+    write_locations(out, result.labels.iter().filter(|label| label.primary));
+    out.push_str(r#"],"relatedLocations":["#);
+    write_locations(out, related.iter());
+    out.push(']');
+    if !result.suggestions.is_empty() {
+        out.push_str(r#","fixes":["#);
+        for (i, (span, replacement)) in result.suggestions.iter().enumerate() {
+            if i > 0 { out.push(','); }
+            out.push_str(r#"{"description":{"text":"#);
+            json::write_string(out, &span.message);
+            out.push_str(r#"},"artifactChanges":[{"artifactLocation":{"uri":"#);
+            json::write_string(out, &span.file.replace('\\', "/"));
+            let _ = write!(out, r#"}},"replacements":[{{"deletedRegion":{{"startLine":{},"startColumn":{},"endLine":{},"endColumn":{}}},"insertedContent":{{"text":"#,
+                           span.start.line_number, span.start.column_number,
+                           span.end.line_number, span.end.column_number);
+            json::write_string(out, replacement);
+            out.push_str("}}]}]}");
+        }
+        out.push(']');
+    }
+    if let Some(time) = result.timestamp {
+        let _ = write!(out, r#","properties":{{"timestamp":"{}"}}"#, rfc3339(time));
+    }
+    out.push('}');
+}
+
+/// Appends a child diagnostic (and its own children) to the message
+/// and to the related locations of its result.
+fn flatten(child: &Resolved, text: &mut String, related: &mut Vec<ResolvedLabel>)
+{
+    let _ = write!(text, "\n{}: {}", super::resolved::level(child.severity), child.message);
+    child.notes.iter().for_each(|note| { text.push('\n'); text.push_str(note); });
+    related.extend(child.labels.iter().cloned().map(|mut label| {
+        if label.message.is_empty() {
+            label.message.clone_from(&child.message);
+        }
+        label
+    }));
+    child.children.iter().for_each(|child| flatten(child, text, related));
+}
+
+fn write_locations<'l>(out: &mut String, labels: impl Iterator<Item=&'l ResolvedLabel>)
+{
+    for (i, label) in labels.enumerate() {
+        if i > 0 { out.push(','); }
+        out.push_str(r#"{"physicalLocation":{"artifactLocation":{"uri":"#);
+        json::write_string(out, &label.file.replace('\\', "/"));
+        let _ = write!(out, r#"}},"region":{{"startLine":{},"startColumn":{},"endLine":{},"endColumn":{}}}}}"#,
+                       label.start.line_number, label.start.column_number,
+                       label.end.line_number, label.end.column_number);
+        if !label.message.is_empty() {
+            out.push_str(r#","message":{"text":"#);
+            json::write_string(out, &label.message);
+            out.push('}');
+        }
+        out.push('}');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json::{self, Value};
+    use crate::PreprocessedFile;
+    use crate::reporting::{Diagnostic, EasyReport};
+    use super::SarifReport;
+
+    #[test]
+    fn sarif_log()
+    {
+        let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a;\n#line 3 \"b.h\"\nint b;\n");
+        let report = SarifReport::new(&file, "tool", "0.1");
+        report.emit(Diagnostic::error().with_code("E1").with_message("m")
+            .with_primary_label(37..40, "p").with_secondary_label(14..17, "s"));
+        report.emit(Diagnostic::note().with_message("n").with_note("more").with_suggestion(14..17, "long", "widen"));
+        let log = json::parse(&report.to_sarif()).unwrap();
+        let run = &log.get("runs").and_then(Value::as_array).unwrap()[0];
+        let results = run.get("results").and_then(Value::as_array).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].get("ruleId").and_then(Value::as_str), Some("E1"));
+        let location = |field: &str| results[0].get(field).and_then(Value::as_array).unwrap()[0]
+            .get("physicalLocation").unwrap().clone();
+        let primary = location("locations");
+        assert_eq!(primary.get("artifactLocation").and_then(|a| a.get("uri")).and_then(Value::as_str), Some("b.h"));
+        assert_eq!(primary.get("region").and_then(|r| r.get("startLine")), Some(&Value::Number(3.)));
+        let related = location("relatedLocations");
+        assert_eq!(related.get("artifactLocation").and_then(|a| a.get("uri")).and_then(Value::as_str), Some("a.c"));
+        assert_eq!(results[1].get("message").and_then(|m| m.get("text")).and_then(Value::as_str), Some("n\nmore"));
+        let fix = &results[1].get("fixes").and_then(Value::as_array).unwrap()[0];
+        let change = &fix.get("artifactChanges").and_then(Value::as_array).unwrap()[0];
+        let replacement = &change.get("replacements").and_then(Value::as_array).unwrap()[0];
+        assert_eq!(replacement.get("insertedContent").and_then(|c| c.get("text")).and_then(Value::as_str), Some("long"));
+    }
+
+    #[test]
+    fn sarif_children()
+    {
+        let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a;\n#line 3 \"b.h\"\nint a;\n");
+        let report = SarifReport::new(&file, "tool", "0.1");
+        report.emit(Diagnostic::error().with_message("redefinition").with_primary_label(37..42, "")
+            .with_child(Diagnostic::note().with_message("previous definition").with_primary_label(14..19, "")
+                .with_child(Diagnostic::help().with_message("rename it").with_primary_label(18..19, "here"))));
+        let log = json::parse(&report.to_sarif()).unwrap();
+        let run = &log.get("runs").and_then(Value::as_array).unwrap()[0];
+        let result = &run.get("results").and_then(Value::as_array).unwrap()[0];
+        let text = result.get("message").and_then(|m| m.get("text")).and_then(Value::as_str);
+        assert_eq!(text, Some("redefinition\nnote: previous definition\nhelp: rename it"));
+        let related = result.get("relatedLocations").and_then(Value::as_array).unwrap();
+        assert_eq!(related.len(), 2);
+        let message = |i: usize| related[i].get("message").and_then(|m| m.get("text")).and_then(Value::as_str);
+        assert_eq!(message(0), Some("previous definition"));
+        assert_eq!(message(1), Some("here"));
+        let region = related[0].get("physicalLocation").and_then(|l| l.get("region")).unwrap();
+        assert_eq!(region.get("startLine"), Some(&Value::Number(1.)));
+    }
+}
@@ -0,0 +1,129 @@
+use std::io::{self, Write};
+use codespan_reporting::term::termcolor::{Ansi, ColorSpec, WriteColor};
+
+/// A writer passing the rendered text to a callback, line by line
+/// (as to report in a browser, where there is no terminal: it builds
+/// for `wasm32-unknown-unknown` without any feature).
+///
+/// The text is passed by whole lines (ended by `\n`), the last
+/// incomplete one being passed at the flush.
+///
+/// # Example
+/// ```
+/// use std::sync::{Arc, Mutex};
+/// use codespan_reporting::term::Config;
+/// use codespan_preprocessed::PreprocessedFile;
+/// use codespan_preprocessed::reporting::{CallbackWriter, Diagnostic, EasyReport, EasyReporting};
+///
+/// let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a;\n");
+/// let lines = Arc::new(Mutex::new(vec![]));
+/// let output = lines.clone();
+/// let report = EasyReporting::with_callback(&file, Config::default(), move |line: &str| {
+///     output.lock().unwrap().push(line.to_string())
+/// });
+/// report.emit(Diagnostic::warning().with_message("unused").with_primary_label(18..19, ""));
+/// assert_eq!(lines.lock().unwrap()[..2], ["warning: unused\n", "  ┌─ a.c:1:5\n"]);
+/// ```
+pub struct CallbackWriter<F:FnMut(&str)>
+{
+    lines: Lines<F>,
+    ansi: bool
+}
+
+impl<F:FnMut(&str)> CallbackWriter<F>
+{
+    /// Builds an uncolored writer.
+    #[inline]
+    pub fn new(callback: F) -> Self
+    {
+        Self { lines: Lines { callback, pending: vec![] }, ansi: false }
+    }
+
+    /// Builds a writer colored by ANSI escape codes
+    /// (as for a terminal emulated in a browser).
+    #[inline]
+    pub fn ansi(callback: F) -> Self
+    {
+        Self { lines: Lines { callback, pending: vec![] }, ansi: true }
+    }
+}
+
+impl<F:FnMut(&str)> Write for CallbackWriter<F>
+{
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> { self.lines.write(buf) }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> { self.lines.flush() }
+}
+
+impl<F:FnMut(&str)> WriteColor for CallbackWriter<F>
+{
+    #[inline]
+    fn supports_color(&self) -> bool { self.ansi }
+
+    fn set_color(&mut self, spec: &ColorSpec) -> io::Result<()>
+    {
+        if self.ansi { Ansi::new(&mut self.lines).set_color(spec) } else { Ok(()) }
+    }
+
+    fn reset(&mut self) -> io::Result<()>
+    {
+        if self.ansi { Ansi::new(&mut self.lines).reset() } else { Ok(()) }
+    }
+}
+
+impl<F:FnMut(&str)> Drop for CallbackWriter<F>
+{
+    fn drop(&mut self) { let _ = self.lines.flush(); }
+}
+
+/// The splitting of the written bytes into lines.
+struct Lines<F:FnMut(&str)>
+{
+    callback: F,
+    pending: Vec<u8>
+}
+
+impl<F:FnMut(&str)> Write for Lines<F>
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize>
+    {
+        self.pending.extend_from_slice(buf);
+        if let Some(end) = memchr::memrchr(b'\n', &self.pending) {
+            let rest = self.pending.split_off(end + 1);
+            let text = std::mem::replace(&mut self.pending, rest);
+            String::from_utf8_lossy(&text).split_inclusive('\n').for_each(&mut self.callback);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()>
+    {
+        if !self.pending.is_empty() {
+            let text = std::mem::take(&mut self.pending);
+            (self.callback)(&String::from_utf8_lossy(&text));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use codespan_reporting::term::termcolor::{Color, ColorSpec, WriteColor};
+    use super::CallbackWriter;
+
+    #[test]
+    fn callback_lines()
+    {
+        let mut lines = vec![];
+        let mut writer = CallbackWriter::ansi(|line: &str| lines.push(line.to_string()));
+        writer.write_all("a\nb".as_bytes()).unwrap();
+        writer.set_color(ColorSpec::new().set_fg(Some(Color::Red))).unwrap();
+        writer.write_all("é\nc\n\nd".as_bytes()).unwrap();
+        writer.reset().unwrap();
+        drop(writer);
+        assert_eq!(lines, ["a\n", "b\x1B[0m\x1B[31mé\n", "c\n", "\n", "d\x1B[0m"]);
+    }
+}
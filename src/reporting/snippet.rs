@@ -0,0 +1,510 @@
+use std::io::Write as _;
+use codespan_reporting::diagnostic;
+use codespan_reporting::files::{Error, Files};
+use codespan_reporting::term;
+use codespan_reporting::term::Config;
+use codespan_reporting::term::termcolor::{Buffer, WriteColor};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+use crate::codemap::EasyLocation;
+
+/// A row of a diagnostic rendered by codespan.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Row {
+    /// The locus starting the snippet of a file
+    Locus,
+    /// A source line, with its number
+    Source(usize),
+    /// The carets (and the messages) of the labels under a source line
+    Caret,
+    /// A skipped part of the source
+    Break,
+    /// Anything else (header, empty border, notes...)
+    Other
+}
+
+/// A row of the rendering completed with context.
+enum Out<'r,'s,Id> {
+    /// A rendered row, marked if it is indented by the outer gutter
+    Row(bool, &'r str),
+    /// A line of context of a snippet
+    Context(&'s Snippet<Id>, usize)
+}
+
+/// The snippet of a file, as rendered by codespan.
+struct Snippet<Id> {
+    id: Id,
+    /// The line numbers covered by the file
+    lines: (usize, usize),
+    /// The number of multi-line labels (one column each)
+    multi_labels: usize
+}
+
+/// Renders a diagnostic with codespan, then completes its snippets:
+/// with the given number of lines of context before and after each
+/// labeled line, and with the wide lines elided around their labels.
+pub(crate) fn emit<'a,L:EasyLocation<'a>>(buffer: &mut Buffer, config: &Config, source: &'a L,
+                                          diag: &diagnostic::Diagnostic<<L as Files<'a>>::FileId>,
+                                          context: usize, width: Option<usize>) -> Result<(), Error>
+{
+    let colored = buffer.supports_color();
+    let mut rendered = if colored { Buffer::ansi() } else { Buffer::no_color() };
+    term::emit(&mut rendered, config, source, diag)?;
+    let snippets = snippets(source, diag)?;
+    let mut rows = String::from_utf8_lossy(rendered.as_slice())
+        .split_inclusive('\n')
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    if context > 0 {
+        rows = with_context(config, source, &snippets, rows, context, colored)?;
+    }
+    if let Some(width) = width {
+        rows = elide(config, &snippets, rows, width);
+    }
+    buffer.write_all(rows.concat().as_bytes())?;
+    Ok(())
+}
+
+/// Adds the lines of context to the rows of a rendered diagnostic.
+///
+/// codespan only shows the labeled lines, so its rendering is completed
+/// with the lines of context (the breaks between the lines are merged
+/// as soon as they are contiguous). Within the snippets of multi-line
+/// labels, the context is only shown at the edges.
+fn with_context<'a,L:EasyLocation<'a>>(config: &Config, source: &'a L,
+                                       snippets: &[Snippet<<L as Files<'a>>::FileId>],
+                                       rendered: Vec<String>, context: usize, colored: bool) -> Result<Vec<String>, Error>
+{
+    let rows = classify(config, &rendered);
+    // the rendering is kept as is if it is not understood
+    let padding = rendered.iter().zip(&rows)
+        .find(|(_, row)| matches!(row, Row::Source(_)))
+        .map(|(line, _)| {
+            let line = strip_ansi(line);
+            line.len() - line.trim_start().len() + line.trim_start().find(' ').unwrap_or(0)
+        });
+    let (Some(padding), true) = (padding, rows.iter().filter(|row| **row == Row::Locus).count() == snippets.len()) else {
+        return Ok(rendered);
+    };
+
+    // the lines of context are rendered once the outer gutter is known
+    let mut out = vec![];
+    let mut snippets = snippets.iter();
+    let mut snippet = None;
+    let mut current = None; // the source line whose carets are rendered
+    let mut shown = None; // the last line shown in the current snippet
+    let mut skipped = None; // the last break not rendered yet
+    let mut in_snippets = false;
+    for (i, (row, line)) in rows.iter().zip(&rendered).enumerate() {
+        match *row {
+            Row::Locus => {
+                snippet = snippets.next();
+                (shown, skipped, in_snippets) = (None, None, true);
+            }
+            // the breaks are rendered again if the context does not fill them
+            Row::Break if snippet.is_some_and(|snippet| snippet.multi_labels == 0) => {
+                skipped = Some(line.as_str());
+                continue;
+            }
+            Row::Source(number) => if let Some(snippet) = snippet {
+                let first = number.saturating_sub(context).max(snippet.lines.0);
+                let before = match shown {
+                    None => first..number,
+                    Some(_) if snippet.multi_labels > 0 => number..number,
+                    Some(last) if first > last + 2 => {
+                        out.extend(skipped.take().map(|line| Out::Row(true, line)));
+                        first..number
+                    }
+                    Some(last) => (last + 1)..number
+                };
+                out.extend(before.map(|n| Out::Context(snippet, n)));
+                (current, skipped) = (Some(number), None);
+            }
+            _ => {}
+        }
+        out.push(Out::Row(in_snippets && line != "\n", line));
+        // the context after a source line, once its carets are rendered
+        if let (Some(snippet), Some(number)) = (snippet, current) {
+            if rows.get(i + 1) != Some(&Row::Caret) {
+                let next = rows[i+1..].iter()
+                    .take_while(|row| **row != Row::Locus)
+                    .find_map(|row| match row { Row::Source(n) => Some(*n), _ => None });
+                let last = match next {
+                    Some(_) if snippet.multi_labels > 0 => number,
+                    Some(next) => (number + context).min(next - 1),
+                    None => (number + context).min(snippet.lines.1).max(number)
+                };
+                out.extend(((number + 1)..=last).map(|n| Out::Context(snippet, n)));
+                (current, shown) = (None, Some(last));
+            }
+        }
+    }
+
+    // the outer gutter is widened for the numbers of the context lines
+    let widest = out.iter()
+        .filter_map(|out| match out { Out::Context(_, n) => Some(count_digits(*n)), _ => None })
+        .max().unwrap_or(0);
+    let widen = widest.saturating_sub(padding);
+    out.into_iter()
+        .map(|out| match out {
+            Out::Row(true, line) => Ok(format!("{:1$}{2}", "", widen, line)),
+            Out::Row(false, line) => Ok(line.to_string()),
+            Out::Context(snippet, n) => context_row(config, source, snippet, n, padding.max(widest), colored)
+        })
+        .collect()
+}
+
+/// Elides the source lines wider than the given number of columns
+/// (and the carets under them), around their labels.
+fn elide<Id>(config: &Config, snippets: &[Snippet<Id>], mut rendered: Vec<String>, width: usize) -> Vec<String>
+{
+    let rows = classify(config, &rendered);
+    let mut snippets = snippets.iter();
+    let mut multi_labels = 0;
+    let mut i = 0;
+    while i < rows.len() {
+        match rows[i] {
+            Row::Locus => multi_labels = snippets.next().map_or(0, |snippet| snippet.multi_labels),
+            Row::Source(_) => {
+                let line = strip_ansi(&rendered[i]);
+                let line = line.trim_end_matches('\n');
+                // the source text starts after the border, the inner gutter and a space
+                let border = line.find(config.chars.source_border_left).unwrap_or(0);
+                let start = line[..border].width() + 1 + 2*multi_labels + 1;
+                let carets = rows[i+1..].iter().take_while(|row| **row == Row::Caret).count();
+                let total = line.width().saturating_sub(start);
+                if total > width {
+                    // the window is centered on the labels of the line
+                    let chars = &config.chars;
+                    let marks = [chars.single_primary_caret, chars.single_secondary_caret,
+                                 chars.multi_primary_caret_start, chars.multi_primary_caret_end,
+                                 chars.multi_secondary_caret_start, chars.multi_secondary_caret_end];
+                    let (left, right) = rendered[i+1..=i+carets].iter()
+                        .map(|caret| strip_ansi(caret))
+                        .filter_map(|caret| {
+                            let left = caret.find(marks)?;
+                            let right = caret.rfind(marks)?;
+                            Some((caret[..left].width(), caret[..right].width() + 1))
+                        })
+                        .reduce(|(l, r), (left, right)| (l.min(left), r.max(right)))
+                        .unwrap_or((start, start));
+                    let (left, right) = (left.saturating_sub(start), right.saturating_sub(start).min(total));
+                    let window = width.saturating_sub(2).max(1);
+                    let mut lo = left.saturating_sub(window.saturating_sub(right.saturating_sub(left)) / 2);
+                    if lo + window >= total {
+                        lo = total.saturating_sub(width.saturating_sub(1).max(1));
+                    }
+                    let hi = if lo == 0 { width.saturating_sub(1).max(1) } else { (lo + window).min(total) };
+                    rendered[i] = cut(&rendered[i], start, lo, Some(hi));
+                    for caret in &mut rendered[i+1..=i+carets] {
+                        *caret = cut(caret, start, lo, None);
+                    }
+                }
+                i += carets;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    rendered
+}
+
+/// Cuts the columns of a row (keeping its ANSI escape sequences) to the
+/// window `lo..hi` of its text starting at the given column, replacing
+/// the cut parts with `…` (or with their last column under the source line).
+///
+/// A wide character across an edge of the window is cut too: on the left,
+/// its marker is padded to keep the carets aligned.
+fn cut(row: &str, start: usize, lo: usize, hi: Option<usize>) -> String
+{
+    let mut out = String::with_capacity(row.len());
+    let mut column = 0usize;
+    let mut ended = false;
+    let mut chars = row.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1B' {
+            // an escape sequence (as `ESC[1;31m`) is kept as is
+            out.push(c);
+            for c in chars.by_ref() {
+                out.push(c);
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+            continue;
+        }
+        let width = c.width().unwrap_or(0);
+        let text = column.checked_sub(start);
+        column += width;
+        match text {
+            // the cut part of a source line is marked, the one of carets is kept
+            // as thin as possible (only the last column is kept)
+            Some(text) if c != '\n' && text < lo => if text + width >= lo {
+                match hi {
+                    Some(_) => out.extend(std::iter::once('…').chain(std::iter::repeat_n(' ', text + width - lo))),
+                    None => out.push(c)
+                }
+            }
+            Some(text) if c != '\n' && hi.is_some_and(|hi| text + width.max(1) > hi) => if !ended {
+                out.push('…');
+                ended = true;
+            }
+            _ => out.push(c)
+        }
+    }
+    out
+}
+
+/// Removes the ANSI escape sequences of a rendered row.
+fn strip_ansi(row: &str) -> String
+{
+    let mut out = String::with_capacity(row.len());
+    let mut chars = row.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1B' {
+            chars.by_ref().find(char::is_ascii_alphabetic);
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Gets the snippets of the files, in order of rendering (the one of
+/// their first label).
+fn snippets<'a,L:EasyLocation<'a>>(source: &'a L, diag: &diagnostic::Diagnostic<<L as Files<'a>>::FileId>)
+    -> Result<Vec<Snippet<<L as Files<'a>>::FileId>>, Error>
+{
+    let mut snippets: Vec<Snippet<_>> = vec![];
+    for label in &diag.labels {
+        let multi = source.line_index(label.file_id, label.range.start)? != source.line_index(label.file_id, label.range.end)?;
+        match snippets.iter_mut().find(|snippet| snippet.id == label.file_id) {
+            Some(snippet) => snippet.multi_labels += usize::from(multi),
+            None => {
+                let bytes = source.file_bytes(label.file_id);
+                let first = source.line_number(label.file_id, source.line_index(label.file_id, bytes.start)?)?;
+                let last = source.line_number(label.file_id, source.line_index(label.file_id, bytes.end)?)?;
+                snippets.push(Snippet { id: label.file_id, lines: (first, last), multi_labels: usize::from(multi) });
+            }
+        }
+    }
+    Ok(snippets)
+}
+
+/// Classifies the rows of a rendered diagnostic.
+fn classify(config: &Config, rows: &[String]) -> Vec<Row>
+{
+    let border = config.chars.source_border_left;
+    let mut in_snippets = false;
+    rows.iter()
+        .map(|row| {
+            let row = strip_ansi(row);
+            let row = row.trim_end_matches('\n').trim_start();
+            let digits = row.len() - row.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            if row.starts_with(config.chars.snippet_start.as_str()) {
+                in_snippets = true;
+                Row::Locus
+            } else if !in_snippets {
+                Row::Other
+            } else if row.starts_with(config.chars.note_bullet) {
+                in_snippets = false;
+                Row::Other
+            } else if digits > 0 && row[digits..].starts_with(' ') && row[digits+1..].starts_with(border) {
+                Row::Source(row[..digits].parse().unwrap_or_default())
+            } else if row.starts_with(config.chars.source_border_left_break) {
+                Row::Break
+            } else if row.starts_with(border) && !row[border.len_utf8()..].trim().is_empty() {
+                Row::Caret
+            } else {
+                Row::Other
+            }
+        })
+        .collect()
+}
+
+/// Renders a line of context (as codespan renders a source line without label).
+fn context_row<'a,L:EasyLocation<'a>>(config: &Config, source: &'a L,
+                                      snippet: &Snippet<<L as Files<'a>>::FileId>,
+                                      number: usize, padding: usize, colored: bool) -> Result<String, Error>
+{
+    let text = source.source(snippet.id)?;
+    let text = &text.as_ref()[source.line_range(snippet.id, number - 1)?];
+    let text = text.trim_end_matches(['\n', '\r', '\0']);
+    let mut row = if colored { Buffer::ansi() } else { Buffer::no_color() };
+    row.set_color(&config.styles.line_number)?;
+    write!(row, "{:>1$}", number, padding)?;
+    row.reset()?;
+    write!(row, " ")?;
+    row.set_color(&config.styles.source_border)?;
+    write!(row, "{}", config.chars.source_border_left)?;
+    row.reset()?;
+    write!(row, "{} ", "  ".repeat(snippet.multi_labels))?;
+    // the tabs are expanded as codespan does
+    let mut column = 0;
+    for c in text.chars() {
+        match (c, config.tab_width) {
+            ('\t', 0) => {}
+            ('\t', width) => {
+                let spaces = width - column % width;
+                write!(row, "{:1$}", "", spaces)?;
+                column += spaces;
+            }
+            (c, _) => {
+                write!(row, "{}", c)?;
+                column += c.width().unwrap_or(0);
+            }
+        }
+    }
+    writeln!(row)?;
+    Ok(String::from_utf8_lossy(row.as_slice()).into_owned())
+}
+
+#[inline]
+fn count_digits(n: usize) -> usize
+{
+    n.checked_ilog10().map_or(1, |digits| digits as usize + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::PreprocessedFile;
+    use crate::reporting::{Diagnostic, EasyReport, EasyReporting};
+    use super::cut;
+
+    #[test]
+    fn context_lines()
+    {
+        let file = PreprocessedFile::new("#line 7 \"a.c\"\nl7\nl8\nl9\nl10\nl11\nl12\nl13\nl14\n");
+        let report = EasyReporting::to_buffer(&file).with_context_lines(1);
+        report.emit(Diagnostic::error().with_message("oops")
+            .with_primary_label(17..19, "here")
+            .with_secondary_label(35..38, "there")
+            .with_note("a note"));
+        assert_eq!(report.rendered(), "\
+error: oops
+   ┌─ a.c:8:1
+   │
+ 7 │ l7
+ 8 │ l8
+   │ ^^ here
+ 9 │ l9
+   ·
+12 │ l12
+13 │ l13
+   │ --- there
+14 │ l14
+   │
+   = a note
+
+");
+    }
+
+    #[test]
+    fn elided_rows()
+    {
+        let row = "1 │ ab\x1B[1mcdef\x1B[0mgh\n";
+        assert_eq!(cut(row, 4, 2, Some(5)), "1 │ …\x1B[1mcde…\x1B[0m\n");
+        assert_eq!(cut("  │ ──────^\n", 4, 3, None), "  │ ────^\n");
+    }
+
+    #[test]
+    fn custom_chars_context()
+    {
+        use codespan_reporting::term::Config;
+        use codespan_reporting::term::termcolor::Buffer;
+
+        let file = PreprocessedFile::new("#line 7 \"a.c\"\nl7\nl8\nl9\nl10\nl11\nl12\nl13\nl14\n");
+        let mut config = Config::default();
+        config.chars.snippet_start = "==>".to_string();
+        config.chars.source_border_left = '!';
+        config.chars.source_border_left_break = '~';
+        config.chars.note_bullet = '*';
+        let report = EasyReporting::with_writer(&file, Buffer::no_color(), config).with_context_lines(1);
+        report.emit(Diagnostic::error().with_message("oops")
+            .with_primary_label(17..19, "here")
+            .with_secondary_label(35..38, "there")
+            .with_note("a note"));
+        assert_eq!(report.rendered(), "\
+error: oops
+   ==> a.c:8:1
+   !
+ 7 ! l7
+ 8 ! l8
+   ! ^^ here
+ 9 ! l9
+   ~
+12 ! l12
+13 ! l13
+   ! --- there
+14 ! l14
+   !
+   * a note
+
+");
+    }
+
+    #[test]
+    fn multi_file_context()
+    {
+        let file = PreprocessedFile::new("#line 1 \"a.c\"\na1\na2\na3\n#line 1 \"b.h\"\nb1\nb2\nb3\nb4\n");
+        let report = EasyReporting::to_buffer(&file).with_context_lines(1);
+        report.emit(Diagnostic::error().with_message("oops")
+            .with_primary_label(43..45, "here")
+            .with_secondary_label(17..19, "there"));
+        // each snippet gets the context of its own file
+        assert_eq!(report.rendered(), "\
+error: oops
+  ┌─ b.h:3:1
+  │
+2 │ b2
+3 │ b3
+  │ ^^ here
+4 │ b4
+  │
+  ┌─ a.c:2:1
+  │
+1 │ a1
+2 │ a2
+  │ -- there
+3 │ a3
+
+");
+    }
+
+    #[test]
+    fn elided_multi_line_label()
+    {
+        let text = "#line 1 \"a.c\"\nint a = 0 + 1 + 2 + 3 + 4 + x + 6 + 7 + 8 + 9 + (\n  10 + 11 + 12 + 13 + 14 + y + 16 + 17 + 18);\n";
+        let file = PreprocessedFile::new(text);
+        let report = EasyReporting::to_buffer(&file).with_max_snippet_width(16);
+        report.emit(Diagnostic::error().with_message("oops")
+            .with_primary_label(text.find('x').unwrap()..text.find('y').unwrap() + 1, "here"));
+        // the lines are elided after the gutter of the multi-line label
+        assert_eq!(report.rendered(), "\
+error: oops
+  ┌─ a.c:1:29
+  │  
+1 │   …+ 4 + x + 6 + …
+  │ ╭────────^
+2 │ │ … 14 + y + 16 +…
+  │ ╰────────^ here
+
+");
+    }
+
+    #[test]
+    fn elided_wide_chars()
+    {
+        let text = "#line 1 \"a.c\"\nlet 変数 = 値一 + 値二 + 値三 + 未定義 + 値四 + 値五 + 値六;\n";
+        let file = PreprocessedFile::new(text);
+        let start = text.find("未定義").unwrap();
+        // the window starts in the middle of `三` (replaced by `…` and a space)
+        // and ends in the middle of `四`
+        let report = EasyReporting::to_buffer(&file).with_max_snippet_width(16);
+        report.emit(Diagnostic::error().with_message("oops").with_primary_label(start..start + 9, "ここ"));
+        assert!(report.rendered().contains("\n1 │ …  + 未定義 + …\n  │      ^^^^^^ ここ\n"));
+        // the window starts right after `三`
+        let report = EasyReporting::to_buffer(&file).with_max_snippet_width(15);
+        report.emit(Diagnostic::error().with_message("oops").with_primary_label(start..start + 9, "ここ"));
+        assert!(report.rendered().contains("\n1 │ … + 未定義 + …\n  │     ^^^^^^ ここ\n"));
+    }
+}
@@ -0,0 +1,96 @@
+use std::error::Error;
+use std::io;
+use std::num::{ParseFloatError, ParseIntError, TryFromIntError};
+use std::ops::Range;
+use std::str::{ParseBoolError, Utf8Error};
+use std::string::FromUtf8Error;
+use super::Diagnostic;
+
+impl Diagnostic<&'static str>
+{
+    /// Builds an error from a library error, labeled at the given span.
+    ///
+    /// The message is the one of the error and its sources
+    /// are appended as notes (`caused by: ...`).
+    /// ```
+    /// use codespan_preprocessed::reporting::Diagnostic;
+    ///
+    /// let text = "12a";
+    /// let error = text.parse::<u8>().unwrap_err();
+    /// let diag = Diagnostic::from_error(&error, 0..3);
+    /// assert_eq!(diag.message(), "invalid digit found in string");
+    /// assert_eq!(diag.primary_location(), Some(&(0..3)));
+    /// ```
+    pub fn from_error<E:Error + ?Sized>(err: &E, range: impl Into<Range<usize>>) -> Self
+    {
+        Self::from_error_without_span(err).with_primary_label(range, "")
+    }
+
+    /// Builds an error (without label) from a library error.
+    fn from_error_without_span<E:Error + ?Sized>(err: &E) -> Self
+    {
+        let mut diag = Diagnostic::error().with_message(err.to_string());
+        let mut source = err.source();
+        while let Some(cause) = source {
+            diag = diag.with_note(format!("caused by: {}", cause));
+            source = cause.source();
+        }
+        diag
+    }
+}
+
+/// Defines the conversion of a std error into a diagnostic with a code.
+macro_rules! from_std_error {
+    ($($error:ty => $code:literal),* $(,)?) => {
+        $(
+            impl From<$error> for Diagnostic<&'static str>
+            {
+                #[inline]
+                fn from(err: $error) -> Self
+                {
+                    Diagnostic::from_error_without_span(&err).with_code($code)
+                }
+            }
+        )*
+    };
+}
+
+from_std_error! {
+    io::Error => "io",
+    ParseIntError => "invalid-integer",
+    ParseFloatError => "invalid-float",
+    ParseBoolError => "invalid-bool",
+    TryFromIntError => "integer-overflow",
+    Utf8Error => "invalid-utf8",
+    FromUtf8Error => "invalid-utf8",
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use crate::reporting::Diagnostic;
+
+    #[test]
+    fn std_errors()
+    {
+        let diag = Diagnostic::from(io::Error::new(io::ErrorKind::NotFound, "a.c: no such file"));
+        assert_eq!(*diag.code(), "io");
+        assert_eq!(diag.message(), "a.c: no such file");
+
+        let diag: Diagnostic<_> = String::from_utf8(vec![0xff]).unwrap_err().into();
+        assert_eq!(*diag.code(), "invalid-utf8");
+        assert!(diag.primary_location().is_none());
+
+        // the sources of an error are noted
+        #[derive(Debug)]
+        struct Config(io::Error);
+        impl std::fmt::Display for Config {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { write!(f, "invalid configuration") }
+        }
+        impl std::error::Error for Config {
+            fn source(&self) -> Option<&(dyn std::error::Error + 'static)> { Some(&self.0) }
+        }
+        let diag = Diagnostic::from_error(&Config(io::Error::other("unreadable")), 0..1);
+        assert_eq!(diag.to_string(), "error: invalid configuration\n  = caused by: unreadable");
+    }
+}
@@ -0,0 +1,289 @@
+use std::fmt::Write as _;
+use std::ops::Range;
+use codespan_reporting::files::{Files, Location};
+use crate::{EasyLocation, FileSlice, PreprocessedFile};
+use crate::error::OverlapError;
+
+/// A fix-it suggestion: the replacement of a span of the preprocessed
+/// source (an insertion if the span is empty, a deletion if the
+/// replacement is empty).
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Suggestion {
+    pub(crate) range: Range<usize>,
+    pub(crate) replacement: String,
+    pub(crate) message: String
+}
+
+impl Suggestion {
+
+    #[inline]
+    pub fn new(range: impl Into<Range<usize>>, replacement: impl Into<String>, message: impl Into<String>) -> Self
+    {
+        Self { range: range.into(), replacement: replacement.into(), message: message.into() }
+    }
+
+    /// The replaced bytes of the preprocessed source.
+    #[inline]
+    pub fn range(&self) -> &Range<usize> { &self.range }
+
+    #[inline]
+    pub fn replacement(&self) -> &str { &self.replacement }
+
+    #[inline]
+    pub fn message(&self) -> &str { &self.message }
+}
+
+/// A suggestion resolved in its original file
+/// (see [`original_edits`]).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OriginalEdit {
+    /// The name of the original file
+    pub file: String,
+    /// The start of the replaced span (one-based)
+    pub start: Location,
+    /// The end of the replaced span (one-based, excluded)
+    pub end: Location,
+    pub replacement: String
+}
+
+/// Sorts the suggestions by span and checks that they do not overlap
+/// (the identical suggestions are applied once).
+fn sorted<'s>(suggestions: impl IntoIterator<Item=&'s Suggestion>) -> Result<Vec<&'s Suggestion>, OverlapError>
+{
+    let mut suggestions = suggestions.into_iter().collect::<Vec<_>>();
+    suggestions.sort_by_key(|s| (s.range.start, s.range.end));
+    suggestions.dedup_by(|a, b| a.range == b.range && a.replacement == b.replacement);
+    suggestions.windows(2)
+        .find(|pair| {
+            let (a, b) = (&pair[0].range, &pair[1].range);
+            // two insertions at the same place are ambiguous
+            b.start < a.end || (a.is_empty() && b.is_empty() && a.start == b.start)
+        })
+        .map_or(Ok(()), |pair| Err(OverlapError { first: pair[0].range.clone(), second: pair[1].range.clone() }))?;
+    Ok(suggestions)
+}
+
+/// Applies the given suggestions to the preprocessed source.
+///
+/// The suggestions are applied at once, so they should not overlap.
+///
+/// # Panics
+/// If a span of a suggestion is out of the source.
+///
+/// # Example
+/// ```
+/// use codespan_preprocessed::PreprocessedFile;
+/// use codespan_preprocessed::reporting::{apply_suggestions, Suggestion};
+///
+/// let file = PreprocessedFile::new("int a\nlong b;\n");
+/// let fixes = [Suggestion::new(5..5, ";", "add a semicolon"), Suggestion::new(6..10, "int", "use an int")];
+/// assert_eq!(apply_suggestions(&file, &fixes).unwrap(), "int a;\nint b;\n");
+/// assert!(apply_suggestions(&file, &[Suggestion::new(0..3, "", ""), Suggestion::new(2..5, "", "")]).is_err());
+/// ```
+pub fn apply_suggestions<'s,S:AsRef<str>>(file: &PreprocessedFile<S>, suggestions: impl IntoIterator<Item=&'s Suggestion>)
+    -> Result<String, OverlapError>
+{
+    let source = file.source();
+    let mut patched = String::with_capacity(source.len());
+    let mut last = 0;
+    for suggestion in sorted(suggestions)? {
+        patched.push_str(&source[last..suggestion.range.start]);
+        patched.push_str(&suggestion.replacement);
+        last = suggestion.range.end;
+    }
+    patched.push_str(&source[last..]);
+    Ok(patched)
+}
+
+/// Translates the given suggestions into edits of the original files
+/// (sorted as the preprocessed source).
+///
+/// The span of a suggestion is located in the file of its start.
+pub fn original_edits<'s,S:AsRef<str>>(file: &PreprocessedFile<S>, suggestions: impl IntoIterator<Item=&'s Suggestion>)
+    -> Result<Vec<OriginalEdit>, OverlapError>
+{
+    Ok(sorted(suggestions)?.into_iter()
+        .map(|suggestion| {
+            let id = file.file_id(suggestion.range.start);
+            let unknown = Location { line_number: 0, column_number: 0 };
+            OriginalEdit {
+                file: file.name(id).map(|name| name.to_string()).unwrap_or_default(),
+                start: file.location(id, suggestion.range.start).unwrap_or(unknown),
+                end: file.location(id, suggestion.range.end).unwrap_or(unknown),
+                replacement: suggestion.replacement.clone()
+            }
+        })
+        .collect())
+}
+
+/// A hunk of a unified diff: its first original line, its numbers
+/// of old and new lines and its body.
+type Hunk = (usize, usize, usize, String);
+
+/// Gets the unified diff of the original files which would be
+/// changed by the given suggestions (with the given number of lines
+/// of context around the changes).
+///
+/// The hunks are located with the original file names and line
+/// numbers (as given by the codemap) and grouped by file, in order of
+/// appearance in the preprocessed source. The context never crosses
+/// the limits of a slice.
+///
+/// # Panics
+/// If a span of a suggestion is out of the source.
+///
+/// # Example
+/// ```
+/// use codespan_preprocessed::PreprocessedFile;
+/// use codespan_preprocessed::reporting::{suggestions_diff, Suggestion};
+///
+/// let file = PreprocessedFile::new("#line 10 \"a.c\"\nint a\nint b;\n");
+/// let diff = suggestions_diff(&file, &[Suggestion::new(20..20, ";", "add a semicolon")], 1).unwrap();
+/// assert_eq!(diff, "--- a/a.c\n+++ b/a.c\n@@ -10,2 +10,2 @@\n-int a\n+int a;\n int b;\n");
+/// ```
+pub fn suggestions_diff<'s,S:AsRef<str>>(file: &PreprocessedFile<S>, suggestions: impl IntoIterator<Item=&'s Suggestion>,
+                                         context: usize) -> Result<String, OverlapError>
+{
+    let source = file.source();
+    // the changed lines (global indices) of each slice, with their suggestions
+    let mut changes: Vec<(&FileSlice, Range<usize>, Vec<&Suggestion>)> = vec![];
+    for suggestion in sorted(suggestions)? {
+        let id = file.file_id(suggestion.range.start);
+        let first = file.lines.line_within(suggestion.range.start, id.lines.clone());
+        // a removed EOL joins the next line
+        let last = file.lines.line_within(suggestion.range.end, id.lines.clone());
+        match changes.last_mut() {
+            Some((slice, lines, list)) if std::ptr::eq(*slice, id) && first < lines.end => {
+                lines.end = lines.end.max(last+1);
+                list.push(suggestion);
+            }
+            _ => changes.push((id, first..last+1, vec![suggestion]))
+        }
+    }
+
+    // the hunks, grouped by original file
+    let mut files: Vec<(String, Vec<Hunk>)> = vec![];
+    let mut changes = changes.into_iter().peekable();
+    while let Some(change) = changes.next() {
+        let slice = change.0;
+        let mut hunk = vec![change];
+        while let Some(next) = changes.next_if(|next| {
+            let previous = &hunk[hunk.len()-1];
+            std::ptr::eq(next.0, slice) && next.1.start - previous.1.end <= 2*context
+        }) {
+            hunk.push(next);
+        }
+        let start = hunk[0].1.start.saturating_sub(context).max(slice.lines.start);
+        let end = (hunk[hunk.len()-1].1.end + context).min(slice.lines.end);
+        let (mut body, mut old, mut new) = (String::new(), 0, 0);
+        let mut line = start;
+        let text = |line: usize| &source[file.lines.get(line).unwrap()];
+        for (_, lines, list) in &hunk {
+            for context in line..lines.start {
+                let _ = writeln!(body, " {}", text(context));
+            }
+            let bytes = file.lines.get(lines.start).unwrap().start .. file.lines.get(lines.end-1).unwrap().end;
+            let mut patched = String::new();
+            let mut last = bytes.start;
+            for suggestion in list {
+                patched.push_str(&source[last..suggestion.range.start]);
+                patched.push_str(&suggestion.replacement);
+                last = suggestion.range.end;
+            }
+            patched.push_str(&source[last.min(bytes.end)..bytes.end]);
+            for removed in source[bytes].split('\n') {
+                let _ = writeln!(body, "-{}", removed);
+                old += 1;
+            }
+            for added in patched.split('\n') {
+                let _ = writeln!(body, "+{}", added);
+                new += 1;
+            }
+            old += lines.start - line;
+            new += lines.start - line;
+            line = lines.end;
+        }
+        for context in line..end {
+            let _ = writeln!(body, " {}", text(context));
+        }
+        old += end - line;
+        new += end - line;
+
+        let name = file.name(slice).map(|name| name.to_string()).unwrap_or_default();
+        let first = (start as isize - slice.offset) as usize + 1;
+        match files.iter_mut().find(|(file, _)| *file == name) {
+            Some((_, hunks)) => hunks.push((first, old, new, body)),
+            None => files.push((name, vec![(first, old, new, body)]))
+        }
+    }
+
+    let mut diff = String::new();
+    for (name, mut hunks) in files {
+        hunks.sort_by_key(|hunk| hunk.0);
+        let _ = writeln!(diff, "--- a/{}\n+++ b/{}", name, name);
+        // the shift of the lines due to the previous hunks
+        let mut shift = 0isize;
+        for (first, old, new, body) in hunks {
+            let _ = writeln!(diff, "@@ -{},{} +{},{} @@", first, old, first as isize + shift, new);
+            diff.push_str(&body);
+            shift += new as isize - old as isize;
+        }
+    }
+    Ok(diff)
+}
+
+#[cfg(test)]
+mod tests {
+    use codespan_reporting::files::Location;
+    use crate::PreprocessedFile;
+    use crate::reporting::{Diagnostic, DiagnosticCollector, EasyReport};
+    use super::{original_edits, suggestions_diff, Suggestion};
+
+    #[test]
+    fn suggested_fixes()
+    {
+        let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a\n#line 9 \"b.h\"\nlong b;\n");
+        let collector = DiagnosticCollector::new();
+        collector.emit(Diagnostic::error().with_message("missing `;`").with_suggestion(19..19, ";", "add"));
+        collector.emit(Diagnostic::warning().with_message("long").with_suggestion(34..38, "int", "use"));
+        // the same fix suggested twice
+        collector.emit(Diagnostic::error().with_message("missing `;`").with_suggestion(19..19, ";", "add"));
+        assert_eq!(collector.apply_suggestions(&file).unwrap(),
+                   "#line 1 \"a.c\"\nint a;\n#line 9 \"b.h\"\nint b;\n");
+
+        let edits = original_edits(&file, &[Suggestion::new(34..38, "int", "use")]).unwrap();
+        assert_eq!(edits[0].file, "b.h");
+        assert_eq!(edits[0].start, Location { line_number: 9, column_number: 1 });
+        assert_eq!(edits[0].end, Location { line_number: 9, column_number: 5 });
+    }
+
+    #[test]
+    fn unified_diff()
+    {
+        let source = "#line 1 \"a.c\"\nint a\n#line 1 \"b.h\"\nlong b;\n#line 3 \"a.c\"\nint c\nint d;\nint e;\nint f;\n";
+        let file = PreprocessedFile::new(source);
+        let at = |text: &str| source.find(text).unwrap();
+        let fixes = [
+            Suggestion::new(at("int c")+5..at("int c")+5, ";", "add"),
+            Suggestion::new(at("long")..at("long")+4, "int", "use"),
+            Suggestion::new(at("int a")+5..at("int a")+5, ";", "add"),
+        ];
+        assert_eq!(suggestions_diff(&file, &fixes, 1).unwrap(), concat!(
+            "--- a/a.c\n+++ b/a.c\n",
+            "@@ -1,1 +1,1 @@\n-int a\n+int a;\n",
+            "@@ -3,2 +3,2 @@\n-int c\n+int c;\n int d;\n",
+            "--- a/b.h\n+++ b/b.h\n",
+            "@@ -1,1 +1,1 @@\n-long b;\n+int b;\n"));
+
+        // joining two lines shifts the following hunks
+        let fixes = [
+            Suggestion::new(at("int c")+5..at("int d"), " ", "join"),
+            Suggestion::new(at("int f")..at("int f")+3, "long", "use"),
+        ];
+        assert_eq!(suggestions_diff(&file, &fixes, 0).unwrap(), concat!(
+            "--- a/a.c\n+++ b/a.c\n",
+            "@@ -3,2 +3,1 @@\n-int c\n-int d;\n+int c int d;\n",
+            "@@ -6,1 +5,1 @@\n-int f;\n+long f;\n"));
+    }
+}
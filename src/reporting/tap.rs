@@ -0,0 +1,201 @@
+use std::fmt::{Display, Write as _};
+use std::io;
+use std::sync::{Mutex, PoisonError};
+use codespan_reporting::diagnostic::Severity;
+use crate::codemap::EasyLocation;
+use crate::json;
+use super::{degrade, Diagnostic, EasyReport, ReportError};
+use super::resolved::{level, Resolved};
+
+/// A reporting of the diagnostics as a Test Anything Protocol stream.
+///
+/// Each diagnostic is a test point written as soon as it is emitted:
+/// `not ok` for the errors and the bugs, `ok` for the others, followed
+/// by a YAML block with its severity, code and original location.
+/// The plan is written at the end (see [`TapReport::flush`]), once: the
+/// diagnostics emitted after it are rejected.
+/// ```
+/// use codespan_preprocessed::PreprocessedFile;
+/// use codespan_preprocessed::reporting::{Diagnostic, EasyReport, TapReport};
+///
+/// let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a;\n");
+/// let report = TapReport::new(&file, vec![]);
+/// report.emit(Diagnostic::error().with_code("E01").with_message("oops").with_primary_label(18..19, "here"));
+/// report.emit(Diagnostic::warning().with_message("unused"));
+/// report.flush().unwrap();
+/// assert_eq!(String::from_utf8(report.into_writer()).unwrap(), "\
+/// TAP version 13
+/// not ok 1 - oops
+///   ---
+///   severity: error
+///   code: \"E01\"
+///   file: \"a.c\"
+///   line: 1
+///   column: 5
+///   ...
+/// ok 2 - unused
+///   ---
+///   severity: warning
+///   ...
+/// 1..2
+/// ");
+/// ```
+pub struct TapReport<'a,L:EasyLocation<'a>,W:io::Write>
+{
+    source: &'a L,
+    /// The writer, the number of test points and whether the plan is written
+    writer: Mutex<(W, usize, bool)>
+}
+
+impl<'a,L:EasyLocation<'a>,W:io::Write> TapReport<'a,L,W>
+{
+    pub fn new(source: &'a L, writer: W) -> Self
+    {
+        Self { source, writer: Mutex::new((writer, 0, false)) }
+    }
+
+    /// Writes the plan (the number of test points written so far)
+    /// which ends the stream. The plan is written only once: the next
+    /// calls just flush the writer.
+    pub fn flush(&self) -> io::Result<()>
+    {
+        let mut writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        let (writer, count, planned) = &mut *writer;
+        if !*planned {
+            if *count == 0 {
+                writeln!(writer, "TAP version 13")?;
+            }
+            writeln!(writer, "1..{}", count)?;
+            *planned = true;
+        }
+        writer.flush()
+    }
+
+    /// Gets back the writer of this reporting.
+    #[inline]
+    pub fn into_writer(self) -> W
+    {
+        self.writer.into_inner().unwrap_or_else(PoisonError::into_inner).0
+    }
+}
+
+impl<'a,L:EasyLocation<'a>,W:io::Write> EasyReport for TapReport<'a,L,W>
+{
+    #[inline]
+    fn emit<E: Display>(&self, diag: impl Into<Diagnostic<E>>)
+    {
+        degrade(self.try_emit(diag))
+    }
+
+    fn try_emit<E: Display>(&self, diag: impl Into<Diagnostic<E>>) -> Result<(), ReportError>
+    {
+        let diag = Resolved::new(self.source, diag.into());
+        let mut writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        let (writer, count, planned) = &mut *writer;
+        if *planned {
+            return Err(io::Error::other("TAP plan already written").into());
+        }
+        if *count == 0 {
+            writeln!(writer, "TAP version 13")?;
+        }
+        *count += 1;
+        writer.write_all(test_point(*count, &diag).as_bytes())?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Writes a diagnostic as a test point with its YAML block.
+fn test_point(number: usize, diag: &Resolved) -> String
+{
+    let status = match diag.severity {
+        Severity::Bug | Severity::Error => "not ok",
+        _ => "ok"
+    };
+    // the description is a single line, where `#` starts a directive
+    let description = diag.message.lines().next().unwrap_or_default().replace('#', "\\#");
+    let mut out = format!("{} {} - {}\n  ---\n  severity: {}\n", status, number, description, level(diag.severity));
+    if !diag.code.is_empty() {
+        out.push_str("  code: ");
+        json::write_string(&mut out, &diag.code);
+        out.push('\n');
+    }
+    if diag.message.contains('\n') {
+        out.push_str("  message: ");
+        json::write_string(&mut out, &diag.message);
+        out.push('\n');
+    }
+    if let Some(label) = diag.labels.iter().find(|label| label.primary) {
+        out.push_str("  file: ");
+        json::write_string(&mut out, &label.file);
+        let _ = write!(out, "\n  line: {}\n  column: {}\n", label.start.line_number, label.start.column_number);
+    }
+    if !diag.notes.is_empty() {
+        out.push_str("  notes:\n");
+        for note in &diag.notes {
+            out.push_str("    - ");
+            json::write_string(&mut out, note);
+            out.push('\n');
+        }
+    }
+    if !diag.children.is_empty() {
+        out.push_str("  children:\n");
+        diag.children.iter().for_each(|child| write_child(&mut out, child));
+    }
+    out.push_str("  ...\n");
+    out
+}
+
+/// Writes a child diagnostic (and its own children) as an item of the YAML block.
+fn write_child(out: &mut String, child: &Resolved)
+{
+    let _ = write!(out, "    - severity: {}\n      message: ", level(child.severity));
+    json::write_string(out, &child.message);
+    out.push('\n');
+    if let Some(label) = child.labels.iter().find(|label| label.primary) {
+        out.push_str("      file: ");
+        json::write_string(out, &label.file);
+        let _ = write!(out, "\n      line: {}\n      column: {}\n", label.start.line_number, label.start.column_number);
+    }
+    child.children.iter().for_each(|child| write_child(out, child));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::PreprocessedFile;
+    use crate::reporting::{Diagnostic, EasyReport};
+    use super::TapReport;
+
+    #[test]
+    fn tap_stream()
+    {
+        let file = PreprocessedFile::new("int a;\n");
+        let report = TapReport::new(&file, vec![]);
+        report.flush().unwrap();
+        assert_eq!(String::from_utf8(report.into_writer()).unwrap(), "TAP version 13\n1..0\n");
+
+        // the plan ends the stream
+        let report = TapReport::new(&file, vec![]);
+        report.emit(Diagnostic::warning().with_message("first"));
+        report.flush().unwrap();
+        report.flush().unwrap();
+        assert!(report.try_emit(Diagnostic::error().with_message("late")).is_err());
+        let output = String::from_utf8(report.into_writer()).unwrap();
+        assert!(output.ends_with("  ...\n1..1\n"));
+        assert!(!output.contains("late"));
+
+        let report = TapReport::new(&file, vec![]);
+        report.emit(Diagnostic::bug().with_message("two # lines\nhere").with_note("a \"note\""));
+        let output = String::from_utf8(report.into_writer()).unwrap();
+        assert!(output.starts_with("TAP version 13\nnot ok 1 - two \\# lines\n"));
+        assert!(output.contains("  message: \"two # lines\\nhere\"\n"));
+        assert!(output.contains("  notes:\n    - \"a \\\"note\\\"\"\n"));
+
+        let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a;\n");
+        let report = TapReport::new(&file, vec![]);
+        report.emit(Diagnostic::error().with_message("parent")
+            .with_child(Diagnostic::note().with_message("child").with_primary_label(18..19, "")));
+        let output = String::from_utf8(report.into_writer()).unwrap();
+        assert!(output.contains("  children:\n    - severity: note\n      message: \"child\"\n      file: \"a.c\"\n      line: 1\n      column: 5\n  ...\n"));
+    }
+}
@@ -0,0 +1,69 @@
+use std::fmt::Display;
+use super::{Diagnostic, EasyReport, ReportError};
+
+/// A sink which forwards each diagnostic to two reportings
+/// (as the terminal and a log file).
+///
+/// The codes are converted into strings before forwarding.
+///
+/// # Example
+/// ```
+/// use codespan_preprocessed::PreprocessedFile;
+/// use codespan_preprocessed::reporting::{Diagnostic, EasyReport, EasyReporting, JsonReport};
+///
+/// let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a;\n");
+/// let terminal = EasyReporting::to_buffer(&file);
+/// let log = JsonReport::new(&file, vec![]);
+/// let report = terminal.tee(log);
+/// report.emit(Diagnostic::error().with_message("oops").with_primary_label(18..19, "here"));
+///
+/// let (terminal, log) = report.into_inner();
+/// assert!(terminal.rendered().starts_with("error: oops"));
+/// assert!(String::from_utf8(log.into_writer()).unwrap().contains(r#""message":"oops""#));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Tee<A,B> {
+    first: A,
+    second: B
+}
+
+impl<A:EasyReport,B:EasyReport> Tee<A,B>
+{
+    #[inline]
+    pub fn new(first: A, second: B) -> Self
+    {
+        Self { first, second }
+    }
+
+    #[inline]
+    pub fn first(&self) -> &A { &self.first }
+
+    #[inline]
+    pub fn second(&self) -> &B { &self.second }
+
+    /// Gets back the two reportings.
+    #[inline]
+    pub fn into_inner(self) -> (A, B) { (self.first, self.second) }
+}
+
+impl<A:EasyReport,B:EasyReport> EasyReport for Tee<A,B>
+{
+    fn emit<E:Display>(&self, diag: impl Into<Diagnostic<E>>)
+    {
+        let diag = diag.into();
+        let code = diag.code.to_string();
+        let diag = diag.with_code(code);
+        self.first.emit(diag.clone());
+        self.second.emit(diag);
+    }
+
+    /// The diagnostic is forwarded to both reportings, even if the first one fails.
+    fn try_emit<E:Display>(&self, diag: impl Into<Diagnostic<E>>) -> Result<(), ReportError>
+    {
+        let diag = diag.into();
+        let code = diag.code.to_string();
+        let diag = diag.with_code(code);
+        let first = self.first.try_emit(diag.clone());
+        first.and(self.second.try_emit(diag))
+    }
+}
@@ -0,0 +1,142 @@
+use std::fmt::{Display, Write as _};
+use std::ops::Range;
+use codespan_reporting::diagnostic::Severity;
+use super::Diagnostic;
+
+/// A definition of a diagnostic, with `{placeholders}` in its messages.
+///
+/// The templates are built in `const` context, so the diagnostics of a tool
+/// could be defined at one place and instantiated where they are emitted
+/// (see [`DiagnosticTemplate::instantiate`] and [`DiagnosticTemplate::at`]).
+/// The named placeholders are replaced by the given arguments, the unknown ones
+/// are kept as is, and `{{` and `}}` are the escaped braces.
+///
+/// # Example
+/// ```
+/// use codespan_preprocessed::PreprocessedFile;
+/// use codespan_preprocessed::reporting::{DiagnosticTemplate, EasyReport, EasyReporting};
+///
+/// const UNDECLARED: DiagnosticTemplate = DiagnosticTemplate::error("E001", "undeclared variable `{name}`")
+///     .with_label("not declared in this scope")
+///     .with_notes(&["declare `{name}` before its use"]);
+///
+/// let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a = b;\n");
+/// let report = EasyReporting::to_buffer(&file);
+/// let diag = UNDECLARED.at(22..23, &[("name", &"b")]);
+/// assert_eq!(diag.message(), "undeclared variable `b`");
+/// report.emit(diag);
+/// assert!(report.rendered().contains("= declare `b` before its use"));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DiagnosticTemplate {
+    code: &'static str,
+    severity: Severity,
+    message: &'static str,
+    label: &'static str,
+    notes: &'static [&'static str]
+}
+
+impl DiagnosticTemplate {
+
+    #[inline]
+    pub const fn new(code: &'static str, severity: Severity, message: &'static str) -> Self
+    {
+        Self { code, severity, message, label: "", notes: &[] }
+    }
+
+    #[inline]
+    pub const fn bug(code: &'static str, message: &'static str) -> Self { Self::new(code, Severity::Bug, message) }
+    #[inline]
+    pub const fn error(code: &'static str, message: &'static str) -> Self { Self::new(code, Severity::Error, message) }
+    #[inline]
+    pub const fn warning(code: &'static str, message: &'static str) -> Self { Self::new(code, Severity::Warning, message) }
+    #[inline]
+    pub const fn note(code: &'static str, message: &'static str) -> Self { Self::new(code, Severity::Note, message) }
+    #[inline]
+    pub const fn help(code: &'static str, message: &'static str) -> Self { Self::new(code, Severity::Help, message) }
+
+    /// Sets the message of the primary label (see [`DiagnosticTemplate::at`]).
+    #[inline]
+    pub const fn with_label(mut self, label: &'static str) -> Self
+    {
+        self.label = label;
+        self
+    }
+
+    /// Sets the fixed notes (which could also hold placeholders).
+    #[inline]
+    pub const fn with_notes(mut self, notes: &'static [&'static str]) -> Self
+    {
+        self.notes = notes;
+        self
+    }
+
+    #[inline]
+    pub fn code(&self) -> &'static str { self.code }
+
+    #[inline]
+    pub fn severity(&self) -> Severity { self.severity }
+
+    #[inline]
+    pub fn message(&self) -> &'static str { self.message }
+
+    /// Builds a diagnostic (without label) with the given arguments.
+    pub fn instantiate(&self, args: &[(&str, &dyn Display)]) -> Diagnostic<&'static str>
+    {
+        self.notes.iter()
+            .fold(Diagnostic::new(self.code, self.severity).with_message(substitute(self.message, args)),
+                  |diag, note| diag.with_note(substitute(note, args)))
+    }
+
+    /// Builds a diagnostic with the given arguments, labeled at the given span.
+    #[inline]
+    pub fn at(&self, range: impl Into<Range<usize>>, args: &[(&str, &dyn Display)]) -> Diagnostic<&'static str>
+    {
+        self.instantiate(args).with_primary_label(range, substitute(self.label, args))
+    }
+}
+
+/// Replaces the named placeholders of a template.
+fn substitute(template: &str, args: &[(&str, &dyn Display)]) -> String
+{
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(i) = rest.find(['{', '}']) {
+        out.push_str(&rest[..i]);
+        let tail = &rest[i..];
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            out.push_str(&tail[..1]);
+            rest = &tail[2..];
+            continue;
+        }
+        let placeholder = tail.find('}')
+            .filter(|_| tail.starts_with('{'))
+            .and_then(|end| args.iter().find(|(name, _)| *name == &tail[1..end]).map(|(_, value)| (end, value)));
+        match placeholder {
+            Some((end, value)) => {
+                let _ = write!(out, "{}", value);
+                rest = &tail[end+1..];
+            }
+            None => {
+                out.push_str(&tail[..1]);
+                rest = &tail[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::substitute;
+
+    #[test]
+    fn placeholders()
+    {
+        assert_eq!(substitute("expected {expected}, found {found}", &[("expected", &"int"), ("found", &42)]),
+                   "expected int, found 42");
+        assert_eq!(substitute("{{name}} is {name}, {unknown} stays", &[("name", &'x')]), "{name} is x, {unknown} stays");
+        assert_eq!(substitute("unbalanced { and }", &[]), "unbalanced { and }");
+    }
+}
@@ -0,0 +1,80 @@
+use codespan_reporting::diagnostic::Severity;
+use codespan_reporting::term::Styles;
+use codespan_reporting::term::termcolor::ColorSpec;
+
+/// A theme of the rendered diagnostics: the colors of their headers
+/// and primary labels by severity, or by code prefix (as to brand
+/// a family of diagnostics).
+///
+/// The style of a code prefix overrides the one of the severity, and
+/// the longest matching prefix wins (see [`EasyReporting::with_theme`](super::EasyReporting::with_theme)).
+///
+/// # Example
+/// ```
+/// use codespan_reporting::diagnostic::Severity;
+/// use codespan_reporting::term::Styles;
+/// use codespan_reporting::term::termcolor::{Color, ColorSpec};
+/// use codespan_preprocessed::reporting::Theme;
+///
+/// let purple = ColorSpec::new().set_fg(Some(Color::Magenta)).set_bold(true).clone();
+/// let theme = Theme::new()
+///     .severity(Severity::Note, ColorSpec::new().set_fg(Some(Color::Cyan)).clone())
+///     .code_prefix("DEP", purple.clone());
+/// let styles = theme.styles(&Styles::default(), Severity::Warning, "DEP001");
+/// assert_eq!(styles.header_warning, purple);
+/// assert_eq!(styles.primary_label_warning, purple);
+/// assert_eq!(theme.styles(&Styles::default(), Severity::Warning, "W001").header_warning, Styles::default().header_warning);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Theme {
+    severities: Vec<(Severity, ColorSpec)>,
+    prefixes: Vec<(String, ColorSpec)>
+}
+
+impl Theme {
+
+    #[inline]
+    pub fn new() -> Self { Self::default() }
+
+    /// Sets the style of the diagnostics of the given severity.
+    #[inline]
+    pub fn severity(mut self, severity: Severity, style: ColorSpec) -> Self
+    {
+        self.severities.retain(|(s, _)| *s != severity);
+        self.severities.push((severity, style));
+        self
+    }
+
+    /// Sets the style of the diagnostics whose code starts with the given prefix.
+    #[inline]
+    pub fn code_prefix(mut self, prefix: impl Into<String>, style: ColorSpec) -> Self
+    {
+        let prefix = prefix.into();
+        self.prefixes.retain(|(p, _)| *p != prefix);
+        self.prefixes.push((prefix, style));
+        self
+    }
+
+    /// Gets the styles of a diagnostic, based on the given ones.
+    pub fn styles(&self, base: &Styles, severity: Severity, code: &str) -> Styles
+    {
+        let mut styles = base.clone();
+        let style = self.prefixes.iter()
+            .filter(|(prefix, _)| code.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, style)| style)
+            .or_else(|| self.severities.iter().find(|(s, _)| *s == severity).map(|(_, style)| style));
+        if let Some(style) = style {
+            let (header, label) = match severity {
+                Severity::Bug => (&mut styles.header_bug, &mut styles.primary_label_bug),
+                Severity::Error => (&mut styles.header_error, &mut styles.primary_label_error),
+                Severity::Warning => (&mut styles.header_warning, &mut styles.primary_label_warning),
+                Severity::Note => (&mut styles.header_note, &mut styles.primary_label_note),
+                Severity::Help => (&mut styles.header_help, &mut styles.primary_label_help)
+            };
+            *header = style.clone();
+            *label = style.clone();
+        }
+        styles
+    }
+}
@@ -0,0 +1,48 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Gets the current time (there is no clock on `wasm32-unknown-unknown`,
+/// where `SystemTime::now` panics).
+#[inline]
+pub(crate) fn now() -> Option<SystemTime>
+{
+    #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+    { None }
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    { Some(SystemTime::now()) }
+}
+
+/// Formats a time as an RFC 3339 timestamp in UTC,
+/// with milliseconds (as `2024-03-01T12:30:05.042Z`).
+pub(crate) fn rfc3339(time: SystemTime) -> String
+{
+    let elapsed = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = elapsed.as_secs();
+    let (days, seconds) = (seconds / 86400, seconds % 86400);
+    // the civil date of a number of days since the epoch
+    // (see http://howardhinnant.github.io/date_algorithms.html)
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe/1460 + doe/36524 - doe/146096) / 365;
+    let doy = doe - (365*yoe + yoe/4 - yoe/100);
+    let mp = (5*doy + 2) / 153;
+    let day = doy - (153*mp + 2)/5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era*400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+            year, month, day, seconds / 3600, seconds / 60 % 60, seconds % 60, elapsed.subsec_millis())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+    use super::rfc3339;
+
+    #[test]
+    fn timestamps()
+    {
+        assert_eq!(rfc3339(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        assert_eq!(rfc3339(UNIX_EPOCH + Duration::from_millis(951_827_696_007)), "2000-02-29T12:34:56.007Z");
+        assert_eq!(rfc3339(UNIX_EPOCH + Duration::from_secs(1_735_689_599)), "2024-12-31T23:59:59.000Z");
+    }
+}
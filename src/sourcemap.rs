@@ -0,0 +1,197 @@
+use crate::{CodemapError, PreprocessedFile};
+use crate::codemap::FileSlice;
+use crate::columns::Columns;
+use crate::json;
+use crate::lines::LineTable;
+
+const BASE64: &[u8;64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Appends a value encoded as base64 VLQ.
+pub(crate) fn encode_vlq(out: &mut String, value: i64)
+{
+    let mut vlq = if value < 0 { ((-value as u64) << 1) | 1 } else { (value as u64) << 1 };
+    loop {
+        let mut digit = (vlq & 31) as usize;
+        vlq >>= 5;
+        if vlq > 0 { digit |= 32; }
+        out.push(BASE64[digit] as char);
+        if vlq == 0 { break; }
+    }
+}
+
+/// Decodes a segment of base64 VLQ values.
+pub(crate) fn decode_vlq(segment: &str) -> Option<Vec<i64>>
+{
+    let mut values = vec![];
+    let (mut value, mut shift) = (0_u64, 0);
+    for c in segment.bytes() {
+        let digit = BASE64.iter().position(|b| *b == c)? as u64;
+        value |= (digit & 31).checked_shl(shift)?;
+        if digit & 32 == 0 {
+            let magnitude = (value >> 1) as i64;
+            values.push(if value & 1 == 1 { -magnitude } else { magnitude });
+            (value, shift) = (0, 0);
+        } else {
+            shift += 5;
+        }
+    }
+    (shift == 0).then_some(values)
+}
+
+impl<S:AsRef<str>> PreprocessedFile<S>
+{
+    /// Builds the codemap from an external source map (revision 3)
+    /// instead of the directives of the contents.
+    ///
+    /// The first segment of each line of the contents gives its original
+    /// file and line (the following segments are ignored). The unmapped
+    /// lines are located in the contents itself (as unnamed slices).
+    /// ```
+    /// use codespan_reporting::files::Files;
+    /// use codespan_preprocessed::{EasyLocation, PreprocessedFile};
+    ///
+    /// let map = r#"{"version":3,"sources":["a.c"],"names":[],"mappings":"AAAA;AACA"}"#;
+    /// let file = PreprocessedFile::from_source_map("int a;\nint b;\n", map).unwrap();
+    /// let id = file.file_id(8);
+    /// assert_eq!(file.name(id).unwrap(), "a.c");
+    /// assert_eq!(file.location(id, 8).unwrap().line_number, 2);
+    /// ```
+    pub fn from_source_map(contents: S, source_map: &str) -> Result<Self, CodemapError>
+    {
+        let invalid = |msg: &str| CodemapError::InvalidSourceMap(msg.to_string());
+        let map = json::parse(source_map).map_err(|e| invalid(&e))?;
+        let sources = map.get("sources").and_then(json::Value::as_array)
+            .ok_or_else(|| invalid("missing sources"))?
+            .iter()
+            .map(|s| s.as_str().unwrap_or_default().to_string())
+            .collect::<Vec<_>>();
+        let mappings = map.get("mappings").and_then(json::Value::as_str)
+            .ok_or_else(|| invalid("missing mappings"))?;
+
+        let lines = LineTable::new(contents.as_ref());
+        // location of each line: (source, original line, column adjustment)
+        let (mut source, mut line, mut column) = (0_i64, 0_i64, 0_i64);
+        let mut locations = Vec::with_capacity(lines.len());
+        for segments in mappings.split(';').take(lines.len()) {
+            let mut first = None;
+            for segment in segments.split(',').filter(|s| !s.is_empty()) {
+                let fields = decode_vlq(segment).ok_or_else(|| invalid("invalid VLQ segment"))?;
+                if let [generated, dsource, dline, dcolumn, ..] = fields[..] {
+                    (source, line, column) = (source + dsource, line + dline, column + dcolumn);
+                    let index = usize::try_from(source).ok().filter(|s| *s < sources.len())
+                        .ok_or_else(|| invalid("invalid source index"))?;
+                    first.get_or_insert((index, line as isize, (column - generated) as isize));
+                }
+            }
+            locations.push(first);
+        }
+        locations.resize(lines.len(), None);
+
+        let mut file = PreprocessedFile { ids: vec![], lines, contents, origin: None, aliases: Default::default(), columns: Columns::default() };
+        let names = sources.into_iter().map(|s| file.intern(s)).collect::<Vec<_>>();
+        let mut ids: Vec<FileSlice> = vec![];
+        for (l, location) in locations.into_iter().enumerate() {
+            let (alias, offset, column) = match location {
+                Some((source, line, column)) => (Some(names[source]), l as isize - line, column),
+                None => (None, 0, 0)
+            };
+            match ids.last_mut() {
+                // the line continues the current slice
+                Some(id) if id.alias == alias && id.offset == offset && id.column == column => {
+                    id.lines.end = l+1;
+                }
+                _ => {
+                    let start = file.lines.get(l).unwrap().start;
+                    if let Some(id) = ids.last_mut() { id.bytes.end = start; }
+                    ids.push(FileSlice { name: 0..0, alias, remapped: false, qualified: None, bytes: start..start, lines: l..l+1, offset, column, parent: None, flags: 0 });
+                }
+            }
+        }
+        ids.last_mut().unwrap().bytes.end = file.lines.last().end;
+        file.ids = ids;
+        Ok(file)
+    }
+
+    /// Exports the codemap as a source map (revision 3).
+    ///
+    /// Each line of the preprocessed source (except the directives)
+    /// is mapped to its original file and line. The sources of the map
+    /// are the displayed names of the files.
+    /// ```
+    /// use codespan_preprocessed::PreprocessedFile;
+    ///
+    /// let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a;\nint b;\n");
+    /// assert_eq!(file.to_source_map(),
+    ///            r#"{"version":3,"sources":["a.c"],"names":[],"mappings":";AAAA;AACA"}"#);
+    /// ```
+    pub fn to_source_map(&self) -> String
+    {
+        let mut sources: Vec<&str> = vec![];
+        let mut mappings = String::new();
+        // the fields are encoded relatively to the previous segment
+        let (mut source, mut line, mut column) = (0_i64, 0_i64, 0_i64);
+        let mut slices = self.ids.iter().peekable();
+        for l in 0..self.lines.len() {
+            if l > 0 { mappings.push(';'); }
+            while slices.next_if(|id| id.lines.end <= l && !id.lines.contains(&l)).is_some() {}
+            let Some(id) = slices.peek().filter(|id| id.lines.contains(&l)) else {
+                continue /* directive line */
+            };
+            let name = self.display_name(id);
+            let index = match sources.iter().position(|s| *s == name) {
+                Some(index) => index,
+                None => { sources.push(name); sources.len()-1 }
+            } as i64;
+            let original_line = l as i64 - id.offset as i64;
+            let original_column = (id.column as i64).max(0);
+            encode_vlq(&mut mappings, 0);
+            encode_vlq(&mut mappings, index - source);
+            encode_vlq(&mut mappings, original_line - line);
+            encode_vlq(&mut mappings, original_column - column);
+            (source, line, column) = (index, original_line, original_column);
+        }
+
+        let mut map = String::from(r#"{"version":3,"sources":["#);
+        sources.iter().enumerate().for_each(|(i, s)| {
+            if i > 0 { map.push(','); }
+            json::write_string(&mut map, s);
+        });
+        map.push_str(r#"],"names":[],"mappings":"#);
+        map.push_str(&json::string(&mappings));
+        map.push('}');
+        map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use codespan_reporting::files::Files;
+    use crate::{EasyLocation, PreprocessedFile};
+
+    #[test]
+    fn source_map()
+    {
+        let file = PreprocessedFile::new("int a;\n#line 10 \"a.h\"\nint b;\nint c;\n#line 3 \"a.c\"\nint d;\n");
+        assert_eq!(file.to_source_map(),
+                   r#"{"version":3,"sources":["","a.h","a.c"],"names":[],"mappings":"AAAA;;ACSA;AACA;;ACRA"}"#);
+
+        let mut vlq = String::new();
+        [0, 1, -1, 15, 16, -16, 1000].iter().for_each(|v| super::encode_vlq(&mut vlq, *v));
+        assert_eq!(vlq, "ACDegBhBw+B");
+        assert_eq!(super::decode_vlq(&vlq), Some(vec![0, 1, -1, 15, 16, -16, 1000]));
+    }
+
+    #[test]
+    fn source_map_import()
+    {
+        let file = PreprocessedFile::new("int a;\n#line 10 \"a.h\"\nint b;\nint c;\n#line 3 \"a.c\"\nint d;\n");
+        let map = file.to_source_map();
+        let imported = PreprocessedFile::from_source_map(file.source(), &map).unwrap();
+        for byte in [0, 22, 29, 50] {
+            let (id, imported_id) = (file.file_id(byte), imported.file_id(byte));
+            assert_eq!(file.name(id).unwrap(), imported.name(imported_id).unwrap());
+            assert_eq!(file.location(id, byte).unwrap(), imported.location(imported_id, byte).unwrap());
+        }
+        assert!(PreprocessedFile::from_source_map("", "{}").is_err());
+    }
+}
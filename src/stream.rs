@@ -0,0 +1,207 @@
+use std::io;
+use std::io::Read;
+use crate::{CodemapError, PreprocessedFile, PreprocessedFileBuilder};
+
+/// Incremental ingestion of a preprocessed text.
+///
+/// The text is pushed by chunks (as it comes from a pipe) and the codemap
+/// of the already complete lines is available before the end of the input,
+/// so diagnostics could be reported early:
+/// ```
+/// use codespan_reporting::files::Files;
+/// use codespan_preprocessed::{EasyLocation, PreprocessedStream};
+///
+/// let mut stream = PreprocessedStream::new();
+/// stream.push(b"#line 7 \"a.c\"\nfirst li").unwrap();
+/// assert_eq!(stream.finalized(), 14);
+/// stream.push(b"ne\nsecond").unwrap();
+/// let file = stream.codemap().unwrap();
+/// assert_eq!(file.location(file.file_id(14), 14).unwrap().line_number, 7);
+/// let file = stream.finish().unwrap();
+/// assert_eq!(file.source(), "#line 7 \"a.c\"\nfirst line\nsecond");
+/// ```
+///
+/// The codemap is extended as the lines are completed: only the new lines
+/// are scanned and sliced (the previous slices are kept as is).
+pub struct PreprocessedStream {
+    builder: PreprocessedFileBuilder,
+    file: PreprocessedFile<String>,
+    pending: Vec<u8>,
+    error: Option<CodemapError>
+}
+
+impl Default for PreprocessedStream {
+    #[inline]
+    fn default() -> Self { Self::new() }
+}
+
+impl PreprocessedStream {
+
+    /// Starts an ingestion with the default builder.
+    #[inline]
+    pub fn new() -> Self { PreprocessedFileBuilder::new().stream() }
+
+    /// Pushes a chunk of the input.
+    ///
+    /// The incomplete last line is kept apart until its EOL arrives
+    /// (the chunks could so split a line or a UTF-8 sequence).
+    /// A leading UTF-8 BOM is stripped.
+    ///
+    /// # Errors
+    /// If a completed line is not valid UTF-8.
+    pub fn push(&mut self, chunk: &[u8]) -> Result<(), io::Error>
+    {
+        self.pending.extend_from_slice(chunk);
+        if let Some(eol) = memchr::memrchr(b'\n', &self.pending) {
+            let tail = self.pending.split_off(eol+1);
+            let lines = std::mem::replace(&mut self.pending, tail);
+            self.ingest(lines)?;
+        }
+        Ok(())
+    }
+
+    /// Appends some lines to the finalized prefix and extends its codemap.
+    ///
+    /// After an invalid directive (in strict mode), the codemap
+    /// is no longer extended and the error is kept.
+    fn ingest(&mut self, lines: Vec<u8>) -> Result<(), io::Error>
+    {
+        let lines = String::from_utf8(lines)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let lines = match self.file.contents.is_empty() {
+            true => lines.strip_prefix('\u{FEFF}').unwrap_or(&lines),
+            false => &lines
+        };
+        let from = self.file.contents.len();
+        self.file.contents.push_str(lines);
+        if self.error.is_none() {
+            self.error = self.builder.extend(&mut self.file, from).err();
+        }
+        Ok(())
+    }
+
+    /// Reads and pushes the next chunk of the reader.
+    ///
+    /// Returns the number of read bytes (0 at the end of the input).
+    pub fn read_from<R: Read>(&mut self, reader: &mut R) -> Result<usize, io::Error>
+    {
+        let mut buf = [0; 8192];
+        let n = reader.read(&mut buf)?;
+        self.push(&buf[..n])?;
+        Ok(n)
+    }
+
+    /// The end of the finalized prefix (only complete lines).
+    #[inline]
+    pub fn finalized(&self) -> usize { self.file.contents.len() }
+
+    /// Gets the codemap of the finalized prefix.
+    ///
+    /// The spans located before [`PreprocessedStream::finalized`]
+    /// are valid and keep the same location when more input arrives.
+    pub fn codemap(&self) -> Result<&PreprocessedFile<String>, CodemapError>
+    {
+        match &self.error {
+            Some(err) => Err(err.clone()),
+            None => Ok(&self.file)
+        }
+    }
+
+    /// Ends the ingestion and gets the codemap of the whole input.
+    ///
+    /// # Errors
+    /// If the last line is not valid UTF-8 or
+    /// if some directive is invalid (in strict mode).
+    pub fn finish(mut self) -> Result<PreprocessedFile<String>, io::Error>
+    {
+        // the last line is not terminated by an EOL
+        if !self.pending.is_empty() {
+            let last = std::mem::take(&mut self.pending);
+            self.ingest(last)?;
+        }
+        match self.error {
+            Some(err) => Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+            None => Ok(self.file)
+        }
+    }
+}
+
+impl PreprocessedFileBuilder {
+
+    /// Starts an incremental ingestion (see [`PreprocessedStream`]).
+    #[inline]
+    pub fn stream(self) -> PreprocessedStream
+    {
+        PreprocessedStream {
+            file: self.build(String::new()),
+            builder: self,
+            pending: vec![],
+            error: None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use codespan_reporting::files::Files;
+    use crate::{EasyLocation, PreprocessedFile, PreprocessedStream};
+
+    #[test]
+    fn incremental_ingestion()
+    {
+        let input = "\u{FEFF}#line 1 \"top\"\nabc\n# 1 \"inc.h\" 1\nd\u{e9}f\n# 2 \"top\" 2\nlast";
+        let whole = PreprocessedFile::new(&input[3..]);
+
+        let mut stream = PreprocessedStream::new();
+        let mut reader = input.as_bytes();
+        let mut chunk = [0; 5];
+        loop {
+            let n = std::io::Read::read(&mut reader, &mut chunk).unwrap();
+            if n == 0 { break; }
+            stream.push(&chunk[..n]).unwrap();
+            if stream.finalized() == 0 { continue; }
+            let file = stream.codemap().unwrap();
+            // the finalized prefix is located as in the whole input
+            for byte in file.ids.iter().filter(|id| !id.lines.is_empty()).map(|id| id.bytes.start) {
+                assert_eq!(file.location(file.file_id(byte), byte).unwrap(),
+                           whole.location(whole.file_id(byte), byte).unwrap());
+                assert_eq!(file.name(file.file_id(byte)).unwrap(), whole.name(whole.file_id(byte)).unwrap());
+            }
+        }
+        let file = stream.finish().unwrap();
+        assert_eq!(file.source(), whole.source());
+        assert_eq!(file.ids, whole.ids);
+    }
+
+    #[test]
+    fn extended_slices()
+    {
+        let input = "a\n# 1 \"/src/top.c\"\nabc\n# 1 \"/src/inc.h\" 1\n# 1 \"/src/in\\\\c2.h\" 1\nx\n# 2 \"/src/inc.h\" 2\nd\n# 3 \"/src/top.c\" 2\n";
+        let builder = || PreprocessedFile::builder()
+            .tolerant(true)
+            .path_remap("/src/", "")
+            .qualified_names(true);
+
+        // the codemap of each finalized prefix is the one built from it
+        let mut stream = builder().stream();
+        for (i, byte) in input.bytes().enumerate() {
+            stream.push(&[byte]).unwrap();
+            if byte != b'\n' { continue; }
+            let file = stream.codemap().unwrap();
+            let prefix = builder().build(input[..=i].to_string());
+            let names = |file: &PreprocessedFile<_>| file.ids.iter().map(|id| file.name(id).unwrap().to_string()).collect::<Vec<_>>();
+            assert_eq!(names(file), names(&prefix));
+            assert_eq!(file.ids.iter().map(|id| (&id.bytes, &id.lines, id.parent, id.flags)).collect::<Vec<_>>(),
+                       prefix.ids.iter().map(|id| (&id.bytes, &id.lines, id.parent, id.flags)).collect::<Vec<_>>());
+            assert_eq!(file.aliases.len(), prefix.aliases.len());
+        }
+        let file = stream.finish().unwrap();
+        assert_eq!(file.name(file.file_id(60)).unwrap(), "in\\c2.h (from inc.h:1, from top.c:2)");
+
+        // an invalid directive stops the ingestion
+        let mut stream = PreprocessedFile::builder().strict(true).stream();
+        stream.push(b"#line 1 \"a.c\"\n#line 99999999999\nint a;\n").unwrap();
+        assert!(stream.codemap().is_err());
+        assert!(stream.finish().is_err());
+    }
+}
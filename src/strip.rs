@@ -0,0 +1,137 @@
+use std::ops::Range;
+use crate::PreprocessedFile;
+
+/// Conversion of the spans between a stripped source (without
+/// its directives) and the original preprocessed one.
+///
+/// See [`PreprocessedFile::strip_directives`].
+#[derive(Clone, Debug, Default)]
+pub struct SpanRemapper {
+    /// The kept chunks: their start in the stripped source and in the original one
+    chunks: Vec<(usize, usize)>,
+    /// The lengths of the stripped and original sources
+    len: (usize, usize)
+}
+
+impl SpanRemapper {
+
+    /// Converts a span of the stripped source into a span of the original one
+    /// (which could then be reported against the codemap).
+    ///
+    /// A span covering several chunks covers also the directives between them.
+    pub fn to_original(&self, span: Range<usize>) -> Range<usize>
+    {
+        // the start is located in the chunk it starts
+        // but the end is located in the chunk it ends
+        let start = self.chunks.partition_point(|(stripped, _)| *stripped <= span.start).saturating_sub(1);
+        let end = self.chunks.partition_point(|(stripped, _)| *stripped < span.end).saturating_sub(1);
+        let origin = |(stripped, original): (usize, usize), pos: usize| original + pos - stripped;
+        match self.chunks.is_empty() {
+            true => span,
+            false => origin(self.chunks[start], span.start) .. origin(self.chunks[end], span.end.max(span.start))
+        }
+    }
+
+    /// Converts a span of the original source into a span of the stripped one.
+    ///
+    /// Returns `None` if the span starts or ends inside a directive.
+    pub fn to_stripped(&self, span: Range<usize>) -> Option<Range<usize>>
+    {
+        if self.chunks.is_empty() { return Some(span); }
+        // the start is searched in the chunk it starts
+        // and the end in the chunk it ends (as for `to_original`)
+        let stripped = |pos: usize, is_end: bool| {
+            let i = self.chunks.partition_point(|(_, original)| *original < pos || (!is_end && *original == pos));
+            let (stripped, original) = self.chunks[i.checked_sub(1)?];
+            let len = self.chunks.get(i).map_or(self.len.0, |(next, _)| *next) - stripped;
+            (pos <= original + len).then_some(stripped + pos - original)
+        };
+        let start = stripped(span.start, false)?;
+        match span.is_empty() {
+            true => Some(start..start),
+            false => Some(start..stripped(span.end, true)?)
+        }
+    }
+}
+
+impl<S:AsRef<str>> PreprocessedFile<S>
+{
+    /// Gets the source without its directive lines (line directives,
+    /// GCC linemarkers and column directives), so a parser could run
+    /// on the clean text, and the remapper of its spans into the
+    /// original source (which are those to report against the codemap).
+    /// ```
+    /// use codespan_reporting::files::Files;
+    /// use codespan_preprocessed::{EasyLocation, PreprocessedFile};
+    ///
+    /// let file = PreprocessedFile::new("#line 1 \"a.c\"\nint a;\n#line 1 \"b.c\"\nint b;\n");
+    /// let (clean, remapper) = file.strip_directives();
+    /// assert_eq!(clean, "int a;\nint b;\n");
+    /// let span = remapper.to_original(11..12);
+    /// assert_eq!(file.name(file.file_id(span.start)).unwrap(), "b.c");
+    /// assert_eq!(remapper.to_stripped(span), Some(11..12));
+    /// ```
+    pub fn strip_directives(&self) -> (String, SpanRemapper)
+    {
+        let source = self.source();
+        // the directives are the lines between the slices
+        let mut directives = vec![];
+        let mut line = 0;
+        for id in &self.ids {
+            directives.extend(line..id.lines.start);
+            line = id.lines.end;
+        }
+        let mut stripped = String::with_capacity(source.len());
+        let mut chunks = vec![];
+        let mut start = 0;
+        for directive in directives {
+            let range = self.lines.get(directive).unwrap();
+            if start < range.start {
+                chunks.push((stripped.len(), start));
+                stripped.push_str(&source[start..range.start]);
+            }
+            // the EOL of the directive is stripped too
+            start = self.lines.get(directive+1).map_or(source.len(), |next| next.start);
+        }
+        if start < source.len() || chunks.is_empty() {
+            chunks.push((stripped.len(), start));
+            stripped.push_str(&source[start..]);
+        }
+        let len = (stripped.len(), source.len());
+        (stripped, SpanRemapper { chunks, len })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::PreprocessedFile;
+
+    #[test]
+    fn stripped_spans()
+    {
+        let file = PreprocessedFile::builder()
+            .column_directive("#column")
+            .build("int x;\n# 1 \"a.c\" 1\n#column 2\nint a;\n#line 1 \"b.c\"");
+        let (clean, remapper) = file.strip_directives();
+        assert_eq!(clean, "int x;\nint a;\n");
+        assert_eq!(remapper.to_original(0..3), 0..3);
+        assert_eq!(remapper.to_original(7..10), 29..32);
+        // the end of a chunk stays in this chunk
+        assert_eq!(remapper.to_original(4..7), 4..7);
+        assert_eq!(remapper.to_original(14..14), 36..36);
+        // a span over several chunks covers the directives
+        assert_eq!(remapper.to_original(4..10), 4..32);
+
+        assert_eq!(remapper.to_stripped(29..32), Some(7..10));
+        assert_eq!(remapper.to_stripped(4..7), Some(4..7));
+        assert_eq!(remapper.to_stripped(4..32), Some(4..10));
+        assert_eq!(remapper.to_stripped(8..12), None);
+        assert_eq!(remapper.to_stripped(2..12), None);
+
+        let file = PreprocessedFile::new("no directive\n");
+        let (clean, remapper) = file.strip_directives();
+        assert_eq!(clean, "no directive\n");
+        assert_eq!(remapper.to_original(3..5), 3..5);
+        assert_eq!(remapper.to_stripped(3..5), Some(3..5));
+    }
+}
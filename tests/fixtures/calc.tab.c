@@ -0,0 +1,17 @@
+/* A Bison parser, made by GNU Bison 3.8.2.  */
+
+#line 1 "C:\\build\\calc\\calc.y"
+
+#include <stdio.h>
+int yylex (void);
+  
+#line 74 "calc.tab.c"
+
+# ifndef YY_CAST
+#  define YY_CAST(Type, Val) ((Type) (Val))
+# endif
+  case 2: /* exp: NUM  */
+#line 25 "C:\\build\\calc\\calc.y"
+                 { (yyval.val) = (yyvsp[0].val); }
+#line 1132 "calc.tab.c"
+    break;
@@ -0,0 +1,17 @@
+#line 1 "lex.yy.c"
+
+#line 3 "lex.yy.c"
+
+#define  YY_INT_ALIGNED short int
+	
+#line 1 "scan.l"
+%{
+#include "calc.tab.h"
+%}
+#line 480 "lex.yy.c"
+YY_DECL
+{
+#line 8 "scan.l"
+[0-9]+    { yylval.val = atoi(yytext); return NUM; }
+#line 702 "lex.yy.c"
+	YY_BREAK
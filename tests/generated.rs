@@ -0,0 +1,37 @@
+//! Codemaps of bison and flex outputs (with tolerant parsing).
+use codespan_reporting::files::Files;
+use codespan_preprocessed::{EasyLocation, PreprocessedFile};
+
+fn locate<S:AsRef<str>>(file: &PreprocessedFile<S>, pattern: &str) -> (String, usize, usize)
+{
+    let byte = file.source().find(pattern).expect("pattern not found");
+    let id = file.file_id(byte);
+    let location = file.location(id, byte).unwrap();
+    (file.name(id).unwrap().to_string(), location.line_number, location.column_number)
+}
+
+#[test]
+fn bison_output()
+{
+    let file = PreprocessedFile::builder()
+        .tolerant(true)
+        .build(include_str!("fixtures/calc.tab.c"));
+
+    assert_eq!(locate(&file, "#include"), ("C:\\build\\calc\\calc.y".to_string(), 2, 1));
+    assert_eq!(locate(&file, "(yyval.val)"), ("C:\\build\\calc\\calc.y".to_string(), 25, 20));
+    assert_eq!(locate(&file, "break"), ("calc.tab.c".to_string(), 1132, 5));
+    assert_eq!(locate(&file, "# ifndef"), ("calc.tab.c".to_string(), 75, 1));
+}
+
+#[test]
+fn flex_output()
+{
+    let file = PreprocessedFile::builder()
+        .tolerant(true)
+        .build(include_str!("fixtures/lex.yy.c"));
+
+    assert_eq!(locate(&file, "#define"), ("lex.yy.c".to_string(), 4, 1));
+    assert_eq!(locate(&file, "%{"), ("scan.l".to_string(), 1, 1));
+    assert_eq!(locate(&file, "YY_DECL"), ("lex.yy.c".to_string(), 480, 1));
+    assert_eq!(locate(&file, "atoi"), ("scan.l".to_string(), 8, 26));
+}